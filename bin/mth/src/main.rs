@@ -0,0 +1,438 @@
+//! Command line interface to the compiler, for scripts and machines without a GPU
+
+use std::{
+    fs,
+    io::{self, Read as _},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::{Parser, Subcommand};
+use code_generator::CompiledModule;
+use mth_ast::{Module, TopLevel};
+use mth_common::{
+    PLOT_TYPE_EQUATION, PLOT_TYPE_FN_GRAPH, PLOT_TYPE_INVERSE, PLOT_TYPE_PARAMETRIC,
+    PLOT_TYPE_POLAR, ops::Instruction, plot_desc::PlotDesc,
+};
+use mth_diagnostics::Diagnostic;
+use mth_render::Viewport;
+
+/// Exit code for programs with errors and failed evaluations
+const EXIT_ERROR: u8 = 1;
+
+/// Exit code for invalid arguments and unreadable files, like clap uses for usage errors
+const EXIT_USAGE: u8 = 2;
+
+#[derive(Parser)]
+#[command(
+    name = "mth",
+    version,
+    about = "Check, compile and evaluate mathlang programs"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Parse and check a program, and report its errors
+    Check {
+        /// Source file, `-` for stdin
+        file: PathBuf,
+    },
+
+    /// Print the syntax tree of a program
+    Ast {
+        /// Source file, `-` for stdin
+        file: PathBuf,
+    },
+
+    /// Print the compiled instructions of every plot of a program
+    Compile {
+        /// Source file, `-` for stdin
+        file: PathBuf,
+    },
+
+    /// Evaluate a definition of a program for the given inputs, bound to its parameters in
+    /// order
+    Eval {
+        /// Source file, `-` for stdin
+        file: PathBuf,
+
+        /// Name of the definition
+        name: String,
+
+        #[arg(allow_negative_numbers = true)]
+        inputs: Vec<f32>,
+    },
+
+    /// Render the plots of a program into a PNG, SVG or GIF image, depending on the extension
+    /// of the output file
+    Render {
+        /// Source file, `-` for stdin
+        file: PathBuf,
+
+        /// Image to write, `.png`, `.svg` or `.gif`
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Graph coordinates at the center of the image [default: the center of the `view`
+        /// of the program, or 0 0]
+        #[arg(long, num_args = 2, value_names = ["X", "Y"], allow_negative_numbers = true)]
+        center: Option<Vec<f32>>,
+
+        /// Zoom level of both axes like in the editor, each step doubles the scale [default:
+        /// the `view` of the program, or 1]
+        #[arg(long, allow_negative_numbers = true)]
+        zoom: Option<f64>,
+
+        /// Zoom level of the x axis, overrides `--zoom`
+        #[arg(long, allow_negative_numbers = true)]
+        zoom_x: Option<f64>,
+
+        /// Zoom level of the y axis, overrides `--zoom`
+        #[arg(long, allow_negative_numbers = true)]
+        zoom_y: Option<f64>,
+
+        /// Size of the image in pixels
+        #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_size, default_value = "800x600")]
+        size: (u32, u32),
+
+        /// Value of a slider of the program [default: the middle of its range]
+        #[arg(long = "set", value_name = "NAME=VALUE", value_parser = parse_assignment)]
+        set: Vec<(String, f32)>,
+
+        /// Value of `t` in seconds, of the first frame of an animation
+        #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+        time: f32,
+
+        /// Number of frames of an animation. A GIF holds all of them, PNG and SVG frames are
+        /// written to numbered files like `graph_0000.png`
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+        frames: u32,
+
+        /// Frames per second of an animation
+        #[arg(long, default_value_t = 30.0, value_parser = parse_fps)]
+        fps: f32,
+    },
+}
+
+impl Command {
+    fn file(&self) -> &Path {
+        match self {
+            Command::Check { file }
+            | Command::Ast { file }
+            | Command::Compile { file }
+            | Command::Eval { file, .. }
+            | Command::Render { file, .. } => file,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let path = cli.command.file();
+    let src = match read_source(path) {
+        Ok(src) => src,
+        Err(e) => {
+            eprintln!("error: couldn't read {}: {e}", path.display());
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+
+    let result = match &cli.command {
+        Command::Check { .. } => compile(&src).map(|_| ()),
+        Command::Ast { .. } => ast(&src),
+        Command::Compile { .. } => compile(&src).map(|compiled| {
+            print!(
+                "{}",
+                format_plots(&compiled.instructions, &compiled.plot_descs)
+            );
+            if compiled.saved_instructions > 0 {
+                println!(
+                    "optimization saved {} instruction{}",
+                    compiled.saved_instructions,
+                    if compiled.saved_instructions == 1 {
+                        ""
+                    } else {
+                        "s"
+                    }
+                );
+            }
+        }),
+        Command::Eval { name, inputs, .. } => eval(&src, name, inputs),
+        Command::Render {
+            output,
+            center,
+            zoom,
+            zoom_x,
+            zoom_y,
+            size: (width, height),
+            set,
+            time,
+            frames,
+            fps,
+            ..
+        } => {
+            match compile(&src) {
+                Ok(compiled) => {
+                    // Options override the view of the program
+                    let mut viewport = match compiled.view {
+                        Some([x, y]) => Viewport::showing(x, y, *width, *height),
+                        None => Viewport {
+                            width: *width,
+                            height: *height,
+                            ..Viewport::default()
+                        },
+                    };
+                    if let Some(center) = center {
+                        viewport.center = [center[0], center[1]];
+                    }
+                    if let Some(zoom) = zoom {
+                        viewport.zoom = [*zoom; 2];
+                    }
+                    viewport.zoom = [
+                        zoom_x.unwrap_or(viewport.zoom[0]),
+                        zoom_y.unwrap_or(viewport.zoom[1]),
+                    ];
+                    let mut params: Vec<f32> =
+                        compiled.sliders.iter().map(|slider| slider.value).collect();
+                    for (name, value) in set {
+                        let slot = compiled
+                            .sliders
+                            .iter()
+                            .position(|slider| slider.name == *name);
+                        let Some(slot) = slot else {
+                            eprintln!("error: the program has no slider named `{name}`");
+                            return ExitCode::from(EXIT_USAGE);
+                        };
+                        params[slot] = *value;
+                    }
+                    let frames = Frames {
+                        start: *time,
+                        count: *frames,
+                        fps: *fps,
+                    };
+                    let result = render(
+                        &compiled.instructions,
+                        &params,
+                        &compiled.plot_descs,
+                        &viewport,
+                        &frames,
+                        output,
+                    );
+                    if let Err(e) = result {
+                        eprintln!("error: couldn't write {}: {e}", output.display());
+                        return ExitCode::from(EXIT_USAGE);
+                    }
+                    Ok(())
+                }
+                Err(diagnostics) => Err(diagnostics),
+            }
+        }
+    };
+
+    let diagnostics = match result {
+        Ok(()) => return ExitCode::SUCCESS,
+        Err(diagnostics) => diagnostics,
+    };
+    let filename = path.display().to_string();
+    for diagnostic in &diagnostics {
+        eprintln!("{}", diagnostic.render(&src, Some(&filename)));
+    }
+    if diagnostics.iter().any(Diagnostic::is_error) {
+        ExitCode::from(EXIT_ERROR)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn read_source(path: &Path) -> io::Result<String> {
+    if path == Path::new("-") {
+        let mut src = String::new();
+        io::stdin().read_to_string(&mut src)?;
+        return Ok(src);
+    }
+    fs::read_to_string(path)
+}
+
+fn parse(src: &str) -> Result<Module<'_>, Vec<Diagnostic>> {
+    let (module, diagnostics) = mth_parser::parse_program(src);
+    if diagnostics.is_empty() {
+        Ok(module)
+    } else {
+        Err(diagnostics)
+    }
+}
+
+/// Compile `src` like the editor does, reporting the errors of the statements that could be
+/// parsed along with the syntax errors
+fn compile(src: &str) -> Result<CompiledModule, Vec<Diagnostic>> {
+    let (module, mut diagnostics) = mth_parser::parse_program(src);
+
+    match code_generator::compile_module(&module) {
+        Ok(compiled) if diagnostics.is_empty() => return Ok(compiled),
+        Ok(_) => {}
+        Err(e) => diagnostics.push(e),
+    }
+    Err(diagnostics)
+}
+
+fn ast(src: &str) -> Result<(), Vec<Diagnostic>> {
+    let (module, diagnostics) = mth_parser::parse_program(src);
+    println!("{module:#?}");
+    if diagnostics.is_empty() {
+        Ok(())
+    } else {
+        Err(diagnostics)
+    }
+}
+
+fn eval(src: &str, name: &str, inputs: &[f32]) -> Result<(), Vec<Diagnostic>> {
+    let module = parse(src)?;
+    let instructions = code_generator::compile_function(&module, name).map_err(|e| vec![e])?;
+
+    let params = module
+        .top_level
+        .iter()
+        .find_map(|tl| match tl {
+            TopLevel::Function(f) if f.name == name => Some(f.params.len()),
+            _ => None,
+        })
+        .expect("compiled, so it is defined");
+    if inputs.len() != params {
+        return Err(vec![Diagnostic::error(format!(
+            "`{name}` takes {params} input{}, got {}",
+            if params == 1 { "" } else { "s" },
+            inputs.len()
+        ))]);
+    }
+
+    let [x, y] = [0, 1].map(|i| inputs.get(i).copied().unwrap_or_default());
+    match mth_interpreter::eval(&instructions, x, y) {
+        Ok(value) => {
+            println!("{value}");
+            Ok(())
+        }
+        Err(e) => Err(vec![Diagnostic::error(format!(
+            "Evaluation of `{name}` failed: {e}"
+        ))]),
+    }
+}
+
+/// The times of the frames of an animation
+struct Frames {
+    /// Time of the first frame in seconds
+    start: f32,
+    count: u32,
+    fps: f32,
+}
+
+impl Frames {
+    fn times(&self) -> impl Iterator<Item = f32> {
+        (0..self.count).map(|i| self.start + i as f32 / self.fps)
+    }
+}
+
+/// Write the plots to `output` in the format of its extension, for every frame with `params`
+/// bound to the sliders of the program
+fn render(
+    instructions: &[Instruction],
+    params: &[f32],
+    plot_descs: &[PlotDesc],
+    viewport: &Viewport,
+    frames: &Frames,
+    output: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let at = |time| mth_interpreter::bind_parameters(instructions, params, time);
+    let ext = output.extension().and_then(|ext| ext.to_str());
+    match ext {
+        Some("gif") => {
+            let images: Vec<_> = frames
+                .times()
+                .map(|time| mth_render::render(&at(time), plot_descs, viewport))
+                .collect();
+            let w = io::BufWriter::new(fs::File::create(output)?);
+            mth_render::write_gif(&images, frames.fps, w)?;
+        }
+        Some(ext @ ("png" | "svg")) => {
+            for (i, time) in frames.times().enumerate() {
+                let path = match frames.count {
+                    1 => output.to_path_buf(),
+                    _ => frame_path(output, i),
+                };
+                let instructions = at(time);
+                if ext == "png" {
+                    let image = mth_render::render(&instructions, plot_descs, viewport);
+                    image.write_png(io::BufWriter::new(fs::File::create(path)?))?;
+                } else {
+                    fs::write(
+                        path,
+                        mth_render::render_svg(&instructions, plot_descs, viewport),
+                    )?;
+                }
+            }
+        }
+        _ => {
+            return Err("unknown image format, expected a `.png`, `.svg` or `.gif` file".into());
+        }
+    }
+    Ok(())
+}
+
+/// `output` with the number of the frame `i` appended to its name, `graph_0007.png`
+fn frame_path(output: &Path, i: usize) -> PathBuf {
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = output.extension().unwrap_or_default().to_string_lossy();
+    output.with_file_name(format!("{stem}_{i:04}.{ext}"))
+}
+
+/// Parse a number of frames per second
+fn parse_fps(s: &str) -> Result<f32, String> {
+    s.parse::<f32>()
+        .ok()
+        .filter(|fps| fps.is_finite() && *fps > 0.0)
+        .ok_or_else(|| format!("expected a positive number, got `{s}`"))
+}
+
+/// Parse an image size like `800x600`
+fn parse_size(s: &str) -> Result<(u32, u32), String> {
+    let parse = |n: &str| n.parse::<u32>().ok().filter(|n| *n > 0);
+    s.split_once('x')
+        .and_then(|(width, height)| Some((parse(width)?, parse(height)?)))
+        .ok_or_else(|| format!("expected WIDTHxHEIGHT, like 800x600, got `{s}`"))
+}
+
+/// Parse the value of a slider like `a=0.5`
+fn parse_assignment(s: &str) -> Result<(String, f32), String> {
+    s.split_once('=')
+        .and_then(|(name, value)| Some((name.trim().to_string(), value.trim().parse().ok()?)))
+        .filter(|(name, _)| !name.is_empty())
+        .ok_or_else(|| format!("expected NAME=VALUE, like a=0.5, got `{s}`"))
+}
+
+/// List the instructions of each plot, with their index in the instruction buffer
+fn format_plots(instructions: &[Instruction], plot_descs: &[PlotDesc]) -> String {
+    let mut out = String::new();
+    for (i, plot) in mth_interpreter::plots(plot_descs).enumerate() {
+        let [start, end] = plot.range;
+        let kind = match plot.type_id {
+            PLOT_TYPE_FN_GRAPH => "function graph".to_string(),
+            PLOT_TYPE_EQUATION => "equation".to_string(),
+            PLOT_TYPE_PARAMETRIC => format!("parametric curve from {start} to {end}"),
+            PLOT_TYPE_POLAR => format!("polar curve from {start} to {end}"),
+            PLOT_TYPE_INVERSE => "inverse function graph".to_string(),
+            _ => "unknown".to_string(),
+        };
+        out += &format!("plot {i}: {kind}, {} instructions\n", plot.len);
+        for (index, instruction) in instructions[plot.offset..plot.offset + plot.len]
+            .iter()
+            .enumerate()
+        {
+            out += &format!("{:>5}  {instruction}\n", plot.offset + index);
+        }
+    }
+    out
+}
@@ -0,0 +1,302 @@
+use std::{
+    io::Write as _,
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+};
+
+/// Write `src` to a file named after the test, since tests run in parallel
+fn source_file(test: &str, src: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("mth_cli_{test}.mth"));
+    std::fs::write(&path, src).unwrap();
+    path
+}
+
+fn mth(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_mth"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn check_valid_program() {
+    let file = source_file("check_valid", "f(x) = sin(x);\nplot(f);\n");
+    let output = mth(&["check", file.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(stderr(&output), "");
+}
+
+#[test]
+fn check_reports_all_errors() {
+    let file = source_file("check_errors", "f(x) = sin(x;\nplot(g);\n");
+    let output = mth(&["check", file.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+
+    let stderr = stderr(&output);
+    assert!(stderr.contains("error[E0001]: syntax error"), "{stderr}");
+    assert!(
+        stderr.contains("error[E0100]: Undefined name `g`"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains(&format!("--> {}:2:6", file.display())),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn check_missing_file() {
+    let output = mth(&["check", "does/not/exist.mth"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).starts_with("error: couldn't read does/not/exist.mth"));
+}
+
+#[test]
+fn ast_prints_module() {
+    let file = source_file("ast", "a = 1;\n");
+    let output = mth(&["ast", file.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = stdout(&output);
+    assert!(stdout.starts_with("Module {"), "{stdout}");
+    assert!(stdout.contains("name: \"a\""), "{stdout}");
+}
+
+#[test]
+fn compile_lists_instructions_per_plot() {
+    let file = source_file("compile", "f(x) = 2x;\nplot(f, x^2 + y^2 == 1);\n");
+    let output = mth(&["compile", file.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "\
+plot 0: function graph, 3 instructions
+    0  CONST 2
+    1  X
+    2  MUL
+plot 1: equation, 9 instructions
+    3  X
+    4  CONST 2
+    5  POW
+    6  Y
+    7  CONST 2
+    8  POW
+    9  ADD
+   10  CONST 1
+   11  EQ
+"
+    );
+}
+
+#[test]
+fn compile_lists_curves() {
+    let file = source_file(
+        "compile_curves",
+        "plot_parametric(t -> t, t -> 1, 0, 2);\nplot_inverse(s -> s);\n",
+    );
+    let output = mth(&["compile", file.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "\
+plot 0: parametric curve from 0 to 2, 2 instructions
+    0  X
+    1  CONST 1
+plot 1: inverse function graph, 1 instructions
+    2  Y
+"
+    );
+}
+
+#[test]
+fn compile_reports_saved_instructions() {
+    let file = source_file("compile_optimized", "plot(2 * 3 + x);\n");
+    let output = mth(&["compile", file.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "\
+plot 0: function graph, 3 instructions
+    0  CONST 6
+    1  X
+    2  ADD
+optimization saved 2 instructions
+"
+    );
+}
+
+#[test]
+fn eval_definition() {
+    let file = source_file("eval", "a = 3;\nf(x, y) = a x - y;\n");
+    let path = file.to_str().unwrap();
+
+    let output = mth(&["eval", path, "f", "2", "-1.5"]);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "7.5\n");
+
+    let output = mth(&["eval", path, "a"]);
+    assert_eq!(stdout(&output), "3\n");
+}
+
+#[test]
+fn eval_wrong_inputs() {
+    let file = source_file("eval_wrong_inputs", "f(x) = x;\n");
+    let path = file.to_str().unwrap();
+
+    let output = mth(&["eval", path, "f"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("`f` takes 1 input, got 0"));
+
+    let output = mth(&["eval", path, "g", "1"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("No definition named `g`"));
+}
+
+#[test]
+fn read_from_stdin() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_mth"))
+        .args(["eval", "-", "f", "4"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"f(x) = x / 2;")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(stdout(&output), "2\n");
+}
+
+#[test]
+fn render_png_and_svg() {
+    let file = source_file("render", "plot(sin(x), y < x);\n");
+    let path = file.to_str().unwrap();
+
+    for ext in ["png", "svg"] {
+        let output = std::env::temp_dir().join(format!("mth_cli_render.{ext}"));
+        let status = mth(&[
+            "render",
+            path,
+            "-o",
+            output.to_str().unwrap(),
+            "--center",
+            "1",
+            "-0.5",
+            "--zoom",
+            "-3",
+            "--size",
+            "64x48",
+        ]);
+        assert_eq!(status.status.code(), Some(0), "{}", stderr(&status));
+
+        let image = std::fs::read(&output).unwrap();
+        match ext {
+            "png" => assert!(image.starts_with(b"\x89PNG")),
+            _ => {
+                assert!(image.starts_with(
+                    br#"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="48""#
+                ))
+            }
+        }
+    }
+}
+
+#[test]
+fn render_view_of_the_program() {
+    let file = source_file("render_view", "view(0, 10, 0, 5);\nplot(x);\n");
+    let path = file.to_str().unwrap();
+    let output = std::env::temp_dir().join("mth_cli_render_view.svg");
+    let render = |options: &[&str]| {
+        let args = [&["render", path, "-o", output.to_str().unwrap()], options].concat();
+        let status = mth(&args);
+        assert_eq!(status.status.code(), Some(0), "{}", stderr(&status));
+        std::fs::read_to_string(&output).unwrap()
+    };
+
+    // The origin is in the bottom left corner
+    let svg = render(&["--size", "100x100"]);
+    assert!(
+        svg.contains(r#"<line x1="0.00" y1="0" x2="0.00" y2="100""#),
+        "{svg}"
+    );
+    assert!(
+        svg.contains(r#"<line x1="0" y1="100.00" x2="100" y2="100.00""#),
+        "{svg}"
+    );
+
+    // Options override it, the scales of the axes stay
+    let svg = render(&["--size", "100x100", "--center", "0", "0"]);
+    assert!(
+        svg.contains(r#"<line x1="50.00" y1="0" x2="50.00" y2="100""#),
+        "{svg}"
+    );
+    assert!(
+        svg.contains(r#"<line x1="0" y1="50.00" x2="100" y2="50.00""#),
+        "{svg}"
+    );
+}
+
+#[test]
+fn render_with_sliders() {
+    let file = source_file(
+        "render_sliders",
+        "slider(a, 0, 4, 0.5);\nf(x) = a x;\nplot(f);\n",
+    );
+    let path = file.to_str().unwrap();
+
+    // Eval uses the initial value, in the middle of the range
+    let output = mth(&["eval", path, "f", "3"]);
+    assert_eq!(stdout(&output), "6\n");
+
+    let output = std::env::temp_dir().join("mth_cli_render_sliders.png");
+    let output = output.to_str().unwrap();
+    let status = mth(&["render", path, "-o", output, "--set", "a=-1.5"]);
+    assert_eq!(status.status.code(), Some(0), "{}", stderr(&status));
+
+    let status = mth(&["render", path, "-o", output, "--set", "b=1"]);
+    assert_eq!(status.status.code(), Some(2));
+    assert!(stderr(&status).contains("the program has no slider named `b`"));
+}
+
+#[test]
+fn render_animation() {
+    let file = source_file("render_animation", "plot(sin(x - t));\n");
+    let path = file.to_str().unwrap();
+    let dir = std::env::temp_dir();
+
+    let gif = dir.join("mth_cli_render_animation.gif");
+    let args = ["--frames", "4", "--fps", "10", "--size", "32x24"];
+    let status = mth(&[&["render", path, "-o", gif.to_str().unwrap()], &args[..]].concat());
+    assert_eq!(status.status.code(), Some(0), "{}", stderr(&status));
+    assert!(std::fs::read(&gif).unwrap().starts_with(b"GIF89a"));
+
+    // One numbered file per frame, each at a later time
+    let png = dir.join("mth_cli_render_animation.png");
+    let status = mth(&[&["render", path, "-o", png.to_str().unwrap()], &args[..]].concat());
+    assert_eq!(status.status.code(), Some(0), "{}", stderr(&status));
+    let frames: Vec<Vec<u8>> = (0..4)
+        .map(|i| std::fs::read(dir.join(format!("mth_cli_render_animation_{i:04}.png"))).unwrap())
+        .collect();
+    assert_ne!(frames[0], frames[1]);
+
+    let status = mth(&["render", path, "-o", png.to_str().unwrap(), "--fps", "0"]);
+    assert_eq!(status.status.code(), Some(2));
+}
+
+#[test]
+fn render_unknown_format() {
+    let file = source_file("render_unknown", "plot(x);\n");
+    let output = mth(&["render", file.to_str().unwrap(), "-o", "graph.bmp"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("unknown image format"));
+}
@@ -1,37 +1,118 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+};
 
-use glam::{dvec2, DVec2};
+use glam::{DVec2, dvec2};
 use iced::{
-    mouse,
-    widget::{shader, Action},
-    Event, Rectangle,
+    Event, Point, Rectangle, advanced, keyboard, mouse,
+    time::Instant,
+    touch,
+    widget::{Action, shader},
 };
 
-use graph_canvas::{controls::Controls, FragmentShaderPrimitive, N_INSTRUCTIONS, N_PLOTS};
-use mth_common::{ops::Instruction, plot_desc::PlotDesc};
+use code_generator::Slider;
+use graph_canvas::{FragmentShaderPrimitive, controls::Controls};
+use mth_common::{MAX_PARAMS, ops::Instruction, plot_desc::PlotDesc};
 
-use crate::message::Message;
+use crate::{PAN_KEY_STEP, ZOOM_KEY_STEP, ZOOM_WHEEL_SCALE, message::Message};
 
 impl Default for Graph {
     fn default() -> Self {
         Self {
             controls: Controls::default(),
-            instructions: Arc::new(Mutex::new([Instruction::default(); N_INSTRUCTIONS])),
-            plot_desc: [PlotDesc::default(); N_PLOTS],
-            instructions_dirty: false,
+            instructions: Arc::new(Mutex::new(Vec::new())),
+            plot_desc: Arc::from([]),
+            plot_names: Arc::from([]),
+            sliders: Vec::new(),
+            params: [0.0; MAX_PARAMS],
+            time: 0.0,
+            playing: false,
+            speed: Speed::default(),
+            last_frame: None,
+            program_generation: 0,
+            size: DVec2::ZERO,
+            view: None,
         }
     }
 }
 
 pub struct Graph {
     pub controls: Controls,
-    pub instructions: Arc<Mutex<[Instruction; N_INSTRUCTIONS]>>,
-    pub plot_desc: [PlotDesc; N_PLOTS],
-    pub instructions_dirty: bool,
+    pub instructions: Arc<Mutex<Vec<Instruction>>>,
+    pub plot_desc: Arc<[PlotDesc]>,
+
+    /// Source of each plot, to label it under the cursor
+    pub plot_names: Arc<[String]>,
+
+    /// Sliders of the program, and their values in the order of the parameter slots. Moving
+    /// a slider only changes a uniform of the shader
+    pub sliders: Vec<Slider>,
+    pub params: [f32; MAX_PARAMS],
+
+    /// Value of `t` in seconds, which advances by `speed` while the animation plays
+    pub time: f64,
+    pub playing: bool,
+    pub speed: Speed,
+
+    /// When `time` last advanced, `None` until the first frame after it starts playing
+    pub last_frame: Option<Instant>,
+
+    /// Bumped whenever a new program is written to `instructions`. The shader uploads the
+    /// program once per generation
+    pub program_generation: u64,
+
+    /// Size of the widget in logical pixels, as last reported by [`Message::GraphResized`]
+    pub size: DVec2,
+
+    /// The x and y range of the `view` statement of the program
+    pub view: Option<[[f32; 2]; 2]>,
+}
+
+/// How fast `t` advances, in seconds per second
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Speed(pub f64);
+
+impl Speed {
+    pub const ALL: [Speed; 5] = [Speed(0.25), Speed(0.5), Speed(1.0), Speed(2.0), Speed(4.0)];
+}
+
+impl Default for Speed {
+    fn default() -> Self {
+        Speed(1.0)
+    }
+}
+
+impl fmt::Display for Speed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}×", self.0)
+    }
+}
+
+/// What the widget remembers between events
+#[derive(Debug, Default)]
+pub struct GraphState {
+    /// Position of the cursor while the graph is dragged
+    dragging: Option<DVec2>,
+
+    /// Positions of the fingers touching the graph, the first two pinch it
+    fingers: Vec<(touch::Finger, DVec2)>,
+
+    /// To tell double clicks apart
+    last_click: Option<advanced::mouse::Click>,
+
+    /// Keys move the graph after it was clicked, until something else is
+    focused: bool,
+
+    /// Ctrl zooms only the x axis, shift only the y axis
+    modifiers: keyboard::Modifiers,
+
+    /// Size of the widget last reported
+    size: DVec2,
 }
 
 impl shader::Program<Message> for Graph {
-    type State = (bool, DVec2);
+    type State = GraphState;
     type Primitive = FragmentShaderPrimitive;
 
     fn draw(
@@ -42,9 +123,11 @@ impl shader::Program<Message> for Graph {
     ) -> Self::Primitive {
         FragmentShaderPrimitive::new(
             self.controls,
+            self.params,
+            self.time as f32,
             Arc::clone(&self.instructions),
-            self.plot_desc,
-            self.instructions_dirty,
+            Arc::clone(&self.plot_desc),
+            self.program_generation,
         )
     }
 
@@ -55,45 +138,165 @@ impl shader::Program<Message> for Graph {
         bounds: Rectangle,
         cursor: mouse::Cursor,
     ) -> Option<Action<Message>> {
-        // Zooming
-        if let Event::Mouse(mouse::Event::WheelScrolled { delta }) = event {
-            if let Some(pos) = cursor.position_in(bounds) {
+        let center = dvec2(bounds.width.into(), bounds.height.into()) * 0.5;
+        let local = |pos: Point| dvec2((pos.x - bounds.x).into(), (pos.y - bounds.y).into());
+
+        match event {
+            // Zooming
+            Event::Mouse(mouse::Event::WheelScrolled { delta }) => {
+                let pos = cursor.position_in(bounds)?;
                 let pos = DVec2::new(pos.x.into(), pos.y.into());
-                let delta = match delta {
-                    mouse::ScrollDelta::Lines { y, .. } => y,
-                    mouse::ScrollDelta::Pixels { y, .. } => y,
+                let (x, y) = match *delta {
+                    mouse::ScrollDelta::Lines { x, y } => (x, y),
+                    mouse::ScrollDelta::Pixels { x, y } => (x, y),
                 };
-                return Some(
-                    Action::publish(Message::ZoomDelta(pos, bounds, *delta as f64)).and_capture(),
-                );
+                // Some platforms scroll sideways while shift is held
+                let delta = if y != 0.0 { y } else { x };
+                let delta = zoom_axes(state.modifiers, delta as f64 * ZOOM_WHEEL_SCALE);
+                return Some(Action::publish(Message::ZoomDelta(pos, bounds, delta)).and_capture());
             }
-        }
 
-        // Panning
-        match state {
-            (false, _) => match event {
-                Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
-                    if let Some(pos) = cursor.position_over(bounds) {
-                        *state = (true, dvec2(pos.x.into(), pos.y.into()));
-                        return Some(Action::capture());
-                    }
-                }
-                _ => {}
-            },
-            (true, prev_pos) => match event {
-                Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
-                    (*state).0 = false;
+            // Panning, and resetting the view on double clicks
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
+                let Some(pos) = cursor.position_over(bounds) else {
+                    state.focused = false;
+                    return None;
+                };
+                state.focused = true;
+
+                let click = advanced::mouse::Click::new(pos, mouse::Button::Left, state.last_click);
+                state.last_click = Some(click);
+                if click.kind() == advanced::mouse::click::Kind::Double {
+                    state.dragging = None;
+                    return Some(Action::publish(Message::ResetView).and_capture());
                 }
-                Event::Mouse(mouse::Event::CursorMoved { position }) => {
+
+                state.dragging = Some(dvec2(pos.x.into(), pos.y.into()));
+                return Some(Action::capture());
+            }
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                state.dragging = None;
+            }
+            Event::Mouse(mouse::Event::CursorMoved { position }) => {
+                if let Some(prev_pos) = state.dragging {
                     let pos = DVec2::new(position.x.into(), position.y.into());
-                    let delta = pos - *prev_pos;
-                    *state = (true, pos);
-                    return Some(Action::publish(Message::PanningDelta(delta)).and_capture());
+                    state.dragging = Some(pos);
+                    return Some(
+                        Action::publish(Message::PanningDelta(pos - prev_pos)).and_capture(),
+                    );
                 }
-                _ => {}
-            },
+            }
+
+            // Dragging with one finger, pinching with two
+            Event::Touch(touch::Event::FingerPressed { id, position })
+                if bounds.contains(*position) =>
+            {
+                state.fingers.push((*id, local(*position)));
+                state.focused = true;
+                return Some(Action::capture());
+            }
+            Event::Touch(touch::Event::FingerMoved { id, position }) => {
+                let prev = gesture(&state.fingers);
+                let finger = state.fingers.iter_mut().find(|(finger, _)| finger == id)?;
+                finger.1 = local(*position);
+
+                let message = match (prev?, gesture(&state.fingers)?) {
+                    (Gesture::Drag(prev_pos), Gesture::Drag(pos)) => {
+                        Message::PanningDelta(pos - prev_pos)
+                    }
+                    (Gesture::Pinch(prev_mid, prev_distance), Gesture::Pinch(mid, distance)) => {
+                        Message::PinchDelta(
+                            mid - prev_mid,
+                            mid,
+                            bounds,
+                            (distance / prev_distance).log2(),
+                        )
+                    }
+                    _ => return None,
+                };
+                return Some(Action::publish(message).and_capture());
+            }
+            Event::Touch(
+                touch::Event::FingerLifted { id, .. } | touch::Event::FingerLost { id, .. },
+            ) => {
+                state.fingers.retain(|(finger, _)| finger != id);
+            }
+
+            Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => {
+                state.modifiers = *modifiers;
+            }
+
+            // Keyboard navigation
+            Event::Keyboard(keyboard::Event::KeyPressed { key, .. }) if state.focused => {
+                use keyboard::key::Named;
+
+                // Keys move the view, the graph moves the other way
+                let message = match key.as_ref() {
+                    keyboard::Key::Named(Named::ArrowLeft) => {
+                        Message::PanningDelta(dvec2(PAN_KEY_STEP, 0.0))
+                    }
+                    keyboard::Key::Named(Named::ArrowRight) => {
+                        Message::PanningDelta(dvec2(-PAN_KEY_STEP, 0.0))
+                    }
+                    keyboard::Key::Named(Named::ArrowUp) => {
+                        Message::PanningDelta(dvec2(0.0, PAN_KEY_STEP))
+                    }
+                    keyboard::Key::Named(Named::ArrowDown) => {
+                        Message::PanningDelta(dvec2(0.0, -PAN_KEY_STEP))
+                    }
+                    keyboard::Key::Character("+" | "=") => {
+                        Message::ZoomDelta(center, bounds, DVec2::splat(ZOOM_KEY_STEP))
+                    }
+                    keyboard::Key::Character("-") => {
+                        Message::ZoomDelta(center, bounds, DVec2::splat(-ZOOM_KEY_STEP))
+                    }
+                    keyboard::Key::Character("0") => Message::ResetView,
+                    keyboard::Key::Character("f") => Message::FitView,
+                    _ => return None,
+                };
+                return Some(Action::publish(message).and_capture());
+            }
+            _ => {}
+        }
+
+        // The size is needed to fit the view from outside of the widget
+        let size = center * 2.0;
+        if state.size != size {
+            state.size = size;
+            return Some(Action::publish(Message::GraphResized(size)));
         }
 
         None
     }
 }
+
+/// Zoom levels along the x and y axis to zoom by `delta` with the wheel: only along x while
+/// ctrl is held, only along y with shift, along both otherwise. Keys zoom both, since `+`
+/// takes shift on many layouts
+fn zoom_axes(modifiers: keyboard::Modifiers, delta: f64) -> DVec2 {
+    if modifiers.control() {
+        dvec2(delta, 0.0)
+    } else if modifiers.shift() {
+        dvec2(0.0, delta)
+    } else {
+        DVec2::splat(delta)
+    }
+}
+
+/// What the fingers on the graph do
+enum Gesture {
+    /// One finger drags the graph, from its position
+    Drag(DVec2),
+
+    /// Two fingers zoom it around their midpoint, by their distance
+    Pinch(DVec2, f64),
+}
+
+/// The gesture of `fingers`, of which only the first two count
+fn gesture(fingers: &[(touch::Finger, DVec2)]) -> Option<Gesture> {
+    match fingers {
+        [(_, a), (_, b), ..] => Some(Gesture::Pinch((*a + *b) * 0.5, a.distance(*b).max(1.0))),
+        [(_, a)] => Some(Gesture::Drag(*a)),
+        [] => None,
+    }
+}
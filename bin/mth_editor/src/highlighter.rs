@@ -0,0 +1,71 @@
+use std::ops::Range;
+
+use iced::advanced::text::Highlighter;
+use mth_diagnostics::Span;
+
+/// Byte ranges to highlight, split into lines: `(line index, range within the line)`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ErrorRanges(Vec<(usize, Range<usize>)>);
+
+impl ErrorRanges {
+    pub fn new(src: &str, spans: impl IntoIterator<Item = Span>) -> Self {
+        let mut ranges = Vec::new();
+        for span in spans {
+            let mut line_start = 0;
+            for (line, text) in src.split('\n').enumerate() {
+                let line_end = line_start + text.len();
+                let start = span.start.max(line_start);
+                let end = span.end.min(line_end);
+                if start < end {
+                    ranges.push((line, start - line_start..end - line_start));
+                }
+                line_start = line_end + 1;
+            }
+        }
+        Self(ranges)
+    }
+}
+
+/// Marks the statements that failed to parse
+pub struct ErrorHighlighter {
+    ranges: ErrorRanges,
+    current_line: usize,
+}
+
+impl Highlighter for ErrorHighlighter {
+    type Settings = ErrorRanges;
+    type Highlight = ();
+    type Iterator<'a> = std::vec::IntoIter<(Range<usize>, ())>;
+
+    fn new(settings: &Self::Settings) -> Self {
+        Self {
+            ranges: settings.clone(),
+            current_line: 0,
+        }
+    }
+
+    fn update(&mut self, new_settings: &Self::Settings) {
+        self.ranges = new_settings.clone();
+        self.current_line = 0;
+    }
+
+    fn change_line(&mut self, line: usize) {
+        self.current_line = self.current_line.min(line);
+    }
+
+    fn highlight_line(&mut self, _line: &str) -> Self::Iterator<'_> {
+        let line = self.current_line;
+        self.current_line += 1;
+        self.ranges
+            .0
+            .iter()
+            .filter(|(l, _)| *l == line)
+            .map(|(_, range)| (range.clone(), ()))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    fn current_line(&self) -> usize {
+        self.current_line
+    }
+}
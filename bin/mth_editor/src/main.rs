@@ -1,10 +1,14 @@
 mod graph;
+mod highlighter;
 mod message;
 mod update;
 mod view;
 
 use graph::Graph;
-use iced::widget::text_editor;
+use highlighter::ErrorRanges;
+use iced::{Subscription, widget::text_editor, window};
+use message::Message;
+use mth_diagnostics::Diagnostic;
 
 pub const ZOOM_DEFAULT: f64 = 2.0;
 
@@ -14,8 +18,15 @@ pub const ZOOM_WHEEL_SCALE: f64 = 0.05;
 #[cfg(target_arch = "wasm32")]
 pub const ZOOM_WHEEL_SCALE: f64 = 8e-4;
 
+/// Zoom levels of a press of `+` or `-`
+pub const ZOOM_KEY_STEP: f64 = 0.25;
+
+/// Logical pixels the arrow keys move the view by
+pub const PAN_KEY_STEP: f64 = 50.0;
+
 fn main() -> iced::Result {
     iced::application(MainState::new, MainState::update, MainState::view)
+        .subscription(MainState::subscription)
         .theme(iced::Theme::SolarizedDark)
         .run()
 }
@@ -23,7 +34,8 @@ fn main() -> iced::Result {
 pub struct MainState {
     text: text_editor::Content,
     graph: Graph,
-    err_msg: Option<String>,
+    diagnostics: Vec<Diagnostic>,
+    error_ranges: ErrorRanges,
 }
 
 impl MainState {
@@ -31,9 +43,25 @@ impl MainState {
         let mut s = Self {
             text: text_editor::Content::with_text("f(x) = sin(x);\nplot(f);"),
             graph: Graph::default(),
-            err_msg: None,
+            diagnostics: Vec::new(),
+            error_ranges: ErrorRanges::default(),
         };
         s.on_text_change();
         s
     }
+
+    /// A message per frame while an animation plays
+    fn subscription(&self) -> Subscription<Message> {
+        if self.graph.playing {
+            window::frames().map(Message::Frame)
+        } else {
+            Subscription::none()
+        }
+    }
+}
+
+impl Default for MainState {
+    fn default() -> Self {
+        Self::new()
+    }
 }
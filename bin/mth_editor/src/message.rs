@@ -1,11 +1,36 @@
 use glam::DVec2;
-use iced::{Rectangle, widget};
+use graph_canvas::grid::AxisScale;
+use iced::{Rectangle, time::Instant, widget};
+use mth_diagnostics::Diagnostic;
+
+use crate::graph::Speed;
 
 #[derive(Debug, Clone)]
 pub enum Message {
     EditText(widget::text_editor::Action),
+    /// Drag the graph by logical pixels
     PanningDelta(DVec2),
-    ZoomDelta(DVec2, Rectangle, f64),
-    SetError(String),
+    /// Zoom in by zoom levels along the x and y axis, keeping the point at a position in the
+    /// graph in place
+    ZoomDelta(DVec2, Rectangle, DVec2),
+    /// Drag the graph and zoom around the new midpoint of the fingers pinching it
+    PinchDelta(DVec2, DVec2, Rectangle, f64),
+    /// Go back to the view of the program, or the one at startup
+    ResetView,
+    /// Move and zoom to show all plots
+    FitView,
+    GraphResized(DVec2),
+    /// The scale of the x (0) or y (1) axis
+    SetAxisScale(usize, AxisScale),
+    /// The value of the slider with a parameter slot
+    SetParameter(usize, f32),
+    /// Start or stop the animation
+    TogglePlayback,
+    /// Set `t` back to 0
+    RestartTime,
+    SetSpeed(Speed),
+    /// A frame is drawn at this moment while the animation plays
+    Frame(Instant),
+    SetErrors(Vec<Diagnostic>),
     ClearErrors,
 }
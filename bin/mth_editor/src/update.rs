@@ -1,7 +1,10 @@
-use graph_canvas::{N_INSTRUCTIONS, N_PLOTS};
-use mth_common::{ops::Instruction, plot_desc::PlotDesc};
+use code_generator::Slider;
+use glam::{DVec2, dvec2};
+use mth_common::{MAX_PARAMS, ops::Instruction, plot_desc::PlotDesc};
 
-use crate::{message::Message, MainState, ZOOM_WHEEL_SCALE};
+use mth_ast::TopLevel;
+
+use crate::{MainState, highlighter::ErrorRanges, message::Message};
 
 impl MainState {
     pub fn update(&mut self, msg: Message) {
@@ -10,16 +13,66 @@ impl MainState {
                 self.text.perform(action);
                 self.on_text_change();
             }
-            Message::PanningDelta(delta) => {
-                self.graph.controls.offset -= 2.0 * delta * self.graph.controls.pixel_ratio();
+            Message::PanningDelta(delta) => self.graph.controls.pan(delta),
+            Message::ZoomDelta(pos, bounds, delta) => {
+                let size = dvec2(bounds.width.into(), bounds.height.into());
+                self.graph.controls.zoom_at(pos, size, delta);
+            }
+            Message::PinchDelta(delta, pos, bounds, zoom) => {
+                let size = dvec2(bounds.width.into(), bounds.height.into());
+                self.graph.controls.pan(delta);
+                self.graph.controls.zoom_at(pos, size, DVec2::splat(zoom));
+            }
+            Message::ResetView => {
+                self.graph.controls.reset();
+                self.show_program_view();
+            }
+            Message::FitView => {
+                let instructions = mth_interpreter::bind_parameters(
+                    &self
+                        .graph
+                        .instructions
+                        .lock()
+                        .expect("Could not lock instructions mutex in MainState::update"),
+                    &self.graph.params,
+                    self.graph.time as f32,
+                );
+                self.graph
+                    .controls
+                    .fit(&instructions, &self.graph.plot_desc, self.graph.size);
+            }
+            Message::GraphResized(size) => {
+                // The view of the program can't be shown before the size is known
+                let first = self.graph.size == DVec2::ZERO;
+                self.graph.size = size;
+                if first {
+                    self.show_program_view();
+                }
+            }
+            Message::SetAxisScale(axis, scale) => self.graph.controls.axes[axis] = scale,
+            Message::SetParameter(slot, value) => {
+                if let Some(slider) = self.graph.sliders.get(slot) {
+                    self.graph.params[slot] = slider.snap(value);
+                }
+            }
+            Message::TogglePlayback => {
+                self.graph.playing = !self.graph.playing;
+                self.graph.last_frame = None;
             }
-            Message::ZoomDelta(_pos, _bounds, delta) => {
-                let delta = delta * ZOOM_WHEEL_SCALE;
-                let prev_zoom = self.graph.controls.zoom;
-                self.graph.controls.zoom = prev_zoom + delta;
+            Message::RestartTime => {
+                self.graph.time = 0.0;
+                self.graph.last_frame = None;
             }
-            Message::SetError(err_msg) => self.err_msg = Some(err_msg),
-            Message::ClearErrors => self.err_msg = None,
+            Message::SetSpeed(speed) => self.graph.speed = speed,
+            Message::Frame(now) => {
+                let graph = &mut self.graph;
+                if let Some(last) = graph.last_frame {
+                    graph.time += (now - last).as_secs_f64() * graph.speed.0;
+                }
+                graph.last_frame = Some(now);
+            }
+            Message::SetErrors(diagnostics) => self.diagnostics = diagnostics,
+            Message::ClearErrors => self.diagnostics.clear(),
         }
     }
 
@@ -27,73 +80,84 @@ impl MainState {
         let text = &self.text.text();
 
         // Parsing
-        match mth_parser::parse_program(&text) {
-            // Ok
-            Ok((rem, module)) if rem.remainder.is_empty() => {
-
-                #[cfg(not(target_arch = "wasm32"))]
-                std::fs::write("output/last_ast", format!("{module:#?}"))
-                    .expect("Couldn't write ast to file: output dir doesn't exists?");
+        let (module, mut diagnostics) = mth_parser::parse_program(text);
+        self.error_ranges = ErrorRanges::new(
+            text,
+            module.top_level.iter().filter_map(|tl| match tl {
+                TopLevel::Error(span) => Some((*span).into()),
+                _ => None,
+            }),
+        );
 
-                // Codegen
-                match code_generator::compile_module(&module) {
+        #[cfg(not(target_arch = "wasm32"))]
+        std::fs::write("output/last_ast", format!("{module:#?}"))
+            .expect("Couldn't write ast to file: output dir doesn't exists?");
 
-                    // Ok
-                    Ok((mut instructions, plot_desc)) if instructions.len() <= N_INSTRUCTIONS => {
-                        self.write_instructions(&mut instructions, &plot_desc);
-                        self.update(Message::ClearErrors);
-                    }
-
-                    // Too many instructions
-                    Ok((instructions, _plot_desc)) => self.update(Message::SetError(
-                        format!("The generated instructions don't fit into the GPU instruction buffer. Got {n} instructions", n = instructions.len())
-                    )),
-
-                    // Error
-                    Err(e) => {
-                        self.update(Message::SetError(format!("Code generation failed: {e:?}")))
-                    }
+        // Codegen, of the statements that could be parsed
+        match code_generator::compile_module(&module) {
+            Ok(compiled) => {
+                self.write_instructions(compiled.instructions, compiled.plot_descs);
+                self.graph.plot_names = compiled
+                    .plot_spans
+                    .iter()
+                    .map(|span| {
+                        let source = text.get(span.start..span.end).unwrap_or_default();
+                        source.split_whitespace().collect::<Vec<_>>().join(" ")
+                    })
+                    .collect();
+                self.set_sliders(compiled.sliders);
+                // Only a changed view moves the graph, edits elsewhere keep it where it is
+                if compiled.view != self.graph.view {
+                    self.graph.view = compiled.view;
+                    self.show_program_view();
                 }
             }
+            Err(e) => diagnostics.push(e),
+        }
 
-            // Parse error
-            Err(e) => self.update(Message::SetError(format!("{e}"))),
-
-            // Incomplete parse
-            Ok((rem, _)) => self.update(Message::SetError(format!(
-                "Couldn't parse everything. Unparsed input: {}",
-                rem.remainder
-            ))),
+        if diagnostics.is_empty() {
+            self.update(Message::ClearErrors);
+        } else {
+            self.update(Message::SetErrors(diagnostics));
         }
     }
 
-    /// Needs to be called with a instructions buffer of length `N_INSTRUCTIONS` or less. Also,
-    /// requires the length of the GPU buffer (`self.graph.instructions`) to be equal or greater
-    /// than `N_INSTRUCTIONS`
-    fn write_instructions(
-        &mut self,
-        instructions: &mut Vec<Instruction>,
-        plot_desc: &[PlotDesc; N_PLOTS],
-    ) {
-        if instructions.len() % 2 == 1 {
-            // Can only write a even amount to the shader
-            instructions.push(Instruction::default())
+    /// Move and zoom to the `view` statement of the program, if it has one
+    fn show_program_view(&mut self) {
+        let graph = &mut self.graph;
+        if let Some([x, y]) = graph.view
+            && graph.size != DVec2::ZERO
+        {
+            graph
+                .controls
+                .show(x.map(f64::from), y.map(f64::from), graph.size);
         }
+    }
 
-        let len = instructions.len();
-        assert!(len <= N_INSTRUCTIONS); // Still true after push anyways
-
-        let len = instructions.len();
-
-        // Copy instructions into the graphs mutex
-        {
-            self.graph
-                .instructions
-                .lock()
-                .expect("Could not lock instructions mutex in MainState::update")[..len]
-                .copy_from_slice(&instructions[..len]);
+    /// Replace the sliders of the graph. Sliders that keep their name keep their value, as far
+    /// as it is in their new range
+    fn set_sliders(&mut self, sliders: Vec<Slider>) {
+        let graph = &mut self.graph;
+        let mut params = [0.0; MAX_PARAMS];
+        for (param, slider) in params.iter_mut().zip(&sliders) {
+            let old = graph.sliders.iter().position(|old| old.name == slider.name);
+            *param = match old {
+                Some(slot) => slider.snap(graph.params[slot]),
+                None => slider.value,
+            };
         }
-        self.graph.plot_desc = *plot_desc;
-        self.graph.instructions_dirty = true;
+        graph.sliders = sliders;
+        graph.params = params;
+    }
+
+    /// Hand a compiled module to the graph, which uploads it to the GPU on the next frame
+    fn write_instructions(&mut self, instructions: Vec<Instruction>, plot_desc: Vec<PlotDesc>) {
+        *self
+            .graph
+            .instructions
+            .lock()
+            .expect("Could not lock instructions mutex in MainState::update") = instructions;
+        self.graph.plot_desc = plot_desc.into();
+        self.graph.program_generation += 1;
     }
 }
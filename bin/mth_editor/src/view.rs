@@ -1,10 +1,16 @@
+use std::sync::Arc;
+
+use graph_canvas::{Axes, Crosshair, grid::AxisScale};
 use iced::{
-    Element,
+    Alignment::Center,
+    Element, Font,
     Length::{Fill, FillPortion},
+    Theme,
+    advanced::text::highlighter,
     widget::{self, column, container, row},
 };
 
-use crate::{MainState, message::Message};
+use crate::{MainState, graph::Speed, highlighter::ErrorHighlighter, message::Message};
 
 impl MainState {
     pub fn view(&'_ self) -> Element<'_, Message> {
@@ -23,18 +29,133 @@ impl MainState {
                     .size(20)
                     .height(Fill)
                     .on_action(Message::EditText)
+                    .highlight_with::<ErrorHighlighter>(
+                        self.error_ranges.clone(),
+                        |_, theme: &Theme| {
+                            highlighter::Format {
+                                color: Some(theme.palette().danger),
+                                font: None,
+                            }
+                        }
+                    )
             )
             .height(FillPortion(80))
             .style(container::rounded_box),
-            container(widget::text(
-                self.err_msg.clone().unwrap_or("No errors".to_string())
-            ))
-            .height(FillPortion(20)),
+            self.sliders_view(),
+            container(widget::scrollable(self.diagnostics_view())).height(FillPortion(20)),
         ]
+        .spacing(10)
+        .into()
+    }
+
+    /// A row with the name, the slider and the value of every slider of the program
+    fn sliders_view(&'_ self) -> Element<'_, Message> {
+        let graph = &self.graph;
+        widget::column(graph.sliders.iter().enumerate().map(|(slot, slider)| {
+            let [min, max] = slider.range;
+            let value = graph.params[slot];
+            row![
+                widget::text(&slider.name).font(Font::MONOSPACE).width(80),
+                widget::slider(min..=max, value, move |value| {
+                    Message::SetParameter(slot, value)
+                })
+                .step(slider.step),
+                widget::text(format_value(value, slider.step))
+                    .font(Font::MONOSPACE)
+                    .width(80),
+            ]
+            .spacing(10)
+            .align_y(Center)
+            .into()
+        }))
+        .spacing(5)
+        .into()
+    }
+
+    fn diagnostics_view(&'_ self) -> Element<'_, Message> {
+        if self.diagnostics.is_empty() {
+            return widget::text("No errors").into();
+        }
+
+        let text = self.text.text();
+        widget::column(self.diagnostics.iter().map(|diagnostic| {
+            widget::text(diagnostic.render(&text, None))
+                .font(Font::MONOSPACE)
+                .into()
+        }))
+        .spacing(10)
         .into()
     }
 
     fn graph_view(&'_ self) -> Element<'_, Message> {
-        widget::shader(&self.graph).height(Fill).width(Fill).into()
+        column![
+            self.axes_view(),
+            self.playback_view(),
+            widget::stack![
+                widget::shader(&self.graph).height(Fill).width(Fill),
+                widget::canvas(Axes::new(self.graph.controls))
+                    .height(Fill)
+                    .width(Fill),
+                widget::canvas(Crosshair::new(
+                    self.graph.controls,
+                    self.graph.params,
+                    self.graph.time as f32,
+                    Arc::clone(&self.graph.instructions),
+                    Arc::clone(&self.graph.plot_desc),
+                    Arc::clone(&self.graph.plot_names),
+                ))
+                .height(Fill)
+                .width(Fill),
+            ]
+        ]
+        .spacing(10)
+        .into()
     }
+
+    /// Controls of the time `t`
+    fn playback_view(&'_ self) -> Element<'_, Message> {
+        let graph = &self.graph;
+        row![
+            widget::button(if graph.playing { "Pause" } else { "Play" })
+                .on_press(Message::TogglePlayback),
+            widget::button("Restart").on_press(Message::RestartTime),
+            widget::text("speed"),
+            widget::pick_list(Speed::ALL, Some(graph.speed), Message::SetSpeed),
+            widget::text(format!("t = {:.2} s", graph.time)).font(Font::MONOSPACE),
+        ]
+        .spacing(10)
+        .align_y(Center)
+        .into()
+    }
+
+    fn axes_view(&'_ self) -> Element<'_, Message> {
+        let [x_scale, y_scale] = self.graph.controls.axes;
+        row![
+            widget::text("x axis"),
+            widget::pick_list(AxisScale::ALL, Some(x_scale), |scale| {
+                Message::SetAxisScale(0, scale)
+            }),
+            widget::text("y axis"),
+            widget::pick_list(AxisScale::ALL, Some(y_scale), |scale| {
+                Message::SetAxisScale(1, scale)
+            }),
+            widget::space::horizontal(),
+            widget::button("Fit all plots").on_press(Message::FitView),
+            widget::button("Reset view").on_press(Message::ResetView),
+        ]
+        .spacing(10)
+        .align_y(Center)
+        .into()
+    }
+}
+
+/// `value` with as many decimals as `step` needs
+fn format_value(value: f32, step: f32) -> String {
+    let decimals = (0..6)
+        .find(|&decimals| {
+            let scaled = step * 10f32.powi(decimals);
+            (scaled - scaled.round()).abs() < 1e-3
+        })
+        .unwrap_or(6) as usize;
+    format!("{value:.decimals$}")
 }
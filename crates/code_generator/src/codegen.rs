@@ -1,71 +1,619 @@
 use std::collections::HashMap;
 
-use mth_ast::{Expr, Function, FunctionCall, Literal, Module, TopLevel};
+use mth_ast::{
+    Expr, Function, FunctionCall, Lambda, Literal, Module, Param, Span, TopLevel, varref,
+};
 use mth_common::{
-    N_PLOTS, PLOT_TYPE_EQUATION, PLOT_TYPE_FN_GRAPH, inst, ops::*, plot_desc::PlotDesc,
+    PLOT_TYPE_EQUATION, PLOT_TYPE_FN_GRAPH, PLOT_TYPE_INVERSE, PLOT_TYPE_PARAMETRIC,
+    PLOT_TYPE_POLAR, STACK_SIZE, inst, ops::*, plot_desc::PlotDesc,
+};
+use mth_diagnostics::{Diagnostic, codes};
+
+use crate::{
+    consteval::const_eval, eliminate_common_subexpressions, inline, max_stack_depth, optimize,
+    reorder_operands, style, symbols, typeck,
 };
 
-type CResult = Result<(u32, u32), String>;
+/// Builtin inputs of a plotted function, in parameter order
+const INPUTS: [&str; 2] = ["x", "y"];
+
+/// The statement `view(x_min, x_max, y_min, y_max)` that sets the part of the graph shown
+pub(crate) const VIEW: &str = "view";
+
+/// The statement `slider(name, min, max, step)` that declares a name whose value is set while
+/// the program is shown, without compiling it again
+pub(crate) const SLIDER: &str = "slider";
+
+/// Names implemented by the compiler itself. They cannot be redefined
+pub const BUILTINS: &[&str] = &[
+    "+",
+    "-",
+    "*",
+    "/",
+    "^",
+    "or",
+    "and",
+    "bitwise_or",
+    "bitwise_xor",
+    "bitwise_and",
+    "==",
+    "!=",
+    "<",
+    "<=",
+    ">",
+    ">=",
+    "sin",
+    "cos",
+    "tan",
+    "log",
+    "abs",
+    "pi",
+    "x",
+    "y",
+    "t",
+];
+
+type CResult = Result<(u32, u32), Diagnostic>;
+
+/// Names visible while compiling an expression
+#[derive(Debug, Default)]
+pub struct Ctx<'a, 's> {
+    /// User defined functions, callable by name
+    pub functions: HashMap<&'s str, &'a Function<'s>>,
+
+    /// Values of definitions without parameters that are known at compile time
+    pub constants: HashMap<&'s str, f32>,
+
+    /// Parameter slots of the sliders, by name
+    pub sliders: HashMap<&'s str, u32>,
+
+    /// User functions currently being inlined, innermost last
+    call_stack: Vec<&'s str>,
+}
+
+/// The programs of all plots of a module, ready for the GPU
+#[derive(Debug, Clone)]
+pub struct CompiledModule {
+    /// Programs of the plots, one after the other
+    pub instructions: Vec<Instruction>,
+    pub plot_descs: Vec<PlotDesc>,
+
+    /// Instructions removed by [`optimize`](crate::optimize) and
+    /// [`eliminate_common_subexpressions`](crate::eliminate_common_subexpressions)
+    pub saved_instructions: usize,
+
+    /// The x and then the y range of graph coordinates of the `view` statement, if any
+    pub view: Option<[[f32; 2]; 2]>,
+
+    /// Code each plot was compiled from, in the order of `plot_descs`: the argument of `plot`
+    /// or `plot_inverse`, and the whole call for curves
+    pub plot_spans: Vec<Span>,
+
+    /// Sliders in the order of their parameter slots
+    pub sliders: Vec<Slider>,
+}
 
-pub fn compile_module(module: &Module) -> Result<(Vec<Instruction>, [PlotDesc; N_PLOTS]), String> {
-    let mut ctx = HashMap::new();
+/// A name declared by `slider(name, min, max, step)`, read by the programs with `OP_PARAM`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Slider {
+    pub name: String,
+    pub range: [f32; 2],
+    pub step: f32,
+
+    /// Value before the slider is moved: the step closest to the middle of the range
+    pub value: f32,
+}
+
+impl Slider {
+    /// The value closest to `value` that the slider can be set to
+    pub fn snap(&self, value: f32) -> f32 {
+        let [min, max] = self.range;
+        let steps = ((value - min) / self.step).round();
+        (min + steps * self.step).clamp(min, max)
+    }
+}
+
+pub fn compile_module(module: &Module) -> Result<CompiledModule, Diagnostic> {
+    let mut ctx = symbols::resolve(module)?;
+    typeck::check(module, &ctx)?;
+    let sliders = compile_sliders(module, &ctx)?;
     let mut instructions = Vec::new();
-    let mut plot_descs = [PlotDesc::default(); N_PLOTS];
-    let mut plot_index = 0usize;
+    let mut plot_descs = Vec::new();
+    let mut plot_spans = Vec::new();
+    let mut saved_instructions = 0;
+    let mut view: Option<(&FunctionCall, [[f32; 2]; 2])> = None;
 
     for expr in &module.top_level {
         match expr {
-            TopLevel::Function(mapping) => {
-                ctx.insert(mapping.name, mapping);
-            }
-            TopLevel::Expr(Expr::FunctionCall(FunctionCall {
-                name,
-                args,
-                is_negated,
-            })) if *name == "plot" => {
-                if plot_index >= N_PLOTS {
-                    return Err("Too many plots".to_string());
+            TopLevel::Function(_) => {} // Collected by `symbols::resolve`
+            TopLevel::Error(_) => {}    // Reported by the parser
+            TopLevel::TypeDecl(_) => {} // Checked by `typeck::check`
+            TopLevel::Expr(Expr::FunctionCall(call))
+                if PlotKind::from_name(call.name).is_some() =>
+            {
+                let style = style::compile_style(call, &ctx)?;
+                for plot in compile_plot_call(call, &mut ctx)? {
+                    let optimized: Vec<Instruction> = plot
+                        .components
+                        .iter()
+                        .flat_map(|program| optimize_program(program))
+                        .collect();
+                    check_stack_depth(&optimized, plot.span)?;
+                    saved_instructions +=
+                        plot.components.iter().map(Vec::len).sum::<usize>() - optimized.len();
+                    plot_descs.push(PlotDesc {
+                        length: optimized.len() as u32,
+                        type_id: plot.type_id,
+                        range: plot.range,
+                        color: style.color(plot_descs.len()),
+                        width: style.width.unwrap_or(1.0),
+                        dash: style.dash.unwrap_or_default(),
+                        fill: style.fill(plot.type_id),
+                        _pad: 0,
+                    });
+                    plot_spans.push(plot.span);
+                    instructions.extend(optimized);
                 }
-                let Some(f) = args.get(0) else {
-                    panic!("`plot` requires a function as argument");
-                };
-                let Expr::FunctionCall(FunctionCall { name: f, .. }) = f else {
-                    panic!("`plot` requires a function as argument");
-                };
-                let Some(mapping) = ctx.get(f) else {
-                    panic!("Could not resolve function `{f:?}`");
-                };
-
-                let (len, plot_type) = compile_fn(mapping, &mut instructions)?;
-                if *is_negated {
-                    instructions.push(inst!(OP_CONST, -1.0));
-                    instructions.push(inst!(OP_MUL));
+            }
+            TopLevel::Expr(Expr::FunctionCall(call)) if call.name == VIEW => {
+                if let Some((first, _)) = view {
+                    return Err(Diagnostic::error("The view is set more than once")
+                        .with_code(codes::DUPLICATE_VIEW)
+                        .with_primary(call.span.into(), "set again here")
+                        .with_secondary(first.span.into(), "first set here"));
                 }
+                view = Some((call, compile_view(call, &ctx)?));
+            }
+            TopLevel::Expr(Expr::FunctionCall(call)) if call.name == SLIDER => {} // Compiled by `compile_sliders`
+            other => {
+                return Err(Diagnostic::error("Invalid top-level statement")
+                    .with_code(codes::INVALID_TOP_LEVEL)
+                    .with_primary(other.span().into(), "")
+                    .with_note(
+                        "only definitions, `view`, `slider` and calls to `plot`, `plot_parametric`, `plot_polar` and `plot_inverse` are allowed at the top level",
+                    ));
+            }
+        }
+    }
+
+    Ok(CompiledModule {
+        instructions,
+        plot_descs,
+        saved_instructions,
+        view: view.map(|(_, ranges)| ranges),
+        plot_spans,
+        sliders,
+    })
+}
+
+/// Parse and compile `src`, panicking on syntax and compile errors. For the tests of the
+/// crates that run compiled programs
+#[cfg(feature = "test-support")]
+pub fn compile_source(src: &str) -> CompiledModule {
+    let (module, diagnostics) = mth_parser::parse_program(src);
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    compile_module(&module).unwrap()
+}
+
+/// Evaluate the ranges and steps of the `slider` statements of `module`, whose parameter slots
+/// are assigned by `symbols::resolve`
+fn compile_sliders(module: &Module, ctx: &Ctx) -> Result<Vec<Slider>, Diagnostic> {
+    let mut sliders = Vec::new();
+    for top_level in &module.top_level {
+        if let TopLevel::Expr(Expr::FunctionCall(call)) = top_level
+            && call.name == SLIDER
+        {
+            sliders.push(compile_slider(call, ctx)?);
+        }
+    }
+    Ok(sliders)
+}
+
+fn compile_slider(call: &FunctionCall, ctx: &Ctx) -> Result<Slider, Diagnostic> {
+    if let Some(arg) = call.named_args.first() {
+        return Err(Diagnostic::error(format!("`{SLIDER}` has no options"))
+            .with_code(codes::UNKNOWN_OPTION)
+            .with_primary(arg.span.into(), "unknown option"));
+    }
+    let [name, min, max, step] = call.args.as_slice() else {
+        return Err(
+            Diagnostic::error(format!("Wrong number of arguments for {SLIDER}"))
+                .with_code(codes::WRONG_ARGUMENT_COUNT)
+                .with_primary(
+                    call.span.into(),
+                    format!("expected 4 arguments, got {}", call.args.len()),
+                )
+                .with_note("the arguments are the name, the range and the step of the slider"),
+        );
+    };
+    let name = match name {
+        Expr::FunctionCall(FunctionCall {
+            name,
+            args,
+            named_args,
+            is_negated: false,
+            ..
+        }) if args.is_empty() && named_args.is_empty() => *name,
+        other => {
+            return Err(
+                Diagnostic::error("The name of a slider must be an identifier")
+                    .with_code(codes::INVALID_SLIDER)
+                    .with_primary(other.span().into(), "not a name"),
+            );
+        }
+    };
+
+    let range = const_range(SLIDER, min, max, ctx)?;
+    let step_value = const_eval(step, &ctx.constants);
+    let Some(step_value) = step_value.filter(|step| step.is_finite() && *step > 0.0) else {
+        return Err(Diagnostic::error(format!(
+            "The step of the slider `{name}` must be a positive number known at compile time"
+        ))
+        .with_code(codes::INVALID_SLIDER)
+        .with_primary(step.span().into(), "not a valid step"));
+    };
+
+    let mut slider = Slider {
+        name: name.to_owned(),
+        range,
+        step: step_value,
+        value: 0.0,
+    };
+    slider.value = slider.snap((range[0] + range[1]) / 2.0);
+    Ok(slider)
+}
+
+/// Evaluate the ranges of the statement `view(x_min, x_max, y_min, y_max)` at compile time
+fn compile_view(call: &FunctionCall, ctx: &Ctx) -> Result<[[f32; 2]; 2], Diagnostic> {
+    if let Some(arg) = call.named_args.first() {
+        return Err(Diagnostic::error(format!("`{VIEW}` has no options"))
+            .with_code(codes::UNKNOWN_OPTION)
+            .with_primary(arg.span.into(), "unknown option"));
+    }
+    let [x_min, x_max, y_min, y_max] = call.args.as_slice() else {
+        return Err(
+            Diagnostic::error(format!("Wrong number of arguments for {VIEW}"))
+                .with_code(codes::WRONG_ARGUMENT_COUNT)
+                .with_primary(
+                    call.span.into(),
+                    format!("expected 4 arguments, got {}", call.args.len()),
+                )
+                .with_note("the arguments are the x range and then the y range"),
+        );
+    };
+    Ok([
+        const_range(VIEW, x_min, x_max, ctx)?,
+        const_range(VIEW, y_min, y_max, ctx)?,
+    ])
+}
+
+/// Compile and optimize the definition `name` of `module` on its own, the way it is plotted
+/// with every slider at its initial value and the time at 0
+pub fn compile_function(module: &Module, name: &str) -> Result<Vec<Instruction>, Diagnostic> {
+    let mut ctx = symbols::resolve(module)?;
+    typeck::check(module, &ctx)?;
+    let sliders = compile_sliders(module, &ctx)?;
+
+    let Some(f) = ctx.functions.get(name).copied() else {
+        return Err(Diagnostic::error(format!("No definition named `{name}`"))
+            .with_code(codes::UNDEFINED_NAME));
+    };
+    let mut instructions = Vec::new();
+    compile_fn(f, &mut ctx, &mut instructions)?;
+    for op in &mut instructions {
+        match op.opcode {
+            OP_PARAM => *op = inst!(OP_CONST, sliders[op.a as usize].value),
+            OP_T => *op = inst!(OP_CONST, 0.0),
+            _ => {}
+        }
+    }
+    let optimized = optimize_program(&instructions);
+    check_stack_depth(&optimized, f.span)?;
+    Ok(optimized)
+}
+
+/// Run the optimization passes over the program of one plot
+fn optimize_program(program: &[Instruction]) -> Vec<Instruction> {
+    eliminate_common_subexpressions(&reorder_operands(&optimize(program)))
+}
+
+/// Check that the VM can evaluate `program`, which was compiled from the code at `span`
+fn check_stack_depth(program: &[Instruction], span: Span) -> Result<(), Diagnostic> {
+    // The VM fails as soon as the stack is full
+    let limit = STACK_SIZE - 1;
+    let depth = max_stack_depth(program);
+    if depth <= limit {
+        return Ok(());
+    }
+    Err(Diagnostic::error(format!(
+        "This plot needs a stack of {depth} values, but the GPU can hold at most {limit}"
+    ))
+    .with_code(codes::STACK_OVERFLOW)
+    .with_primary(span.into(), "nested too deeply")
+    .with_note(
+        "operands of `-`, `/` and `^` on the right side are evaluated on top of the left side",
+    )
+    .with_help("compute deeply nested operands first, like `(a - b) + c` instead of `c - (b - a)`"))
+}
+
+/// The builtins that draw their arguments
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PlotKind {
+    /// `plot(f, ...)`: graphs of functions of `x`, and equations in `x` and `y`
+    Plot,
+    /// `plot_parametric(fx, fy[, t_min, t_max])`: the curve `(fx(t), fy(t))`
+    Parametric,
+    /// `plot_polar(r[, θ_min, θ_max])`: the curve with radius `r(θ)` at the angle `θ`
+    Polar,
+    /// `plot_inverse(g, ...)`: graphs of `x = g(y)`
+    Inverse,
+}
+
+impl PlotKind {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "plot" => Some(Self::Plot),
+            "plot_parametric" => Some(Self::Parametric),
+            "plot_polar" => Some(Self::Polar),
+            "plot_inverse" => Some(Self::Inverse),
+            _ => None,
+        }
+    }
 
-                plot_descs[plot_index] = PlotDesc {
-                    length: if *is_negated { len + 2 } else { len },
-                    type_id: plot_type,
-                    ..Default::default()
-                };
-                plot_index += 1;
+    /// Number of arguments that are the components of one curve, the optional range of its
+    /// parameter follows them. `None` if every argument is a plot of its own
+    pub(crate) fn components(self) -> Option<usize> {
+        match self {
+            Self::Parametric => Some(2),
+            Self::Polar => Some(1),
+            Self::Plot | Self::Inverse => None,
+        }
+    }
+
+    /// Inputs that the parameters of plotted functions are bound to
+    fn inputs(self) -> &'static [&'static str] {
+        match self {
+            Self::Plot => &INPUTS,
+            Self::Parametric | Self::Polar => &INPUTS[..1],
+            Self::Inverse => &INPUTS[1..],
+        }
+    }
+}
+
+/// Range of the parameter of curves without an explicit range
+const DEFAULT_RANGE: [f32; 2] = [0.0, std::f32::consts::TAU];
+
+/// One plot of a call to a plotting builtin, before optimization
+struct Plot {
+    type_id: u32,
+
+    /// Programs of the values of the plot, evaluated one after the other
+    components: Vec<Vec<Instruction>>,
+    range: [f32; 2],
+
+    /// Code the plot was compiled from
+    span: Span,
+}
+
+/// Compile a call to `plot`, `plot_parametric`, `plot_polar` or `plot_inverse`
+fn compile_plot_call<'s>(
+    call: &FunctionCall<'s>,
+    ctx: &mut Ctx<'_, 's>,
+) -> Result<Vec<Plot>, Diagnostic> {
+    let kind = PlotKind::from_name(call.name).expect("called with a plotting builtin");
+    let FunctionCall {
+        name,
+        args,
+        is_negated,
+        span,
+        ..
+    } = call;
+    if args.is_empty() {
+        return Err(
+            Diagnostic::error(format!("`{name}` requires at least one argument"))
+                .with_code(codes::EMPTY_PLOT)
+                .with_primary((*span).into(), "nothing to plot"),
+        );
+    }
+
+    let mut compile = |arg: &Expr<'s>| {
+        let mut program = Vec::new();
+        let (_len, plot_type) = compile_curve(arg, kind.inputs(), ctx, &mut program)?;
+        if *is_negated {
+            program.push(inst!(OP_NEG));
+        }
+        Ok::<_, Diagnostic>((program, plot_type))
+    };
+
+    let Some(n) = kind.components() else {
+        return args
+            .iter()
+            .map(|arg| {
+                let (program, plot_type) = compile(arg)?;
+                Ok(Plot {
+                    type_id: match kind {
+                        PlotKind::Inverse => PLOT_TYPE_INVERSE,
+                        _ => plot_type,
+                    },
+                    components: vec![program],
+                    range: [0.0, 0.0],
+                    span: arg.span(),
+                })
+            })
+            .collect();
+    };
+
+    if args.len() != n && args.len() != n + 2 {
+        return Err(
+            Diagnostic::error(format!("Wrong number of arguments for {name}"))
+                .with_code(codes::WRONG_ARGUMENT_COUNT)
+                .with_primary(
+                    (*span).into(),
+                    format!("expected {n} or {} arguments, got {}", n + 2, args.len()),
+                )
+                .with_note("the optional last two arguments are the range of the parameter"),
+        );
+    }
+    let (curves, range) = args.split_at(n);
+    let components = curves
+        .iter()
+        .map(|arg| compile(arg).map(|(program, _)| program))
+        .collect::<Result<_, _>>()?;
+    let range = match range {
+        [] => DEFAULT_RANGE,
+        [start, end] => const_range(name, start, end, ctx)?,
+        _ => unreachable!("checked above"),
+    };
+
+    Ok(vec![Plot {
+        type_id: match kind {
+            PlotKind::Polar => PLOT_TYPE_POLAR,
+            _ => PLOT_TYPE_PARAMETRIC,
+        },
+        components,
+        range,
+        span: *span,
+    }])
+}
+
+/// Evaluate the range of the parameter of the curve of `name` at compile time
+fn const_range(name: &str, start: &Expr, end: &Expr, ctx: &Ctx) -> Result<[f32; 2], Diagnostic> {
+    let eval = |bound: &Expr| {
+        const_eval(bound, &ctx.constants).ok_or_else(|| {
+            Diagnostic::error(format!(
+                "The range of `{name}` must be known at compile time"
+            ))
+            .with_code(codes::NON_CONSTANT_RANGE)
+            .with_primary(bound.span().into(), "not a constant")
+            .with_note("ranges can use numbers, `pi` and definitions without parameters")
+        })
+    };
+    let [start_value, end_value] = [eval(start)?, eval(end)?];
+    if !(start_value.is_finite() && end_value.is_finite() && start_value < end_value) {
+        return Err(Diagnostic::error(format!(
+            "Invalid range from {start_value} to {end_value} for `{name}`"
+        ))
+        .with_code(codes::INVALID_RANGE)
+        .with_primary(
+            start.span().to(end.span()).into(),
+            "no values in this range",
+        )
+        .with_help("the start of the range must be a finite number less than its end"));
+    }
+    Ok([start_value, end_value])
+}
+
+/// Compile one argument of `plot`: the name of a function, a lambda, or an expression in the
+/// inputs `x` and `y`
+pub fn compile_plot<'s>(
+    arg: &Expr<'s>,
+    ctx: &mut Ctx<'_, 's>,
+    buf: &mut Vec<Instruction>,
+) -> CResult {
+    compile_curve(arg, &INPUTS, ctx, buf)
+}
+
+/// Compile a plotted function like [`compile_plot`], whose parameters are bound to `inputs`
+fn compile_curve<'s>(
+    arg: &Expr<'s>,
+    inputs: &[&'static str],
+    ctx: &mut Ctx<'_, 's>,
+    buf: &mut Vec<Instruction>,
+) -> CResult {
+    match arg {
+        Expr::Lambda(Lambda { params, body, span }) => {
+            compile_mapping("lambda", *span, params, body, inputs, ctx, buf)
+        }
+        Expr::FunctionCall(FunctionCall {
+            name,
+            args,
+            is_negated,
+            ..
+        }) if args.is_empty() && ctx.functions.contains_key(name) => {
+            let f = ctx.functions[name];
+            if *is_negated {
+                compile_mapping(
+                    f.name,
+                    f.span,
+                    &f.params,
+                    &-f.body.clone(),
+                    inputs,
+                    ctx,
+                    buf,
+                )
+            } else {
+                compile_mapping(f.name, f.span, &f.params, &f.body, inputs, ctx, buf)
             }
-            other => return Err(format!("Invalid top-level: {other:?}")),
         }
+        expr => compile_expr(expr, ctx, buf),
     }
+}
 
-    Ok((instructions, plot_descs))
+/// Compile `f` as a plot. Its parameters are bound to the inputs `x` and `y`, in that order
+pub fn compile_fn<'s>(
+    f: &Function<'s>,
+    ctx: &mut Ctx<'_, 's>,
+    buf: &mut Vec<Instruction>,
+) -> CResult {
+    compile_mapping(f.name, f.span, &f.params, &f.body, &INPUTS, ctx, buf)
 }
 
-pub fn compile_fn(f: &Function, buf: &mut Vec<Instruction>) -> CResult {
-    match f {
-        Function { body, .. } => compile_expr(body, buf),
+fn compile_mapping<'s>(
+    name: &str,
+    span: Span,
+    params: &[Param<'s>],
+    body: &Expr<'s>,
+    inputs: &[&'static str],
+    ctx: &mut Ctx<'_, 's>,
+    buf: &mut Vec<Instruction>,
+) -> CResult {
+    if params.len() > inputs.len() {
+        return Err(Diagnostic::error(format!(
+            "Cannot plot `{name}`: it takes {} parameters, but at most {} can be plotted",
+            params.len(),
+            inputs.len()
+        ))
+        .with_code(codes::TOO_MANY_PARAMETERS)
+        .with_primary(
+            params[inputs.len()].1.to(params[params.len() - 1].1).into(),
+            "no input left for these parameters",
+        )
+        .with_secondary(span.into(), format!("`{name}` is plotted here"))
+        .with_note(format!(
+            "the parameters of a plotted function are bound to {}",
+            inputs
+                .iter()
+                .map(|input| format!("`{input}`"))
+                .collect::<Vec<_>>()
+                .join(" and ")
+        )));
     }
+
+    let inputs = inputs[..params.len()].iter().map(|input| varref(input));
+    let body = inline::substitute(body, &inline::bind(params, inputs));
+    compile_expr(&body, ctx, buf)
 }
 
-pub fn compile_expr(expr: &Expr, buf: &mut Vec<Instruction>) -> CResult {
+pub fn compile_expr<'s>(
+    expr: &Expr<'s>,
+    ctx: &mut Ctx<'_, 's>,
+    buf: &mut Vec<Instruction>,
+) -> CResult {
     match expr {
-        Expr::Literal(lit) => compile_literal(lit, buf),
-        Expr::FunctionCall(s_expr) => compile_s_expr(s_expr, buf),
+        Expr::Literal(lit, span) => {
+            compile_literal(lit, buf).map_err(|e| e.with_primary((*span).into(), "in this literal"))
+        }
+        Expr::FunctionCall(s_expr) => compile_s_expr(s_expr, ctx, buf),
+        Expr::Lambda(lambda) => Err(Diagnostic::error(
+            "Lambdas can only be used as arguments of `plot`",
+        )
+        .with_code(codes::MISPLACED_LAMBDA)
+        .with_primary(lambda.span.into(), "")),
+        Expr::List(_, span) => Err(Diagnostic::error(
+            "Lists can only be used as values of named arguments",
+        )
+        .with_code(codes::MISPLACED_LIST)
+        .with_primary((*span).into(), "")),
     }
 }
 
@@ -79,86 +627,111 @@ pub fn compile_literal(lit: &Literal, buf: &mut Vec<Instruction>) -> CResult {
             buf.push(inst!(OP_CONST, *float as f32));
             Ok((1, PLOT_TYPE_FN_GRAPH))
         }
-        _ => Err(format!("Invalid literal: {lit:?}")),
+        _ => Err(Diagnostic::error(format!("Invalid literal: {lit:?}"))
+            .with_code(codes::UNSUPPORTED_LITERAL)),
     }
 }
 
-pub fn compile_s_expr(s_expr: &FunctionCall, buf: &mut Vec<Instruction>) -> CResult {
+pub fn compile_s_expr<'s>(
+    s_expr: &FunctionCall<'s>,
+    ctx: &mut Ctx<'_, 's>,
+    buf: &mut Vec<Instruction>,
+) -> CResult {
+    if let Some(arg) = s_expr.named_args.first() {
+        return Err(Diagnostic::error(format!(
+            "`{}` takes no named arguments",
+            s_expr.name
+        ))
+        .with_code(codes::UNKNOWN_OPTION)
+        .with_primary(arg.span.into(), "unexpected named argument")
+        .with_note(
+            "only `plot`, `plot_parametric`, `plot_polar` and `plot_inverse` take named arguments",
+        ));
+    }
+
+    if s_expr.args.is_empty()
+        && let Some(value) = ctx.constants.get(s_expr.name)
+    {
+        let value = if s_expr.is_negated { -value } else { *value };
+        buf.push(inst!(OP_CONST, value));
+        return Ok((1, PLOT_TYPE_FN_GRAPH));
+    }
+
     let start_len = buf.len();
     let plot_type = match s_expr.name {
         "+" => {
-            compile_binary_op(s_expr, OP_ADD, buf)?;
+            compile_binary_op(s_expr, ctx, OP_ADD, buf)?;
             PLOT_TYPE_FN_GRAPH
         }
         "-" => {
-            compile_binary_op(s_expr, OP_SUB, buf)?;
+            compile_binary_op(s_expr, ctx, OP_SUB, buf)?;
             PLOT_TYPE_FN_GRAPH
         }
         "*" => {
-            compile_binary_op(s_expr, OP_MUL, buf)?;
+            compile_binary_op(s_expr, ctx, OP_MUL, buf)?;
             PLOT_TYPE_FN_GRAPH
         }
         "/" => {
-            compile_binary_op(s_expr, OP_DIV, buf)?;
+            compile_binary_op(s_expr, ctx, OP_DIV, buf)?;
             PLOT_TYPE_FN_GRAPH
         }
         "^" => {
-            compile_binary_op(s_expr, OP_POW, buf)?;
+            compile_binary_op(s_expr, ctx, OP_POW, buf)?;
             PLOT_TYPE_FN_GRAPH
         }
 
         "or" => {
-            compile_binary_op(s_expr, OP_OR, buf)?;
-            PLOT_TYPE_FN_GRAPH
+            compile_binary_op(s_expr, ctx, OP_OR, buf)?;
+            PLOT_TYPE_EQUATION
         }
         "and" => {
-            compile_binary_op(s_expr, OP_AND, buf)?;
-            PLOT_TYPE_FN_GRAPH
+            compile_binary_op(s_expr, ctx, OP_AND, buf)?;
+            PLOT_TYPE_EQUATION
         }
 
         "bitwise_or" => {
-            compile_binary_op(s_expr, OP_BW_OR, buf)?;
+            compile_binary_op(s_expr, ctx, OP_BW_OR, buf)?;
             PLOT_TYPE_FN_GRAPH
         }
         "bitwise_xor" => {
-            compile_binary_op(s_expr, OP_BW_XOR, buf)?;
+            compile_binary_op(s_expr, ctx, OP_BW_XOR, buf)?;
             PLOT_TYPE_FN_GRAPH
         }
         "bitwise_and" => {
-            compile_binary_op(s_expr, OP_BW_AND, buf)?;
+            compile_binary_op(s_expr, ctx, OP_BW_AND, buf)?;
             PLOT_TYPE_FN_GRAPH
         }
 
         "==" => {
-            compile_binary_op(s_expr, OP_EQ, buf)?;
+            compile_binary_op(s_expr, ctx, OP_EQ, buf)?;
             PLOT_TYPE_EQUATION
         }
         "!=" => {
-            compile_binary_op(s_expr, OP_NE, buf)?;
+            compile_binary_op(s_expr, ctx, OP_NE, buf)?;
             PLOT_TYPE_EQUATION
         }
         "<" => {
-            compile_binary_op(s_expr, OP_LT, buf)?;
+            compile_binary_op(s_expr, ctx, OP_LT, buf)?;
             PLOT_TYPE_EQUATION
         }
         "<=" => {
-            compile_binary_op(s_expr, OP_LE, buf)?;
+            compile_binary_op(s_expr, ctx, OP_LE, buf)?;
             PLOT_TYPE_EQUATION
         }
         ">" => {
-            compile_binary_op(s_expr, OP_GT, buf)?;
+            compile_binary_op(s_expr, ctx, OP_GT, buf)?;
             PLOT_TYPE_EQUATION
         }
         ">=" => {
-            compile_binary_op(s_expr, OP_GE, buf)?;
+            compile_binary_op(s_expr, ctx, OP_GE, buf)?;
             PLOT_TYPE_EQUATION
         }
 
         "sin" | "cos" | "tan" | "log" => {
             if s_expr.args.len() != 1 {
-                return Err(format!("Wrong number of arguments for {}", s_expr.name));
+                return Err(wrong_argument_count(s_expr, 1));
             }
-            let (_inner_len, _) = compile_expr(&s_expr.args[0], buf)?;
+            let (_inner_len, _) = compile_expr(&s_expr.args[0], ctx, buf)?;
             let opcode = match s_expr.name {
                 "sin" => OP_SIN,
                 "cos" => OP_COS,
@@ -172,60 +745,157 @@ pub fn compile_s_expr(s_expr: &FunctionCall, buf: &mut Vec<Instruction>) -> CRes
 
         "abs" => {
             if s_expr.args.len() != 1 {
-                return Err(format!("Wrong number of arguments for {}", s_expr.name));
+                return Err(wrong_argument_count(s_expr, 1));
             }
-            let (_inner_len, _) = compile_expr(&s_expr.args[0], buf)?;
+            let (_inner_len, _) = compile_expr(&s_expr.args[0], ctx, buf)?;
             buf.push(inst!(OP_ABS));
             PLOT_TYPE_FN_GRAPH
         }
 
         "pi" => {
-            if s_expr.args.len() != 0 {
-                return Err(format!("Wrong number of arguments for {}", s_expr.name));
+            if !s_expr.args.is_empty() {
+                return Err(wrong_argument_count(s_expr, 0));
             }
             buf.push(inst!(OP_CONST, std::f32::consts::PI));
             PLOT_TYPE_FN_GRAPH
         }
 
         "x" => {
-            if s_expr.args.len() != 0 {
-                return Err(format!("Wrong number of arguments for {}", s_expr.name));
+            if !s_expr.args.is_empty() {
+                return Err(wrong_argument_count(s_expr, 0));
             }
             buf.push(inst!(OP_X));
             PLOT_TYPE_FN_GRAPH
         }
 
         "y" => {
-            if s_expr.args.len() != 0 {
-                return Err(format!("Wrong number of arguments for {}", s_expr.name));
+            if !s_expr.args.is_empty() {
+                return Err(wrong_argument_count(s_expr, 0));
             }
             buf.push(inst!(OP_Y));
             PLOT_TYPE_FN_GRAPH
         }
 
-        _ => return Err(format!("Unknown function: {}", s_expr.name)),
+        "t" => {
+            if !s_expr.args.is_empty() {
+                return Err(wrong_argument_count(s_expr, 0));
+            }
+            buf.push(inst!(OP_T));
+            PLOT_TYPE_FN_GRAPH
+        }
+
+        name if s_expr.args.is_empty() && ctx.sliders.contains_key(name) => {
+            buf.push(inst!(OP_PARAM, ctx.sliders[name] as f32));
+            PLOT_TYPE_FN_GRAPH
+        }
+
+        name => {
+            let Some(f) = ctx.functions.get(name).copied() else {
+                return Err(Diagnostic::error(format!("Unknown function: {name}"))
+                    .with_code(codes::UNDEFINED_NAME)
+                    .with_primary(s_expr.span.into(), "not defined"));
+            };
+            let (_inner_len, plot_type) = compile_user_call(f, s_expr, ctx, buf)?;
+            plot_type
+        }
     };
 
     if s_expr.is_negated {
-        buf.push(inst!(OP_CONST, -1.0));
-        buf.push(inst!(OP_MUL));
+        buf.push(inst!(OP_NEG));
     }
 
     let len = (buf.len() - start_len) as u32;
     Ok((len, plot_type))
 }
 
-pub fn compile_binary_op(
-    s_expr: &FunctionCall,
+/// Inline a call to the user function `f`, binding the call arguments to its parameters
+pub fn compile_user_call<'s>(
+    f: &Function<'s>,
+    s_expr: &FunctionCall<'s>,
+    ctx: &mut Ctx<'_, 's>,
+    buf: &mut Vec<Instruction>,
+) -> CResult {
+    if s_expr.args.len() != f.params.len() {
+        return Err(wrong_user_argument_count(f, s_expr));
+    }
+
+    // Only the calls in the body of `f` can recurse, the arguments are the caller's
+    let mut callees = Vec::new();
+    symbols::dependencies(
+        &f.body,
+        &symbols::param_names(&f.params),
+        &ctx.functions,
+        &mut callees,
+    );
+    if let Some(callee) = callees
+        .into_iter()
+        .find(|callee| *callee == f.name || ctx.call_stack.contains(callee))
+    {
+        let path = [ctx.call_stack.as_slice(), &[f.name, callee]].concat();
+        return Err(Diagnostic::error(format!(
+            "Recursive call to {callee} cannot be inlined: {}",
+            path.join(" -> ")
+        ))
+        .with_code(codes::RECURSIVE_CALL)
+        .with_primary(s_expr.span.into(), "recursive call")
+        .with_note("calls to user functions are inlined, so they can't be recursive"));
+    }
+
+    let body = inline::substitute(
+        &f.body,
+        &inline::bind(&f.params, s_expr.args.iter().cloned()),
+    );
+
+    ctx.call_stack.push(f.name);
+    let result = compile_expr(&body, ctx, buf);
+    ctx.call_stack.pop();
+    result
+}
+
+pub fn compile_binary_op<'s>(
+    s_expr: &FunctionCall<'s>,
+    ctx: &mut Ctx<'_, 's>,
     opcode: u32,
     buf: &mut Vec<Instruction>,
-) -> Result<u32, String> {
+) -> Result<u32, Diagnostic> {
     if s_expr.args.len() != 2 {
-        return Err(format!("Wrong number of arguments for {}", s_expr.name));
+        return Err(wrong_argument_count(s_expr, 2));
     }
     let start_len = buf.len();
-    let (_len1, _) = compile_expr(&s_expr.args[0], buf)?;
-    let (_len2, _) = compile_expr(&s_expr.args[1], buf)?;
+    let (_len1, _) = compile_expr(&s_expr.args[0], ctx, buf)?;
+    let (_len2, _) = compile_expr(&s_expr.args[1], ctx, buf)?;
     buf.push(inst!(opcode));
     Ok((buf.len() - start_len) as u32)
 }
+
+pub(crate) fn wrong_argument_count(s_expr: &FunctionCall, expected: usize) -> Diagnostic {
+    let plural = if expected == 1 { "" } else { "s" };
+    Diagnostic::error(format!("Wrong number of arguments for {}", s_expr.name))
+        .with_code(codes::WRONG_ARGUMENT_COUNT)
+        .with_primary(
+            s_expr.span.into(),
+            format!(
+                "expected {expected} argument{plural}, got {}",
+                s_expr.args.len()
+            ),
+        )
+}
+
+pub(crate) fn wrong_user_argument_count(f: &Function, s_expr: &FunctionCall) -> Diagnostic {
+    Diagnostic::error(format!(
+        "Wrong number of arguments for {}: expected {}, got {}",
+        f.name,
+        f.params.len(),
+        s_expr.args.len()
+    ))
+    .with_code(codes::WRONG_ARGUMENT_COUNT)
+    .with_primary(
+        s_expr.span.into(),
+        format!(
+            "expected {} argument{}",
+            f.params.len(),
+            if f.params.len() == 1 { "" } else { "s" }
+        ),
+    )
+    .with_secondary(f.span.into(), format!("`{}` is defined here", f.name))
+}
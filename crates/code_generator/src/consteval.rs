@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+
+use mth_ast::{Expr, FunctionCall, Literal};
+use mth_common::spow;
+
+/// Evaluate `expr` at compile time, if it only depends on literals and the given constants.
+/// Folds in `f32` like the VM, so constants have the values the shader would compute
+pub fn const_eval(expr: &Expr, constants: &HashMap<&str, f32>) -> Option<f32> {
+    match expr {
+        Expr::Literal(Literal::Int(int), _) => Some(*int as f32),
+        Expr::Literal(Literal::Float(float), _) => Some(*float as f32),
+        Expr::Literal(Literal::Bool(_) | Literal::String(_), _)
+        | Expr::Lambda(_)
+        | Expr::List(..) => None,
+        Expr::FunctionCall(FunctionCall {
+            name,
+            args,
+            is_negated,
+            ..
+        }) => {
+            let value = match constants.get(name) {
+                Some(value) if args.is_empty() => *value,
+                _ => {
+                    let args = args
+                        .iter()
+                        .map(|arg| const_eval(arg, constants))
+                        .collect::<Option<Vec<_>>>()?;
+                    eval_builtin(name, &args)?
+                }
+            };
+            Some(if *is_negated { -value } else { value })
+        }
+    }
+}
+
+/// Evaluate a pure numeric builtin the same way the VM does.
+///
+/// Returns `None` for unknown names, wrong argument counts and builtins that depend on the
+/// inputs of the plot (`x`, `y`).
+pub fn eval_builtin(name: &str, args: &[f32]) -> Option<f32> {
+    let value = match (name, args) {
+        ("+", [a, b]) => a + b,
+        ("-", [a, b]) => a - b,
+        ("*", [a, b]) => a * b,
+        ("/", [a, b]) => a / b,
+        ("^", [a, b]) => spow(*a, *b),
+        ("sin", [a]) => a.sin(),
+        ("cos", [a]) => a.cos(),
+        ("tan", [a]) => a.tan(),
+        ("log", [a]) => a.ln(),
+        ("abs", [a]) => a.abs(),
+        ("pi", []) => std::f32::consts::PI,
+        _ => return None,
+    };
+    Some(value)
+}
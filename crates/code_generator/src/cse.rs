@@ -0,0 +1,124 @@
+//! Common subexpression elimination.
+//!
+//! Subtrees of a program that occur more than once are evaluated once: the first occurrence
+//! stores its value in a local slot with `OP_TEE`, the others are replaced by `OP_LOAD`. A
+//! value that is reused right away is duplicated with `OP_DUP` instead
+
+use std::collections::HashMap;
+
+use mth_common::{N_LOCALS, inst, ops::*};
+
+use crate::optimize::{Node, emit, to_tree};
+
+/// Structural identity of a subtree: its instructions, with the bits of their arguments
+type Key = Vec<(u32, u32)>;
+
+/// Evaluate repeated subtrees of `program` once. Programs that the optimizer can't turn into
+/// a tree are returned unchanged
+pub fn eliminate_common_subexpressions(program: &[Instruction]) -> Vec<Instruction> {
+    let Some(tree) = to_tree(program) else {
+        return program.to_vec();
+    };
+
+    let mut counts = HashMap::new();
+    count(&tree, &mut counts);
+
+    let mut emitter = Emitter {
+        counts,
+        slots: HashMap::new(),
+        out: Vec::with_capacity(program.len()),
+    };
+    emitter.emit(&tree);
+    use_dup(emitter.out)
+}
+
+fn key(node: &Node) -> Key {
+    let mut instructions = Vec::new();
+    emit(node, &mut instructions);
+    instructions
+        .iter()
+        .map(|op| (op.opcode, op.a.to_bits()))
+        .collect()
+}
+
+/// Constants and inputs are as cheap to push again as to load
+fn is_leaf(node: &Node) -> bool {
+    matches!(node, Node::Const(_)) || matches!(node, Node::Op(_, args) if args.is_empty())
+}
+
+/// Count the occurrences of every subtree. Repeated subtrees are only descended into once,
+/// since the others are loaded as a whole
+fn count(node: &Node, counts: &mut HashMap<Key, usize>) {
+    if is_leaf(node) {
+        return;
+    }
+    let n = counts.entry(key(node)).or_default();
+    *n += 1;
+    if *n == 1
+        && let Node::Op(_, args) = node
+    {
+        for arg in args {
+            count(arg, counts);
+        }
+    }
+}
+
+struct Emitter {
+    counts: HashMap<Key, usize>,
+
+    /// Local slots of the repeated subtrees that were evaluated already
+    slots: HashMap<Key, u32>,
+
+    out: Vec<Instruction>,
+}
+
+impl Emitter {
+    fn emit(&mut self, node: &Node) {
+        let Node::Op(op, args) = node else {
+            emit(node, &mut self.out);
+            return;
+        };
+
+        let key = (!is_leaf(node))
+            .then(|| key(node))
+            .filter(|key| self.counts[key] > 1);
+        if let Some(slot) = key.as_ref().and_then(|key| self.slots.get(key)) {
+            self.out.push(inst!(OP_LOAD, *slot as f32));
+            return;
+        }
+
+        for arg in args {
+            self.emit(arg);
+        }
+        self.out.push(*op);
+
+        // Without a free slot, the subtree is evaluated again
+        if let Some(key) = key
+            && self.slots.len() < N_LOCALS
+        {
+            let slot = self.slots.len() as u32;
+            self.slots.insert(key, slot);
+            self.out.push(inst!(OP_TEE, slot as f32));
+        }
+    }
+}
+
+/// Replace `TEE s, LOAD s` by `DUP` for slots that are loaded only there
+fn use_dup(program: Vec<Instruction>) -> Vec<Instruction> {
+    let mut loads: HashMap<u32, usize> = HashMap::new();
+    for op in &program {
+        if op.opcode == OP_LOAD {
+            *loads.entry(op.a.to_bits()).or_default() += 1;
+        }
+    }
+
+    let mut out = Vec::with_capacity(program.len());
+    let mut ops = program.into_iter().peekable();
+    while let Some(op) = ops.next() {
+        let reused_once = op.opcode == OP_TEE
+            && loads.get(&op.a.to_bits()) == Some(&1)
+            && ops.next_if(|next| *next == inst!(OP_LOAD, op.a)).is_some();
+        out.push(if reused_once { inst!(OP_DUP) } else { op });
+    }
+    out
+}
@@ -0,0 +1,76 @@
+//! Stack depth of programs, and the order of operands that keeps it small.
+//!
+//! Evaluating the operand that needs the deeper stack first leaves only one value on the stack
+//! while the other operand is evaluated (Sethi–Ullman numbering). Operands of commutative
+//! operations are swapped freely, `<` and `>` (`<=` and `>=`) are swapped along with theirs
+
+use mth_common::ops::*;
+
+use crate::optimize::{Node, emit, to_tree};
+
+/// The most values on the stack at any point of `program`. Unknown opcodes are skipped, like
+/// by the VM
+pub fn max_stack_depth(program: &[Instruction]) -> usize {
+    let mut depth: usize = 0;
+    let mut max = 0;
+    for op in program {
+        if let Some(info) = op_info(op.opcode) {
+            depth = depth.saturating_sub(info.pops as usize) + info.pushes() as usize;
+            max = max.max(depth);
+        }
+    }
+    max
+}
+
+/// Reorder the operands of `program` to minimize its stack depth. Programs that the optimizer
+/// can't turn into a tree are returned unchanged
+pub fn reorder_operands(program: &[Instruction]) -> Vec<Instruction> {
+    let Some(tree) = to_tree(program) else {
+        return program.to_vec();
+    };
+
+    let (tree, _) = reorder(tree);
+    let mut out = Vec::with_capacity(program.len());
+    emit(&tree, &mut out);
+    out
+}
+
+/// The reordered node, and the stack depth it needs
+fn reorder(node: Node) -> (Node, usize) {
+    let Node::Op(mut op, args) = node else {
+        return (node, 1);
+    };
+    let (mut args, mut needs): (Vec<Node>, Vec<usize>) = args.into_iter().map(reorder).unzip();
+
+    if let [left, right] = needs[..]
+        && right > left
+        && let Some(swapped) = swapped_opcode(op.opcode)
+    {
+        op.opcode = swapped;
+        args.swap(0, 1);
+        needs.swap(0, 1);
+    }
+
+    // The i-th operand is evaluated on top of the i values before it
+    let need = needs
+        .iter()
+        .enumerate()
+        .map(|(i, need)| i + need)
+        .max()
+        .unwrap_or(1);
+    (Node::Op(op, args), need)
+}
+
+/// The opcode that computes the same value with the operands of `opcode` swapped
+fn swapped_opcode(opcode: u32) -> Option<u32> {
+    match opcode {
+        OP_ADD | OP_MUL | OP_EQ | OP_NE | OP_AND | OP_OR | OP_BW_OR | OP_BW_XOR | OP_BW_AND => {
+            Some(opcode)
+        }
+        OP_LT => Some(OP_GT),
+        OP_GT => Some(OP_LT),
+        OP_LE => Some(OP_GE),
+        OP_GE => Some(OP_LE),
+        _ => None,
+    }
+}
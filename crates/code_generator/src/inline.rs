@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+
+use mth_ast::{Expr, FunctionCall, Lambda, NamedArg, Param};
+
+pub type Bindings<'s> = HashMap<&'s str, Expr<'s>>;
+
+/// Pair up parameters with the expressions passed for them
+pub fn bind<'s>(params: &[Param<'s>], args: impl IntoIterator<Item = Expr<'s>>) -> Bindings<'s> {
+    params
+        .iter()
+        .map(|Param(name, _)| *name)
+        .zip(args)
+        .collect()
+}
+
+/// Replace every reference to a bound parameter in `expr` by its bound expression.
+///
+/// Bound expressions are inserted as they are and not substituted again, so arguments that
+/// mention names of the callee's parameters keep referring to the caller's meaning.
+pub fn substitute<'s>(expr: &Expr<'s>, bindings: &Bindings<'s>) -> Expr<'s> {
+    match expr {
+        Expr::Literal(..) => expr.clone(),
+        Expr::Lambda(Lambda { params, body, span }) => {
+            // The lambda's own parameters shadow outer bindings
+            let mut inner = bindings.clone();
+            for Param(name, _) in params {
+                inner.remove(name);
+            }
+            Expr::Lambda(Lambda {
+                params: params.clone(),
+                body: Box::new(substitute(body, &inner)),
+                span: *span,
+            })
+        }
+        Expr::List(items, span) => Expr::List(
+            items
+                .iter()
+                .map(|item| substitute(item, bindings))
+                .collect(),
+            *span,
+        ),
+        Expr::FunctionCall(FunctionCall {
+            name,
+            args,
+            named_args,
+            is_negated,
+            span,
+        }) => {
+            if args.is_empty()
+                && let Some(bound) = bindings.get(name)
+            {
+                return if *is_negated {
+                    -bound.clone()
+                } else {
+                    bound.clone()
+                };
+            }
+
+            Expr::FunctionCall(FunctionCall {
+                name,
+                args: args.iter().map(|arg| substitute(arg, bindings)).collect(),
+                named_args: named_args
+                    .iter()
+                    .map(|arg| NamedArg {
+                        value: substitute(&arg.value, bindings),
+                        ..arg.clone()
+                    })
+                    .collect(),
+                is_negated: *is_negated,
+                span: *span,
+            })
+        }
+    }
+}
@@ -1,5 +1,27 @@
 mod codegen;
-pub use codegen::{compile_fn, compile_module};
+#[cfg(feature = "test-support")]
+pub use codegen::compile_source;
+pub use codegen::{
+    CompiledModule, Ctx, Slider, compile_fn, compile_function, compile_module, compile_plot,
+};
+
+mod consteval;
+mod inline;
+mod symbols;
+
+mod style;
+pub use style::{PALETTE, REGION_FILL};
+
+mod cse;
+pub use cse::eliminate_common_subexpressions;
+
+mod depth;
+pub use depth::{max_stack_depth, reorder_operands};
+
+mod optimize;
+pub use optimize::optimize;
+
+pub mod typeck;
 
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,132 @@
+//! Simplification of compiled programs: constant folding and algebraic identities.
+//!
+//! A program is turned back into the expression tree it was compiled from, simplified bottom
+//! up and compiled again. Folding happens in `f32` like on the GPU, so the optimized program
+//! computes the same values as the original one
+
+use mth_common::{inst, ops::*, spow};
+
+/// An expression of the VM, the operands of an instruction are the values it pops
+#[derive(Debug)]
+pub(crate) enum Node {
+    Const(f32),
+    Op(Instruction, Vec<Node>),
+}
+
+/// Simplify `program`. Programs that [`to_tree`] can't handle are returned unchanged
+pub fn optimize(program: &[Instruction]) -> Vec<Instruction> {
+    let Some(tree) = to_tree(program) else {
+        return program.to_vec();
+    };
+
+    let mut out = Vec::with_capacity(program.len());
+    emit(&simplify(tree), &mut out);
+    out
+}
+
+/// The expression tree of `program`, if it only contains opcodes without side effects that
+/// push one value, and leaves exactly one value on the stack
+pub(crate) fn to_tree(program: &[Instruction]) -> Option<Node> {
+    let mut stack = Vec::new();
+    for op in program {
+        let info = op_info(op.opcode)?;
+        if info.pushes() != 1 || !info.effect.is_empty() {
+            return None;
+        }
+        let base = stack.len().checked_sub(info.pops as usize)?;
+        let args = stack.split_off(base);
+
+        stack.push(match op.opcode {
+            OP_CONST => Node::Const(op.a),
+            _ => Node::Op(*op, args),
+        });
+    }
+
+    let tree = stack.pop()?;
+    stack.is_empty().then_some(tree)
+}
+
+pub(crate) fn emit(node: &Node, out: &mut Vec<Instruction>) {
+    match node {
+        Node::Const(value) => out.push(inst!(OP_CONST, *value)),
+        Node::Op(op, args) => {
+            for arg in args {
+                emit(arg, out);
+            }
+            out.push(*op);
+        }
+    }
+}
+
+fn simplify(node: Node) -> Node {
+    let Node::Op(op, args) = node else {
+        return node;
+    };
+    let mut args: Vec<Node> = args.into_iter().map(simplify).collect();
+
+    if let Some(value) = fold(op.opcode, &args) {
+        return Node::Const(value);
+    }
+
+    let neg = |node| simplify(Node::Op(inst!(OP_NEG), vec![node]));
+    match (op.opcode, args.as_slice()) {
+        // x + 0, x - 0, x * 1, x / 1, x ^ 1
+        (OP_ADD | OP_SUB, [_, Node::Const(0.0)])
+        | (OP_MUL | OP_DIV | OP_POW, [_, Node::Const(1.0)]) => args.remove(0),
+        // 0 + x, 1 * x
+        (OP_ADD, [Node::Const(0.0), _]) | (OP_MUL, [Node::Const(1.0), _]) => args.remove(1),
+        // x * -1, x / -1
+        (OP_MUL | OP_DIV, [_, Node::Const(-1.0)]) => neg(args.remove(0)),
+        // -1 * x, 0 - x
+        (OP_MUL, [Node::Const(-1.0), _]) | (OP_SUB, [Node::Const(0.0), _]) => neg(args.remove(1)),
+        // x + -y, x - -y
+        (OP_ADD | OP_SUB, [_, b]) if is_neg(b) => {
+            let opcode = if op.opcode == OP_ADD { OP_SUB } else { OP_ADD };
+            let b = operand(args.remove(1));
+            Node::Op(inst!(opcode), vec![args.remove(0), b])
+        }
+        // --x
+        (OP_NEG, [a]) if is_neg(a) => operand(args.remove(0)),
+        _ => Node::Op(op, args),
+    }
+}
+
+fn is_neg(node: &Node) -> bool {
+    matches!(node, Node::Op(op, _) if op.opcode == OP_NEG)
+}
+
+/// The only operand of a unary operation
+fn operand(node: Node) -> Node {
+    match node {
+        Node::Op(_, mut args) if args.len() == 1 => args.remove(0),
+        _ => unreachable!("not a unary operation: {node:?}"),
+    }
+}
+
+/// The value of `opcode` applied to constant `args`, for the arithmetic opcodes. Results that
+/// aren't finite are left to the GPU
+fn fold(opcode: u32, args: &[Node]) -> Option<f32> {
+    let args: Vec<f32> = args
+        .iter()
+        .map(|arg| match arg {
+            Node::Const(value) => Some(*value),
+            Node::Op(..) => None,
+        })
+        .collect::<Option<_>>()?;
+
+    let value = match (opcode, args.as_slice()) {
+        (OP_ADD, [a, b]) => a + b,
+        (OP_SUB, [a, b]) => a - b,
+        (OP_MUL, [a, b]) => a * b,
+        (OP_DIV, [a, b]) => a / b,
+        (OP_POW, [a, b]) => spow(*a, *b),
+        (OP_NEG, [a]) => -a,
+        (OP_ABS, [a]) => a.abs(),
+        (OP_COS, [a]) => a.cos(),
+        (OP_SIN, [a]) => a.sin(),
+        (OP_TAN, [a]) => a.tan(),
+        (OP_LOG, [a]) => a.ln(),
+        _ => return None,
+    };
+    value.is_finite().then_some(value)
+}
@@ -0,0 +1,213 @@
+//! Named arguments of the plotting builtins, which change how plots are drawn:
+//! `plot(f, color = "red", width = 2, dash = [4, 2], fill = 0.3)`
+
+use mth_ast::{Expr, FunctionCall, Literal, NamedArg};
+use mth_common::PLOT_TYPE_EQUATION;
+use mth_diagnostics::{Diagnostic, codes};
+
+use crate::{Ctx, consteval::const_eval};
+
+/// Colors of plots without a `color`, cycling with the index of the plot. The first plot
+/// keeps the white of plots before styles existed
+pub const PALETTE: [[f32; 3]; 6] = [
+    rgb(0xffffff),
+    rgb(0xf06060),
+    rgb(0x4da6ff),
+    rgb(0x5ad17a),
+    rgb(0xffb347),
+    rgb(0xb48cff),
+];
+
+/// Opacity of the regions where conditions hold, for plots without a `fill`
+pub const REGION_FILL: f32 = 0.4;
+
+/// Names of the options, in the order they are documented
+const OPTIONS: [&str; 4] = ["color", "width", "dash", "fill"];
+
+/// Colors that can be given by name, the colors of the palette among them
+const NAMED_COLORS: &[(&str, [f32; 3])] = &[
+    ("white", PALETTE[0]),
+    ("red", PALETTE[1]),
+    ("blue", PALETTE[2]),
+    ("green", PALETTE[3]),
+    ("orange", PALETTE[4]),
+    ("purple", PALETTE[5]),
+    ("black", rgb(0x000000)),
+    ("gray", rgb(0x808080)),
+    ("grey", rgb(0x808080)),
+    ("yellow", rgb(0xffe066)),
+    ("cyan", rgb(0x5ce1e6)),
+    ("magenta", rgb(0xff66d9)),
+    ("pink", rgb(0xffa0c0)),
+];
+
+const fn rgb(hex: u32) -> [f32; 3] {
+    [
+        ((hex >> 16) & 0xff) as f32 / 255.0,
+        ((hex >> 8) & 0xff) as f32 / 255.0,
+        (hex & 0xff) as f32 / 255.0,
+    ]
+}
+
+/// The options given to one call of a plotting builtin, `None` where the default is used
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct Style {
+    pub(crate) color: Option<[f32; 3]>,
+    pub(crate) width: Option<f32>,
+    pub(crate) dash: Option<[f32; 2]>,
+    pub(crate) fill: Option<f32>,
+}
+
+impl Style {
+    /// The color of the `index`-th plot of the module
+    pub(crate) fn color(&self, index: usize) -> [f32; 3] {
+        self.color.unwrap_or(PALETTE[index % PALETTE.len()])
+    }
+
+    /// The opacity of the area of a plot. Only the regions of conditions are filled by default
+    pub(crate) fn fill(&self, type_id: u32) -> f32 {
+        match type_id {
+            PLOT_TYPE_EQUATION => self.fill.unwrap_or(REGION_FILL),
+            _ => self.fill.unwrap_or(0.0),
+        }
+    }
+}
+
+/// Evaluate the named arguments of the plotting builtin call `call`
+pub(crate) fn compile_style(call: &FunctionCall, ctx: &Ctx) -> Result<Style, Diagnostic> {
+    let mut style = Style::default();
+    for (i, arg) in call.named_args.iter().enumerate() {
+        if let Some(first) = call.named_args[..i].iter().find(|a| a.name == arg.name) {
+            return Err(
+                Diagnostic::error(format!("`{}` is given more than once", arg.name))
+                    .with_code(codes::INVALID_OPTION)
+                    .with_primary(arg.span.into(), "given again here")
+                    .with_secondary(first.span.into(), "first given here"),
+            );
+        }
+
+        match arg.name {
+            "color" => {
+                let color =
+                    const_string(&arg.value, ctx).ok_or_else(|| invalid(arg, "not a string"))?;
+                style.color = Some(parse_color(color).ok_or_else(|| {
+                    invalid(arg, "unknown color").with_help(format!(
+                        "use a color like \"#ff8000\" or one of {}",
+                        NAMED_COLORS
+                            .iter()
+                            .map(|(name, _)| format!("\"{name}\""))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ))
+                })?);
+            }
+            "width" => {
+                let width = const_number(&arg.value, ctx).ok_or_else(|| not_constant(arg))?;
+                if !(width.is_finite() && width > 0.0) {
+                    return Err(invalid(arg, "not a positive number"));
+                }
+                style.width = Some(width);
+            }
+            "dash" => {
+                let Expr::List(items, _) = &arg.value else {
+                    return Err(invalid(arg, "not a list").with_help(
+                        "give the lengths of a dash and a gap in pixels, like `dash = [4, 2]`",
+                    ));
+                };
+                let lengths = items
+                    .iter()
+                    .map(|item| const_number(item, ctx))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| not_constant(arg))?;
+                let [on, off] = lengths[..] else {
+                    return Err(invalid(
+                        arg,
+                        format!("expected 2 lengths, got {}", lengths.len()),
+                    ));
+                };
+                if !(on.is_finite() && off.is_finite() && on > 0.0 && off >= 0.0) {
+                    return Err(invalid(
+                        arg,
+                        "the dash must be longer than 0, the gap at least 0",
+                    ));
+                }
+                style.dash = Some([on, off]);
+            }
+            "fill" => {
+                let fill = const_number(&arg.value, ctx).ok_or_else(|| not_constant(arg))?;
+                if !(0.0..=1.0).contains(&fill) {
+                    return Err(invalid(arg, "not between 0 and 1"));
+                }
+                style.fill = Some(fill);
+            }
+            name => {
+                return Err(Diagnostic::error(format!(
+                    "`{}` has no option named `{name}`",
+                    call.name
+                ))
+                .with_code(codes::UNKNOWN_OPTION)
+                .with_primary(arg.span.into(), "unknown option")
+                .with_note(format!(
+                    "the options of `{}` are {}",
+                    call.name,
+                    OPTIONS
+                        .iter()
+                        .map(|option| format!("`{option}`"))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )));
+            }
+        }
+    }
+    Ok(style)
+}
+
+fn invalid(arg: &NamedArg, label: impl Into<String>) -> Diagnostic {
+    Diagnostic::error(format!("Invalid value for the option `{}`", arg.name))
+        .with_code(codes::INVALID_OPTION)
+        .with_primary(arg.value.span().into(), label)
+}
+
+/// `arg` has a value that [`const_number`] can't evaluate
+fn not_constant(arg: &NamedArg) -> Diagnostic {
+    invalid(arg, "not a constant")
+        .with_note("options are evaluated at compile time, they can't depend on `x` or `y`")
+}
+
+fn const_number(expr: &Expr, ctx: &Ctx) -> Option<f32> {
+    const_eval(expr, &ctx.constants)
+}
+
+/// The value of a string literal, or of a definition without parameters that is one
+fn const_string<'e>(expr: &'e Expr, ctx: &'e Ctx) -> Option<&'e str> {
+    match expr {
+        Expr::Literal(Literal::String(s), _) => Some(s),
+        Expr::FunctionCall(FunctionCall {
+            name,
+            args,
+            is_negated: false,
+            ..
+        }) if args.is_empty() => {
+            let f = ctx.functions.get(name)?;
+            if !f.params.is_empty() {
+                return None;
+            }
+            const_string(&f.body, ctx)
+        }
+        _ => None,
+    }
+}
+
+/// A color by name, or in hex notation like `#ff8000`
+fn parse_color(color: &str) -> Option<[f32; 3]> {
+    if let Some(hex) = color.strip_prefix('#') {
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        return u32::from_str_radix(hex, 16).ok().map(rgb);
+    }
+    NAMED_COLORS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(color))
+        .map(|(_, color)| *color)
+}
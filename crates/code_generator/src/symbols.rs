@@ -0,0 +1,369 @@
+use std::collections::HashMap;
+
+use mth_ast::{Expr, Function, FunctionCall, Lambda, Module, Param, Span, TopLevel};
+use mth_common::MAX_PARAMS;
+use mth_diagnostics::{Diagnostic, codes};
+
+use crate::{
+    codegen::{BUILTINS, Ctx, PlotKind, SLIDER, VIEW},
+    consteval::const_eval,
+};
+
+/// Collect the definitions of `module` into a compile context.
+///
+/// Fails on duplicate definitions, redefined builtins, references to undefined names and
+/// cyclic definitions. Sliders get the parameter slots in the order they are declared, and
+/// definitions without parameters whose value is known at compile time are evaluated to
+/// constants.
+pub fn resolve<'a, 's>(module: &'a Module<'s>) -> Result<Ctx<'a, 's>, Diagnostic> {
+    let mut ctx = Ctx::default();
+    let definitions: Vec<&Function> = module
+        .top_level
+        .iter()
+        .filter_map(|top_level| match top_level {
+            TopLevel::Function(f) => Some(f),
+            _ => None,
+        })
+        .collect();
+
+    for f in &definitions {
+        if BUILTINS.contains(&f.name) {
+            return Err(Diagnostic::error(format!(
+                "`{}` is a builtin and cannot be redefined",
+                f.name
+            ))
+            .with_code(codes::BUILTIN_REDEFINITION)
+            .with_primary(f.span.into(), ""));
+        }
+        if let Some(first) = ctx.functions.insert(f.name, f) {
+            return Err(
+                Diagnostic::error(format!("`{}` is defined more than once", f.name))
+                    .with_code(codes::DUPLICATE_DEFINITION)
+                    .with_primary(f.span.into(), "defined again here")
+                    .with_secondary(first.span.into(), "first definition"),
+            );
+        }
+    }
+
+    // Sliders are visible everywhere, unless a parameter has the same name
+    let mut slider_spans: HashMap<&str, Span> = HashMap::new();
+    let mut sliders: Vec<&str> = Vec::new();
+    for top_level in &module.top_level {
+        let TopLevel::Expr(Expr::FunctionCall(call)) = top_level else {
+            continue;
+        };
+        if call.name != SLIDER {
+            continue;
+        }
+        let Some(Expr::FunctionCall(FunctionCall {
+            name,
+            args,
+            named_args,
+            is_negated: false,
+            span,
+        })) = call.args.first()
+        else {
+            continue; // Reported by code generation
+        };
+        if !args.is_empty() || !named_args.is_empty() {
+            continue;
+        }
+
+        if BUILTINS.contains(name) {
+            return Err(
+                Diagnostic::error(format!("`{name}` is a builtin and cannot be a slider"))
+                    .with_code(codes::BUILTIN_REDEFINITION)
+                    .with_primary((*span).into(), ""),
+            );
+        }
+        let first = ctx
+            .functions
+            .get(name)
+            .map(|f| f.span)
+            .or_else(|| slider_spans.get(name).copied());
+        if let Some(first) = first {
+            return Err(
+                Diagnostic::error(format!("`{name}` is defined more than once"))
+                    .with_code(codes::DUPLICATE_DEFINITION)
+                    .with_primary((*span).into(), "defined again here")
+                    .with_secondary(first.into(), "first definition"),
+            );
+        }
+        if sliders.len() == MAX_PARAMS {
+            return Err(Diagnostic::error(format!(
+                "Too many sliders, the GPU has room for at most {MAX_PARAMS}"
+            ))
+            .with_code(codes::TOO_MANY_SLIDERS)
+            .with_primary(call.span.into(), "one slider too many"));
+        }
+        slider_spans.insert(name, *span);
+        ctx.sliders.insert(name, sliders.len() as u32);
+        sliders.push(name);
+    }
+
+    // Undefined names
+    for top_level in &module.top_level {
+        match top_level {
+            TopLevel::Function(f) => {
+                let params = [sliders.as_slice(), &param_names(&f.params)].concat();
+                check_names(&f.body, &params, &ctx.functions).map_err(|call| {
+                    let message = format!(
+                        "Undefined name `{}` in definition of `{}`",
+                        call.name, f.name
+                    );
+                    undefined_name(message, call, &params, &ctx.functions)
+                })?
+            }
+            TopLevel::Expr(Expr::FunctionCall(FunctionCall {
+                name,
+                args,
+                named_args,
+                ..
+            })) if PlotKind::from_name(name).is_some() || *name == VIEW || *name == SLIDER => {
+                // The name of a slider is declared, not referenced
+                let skip = usize::from(*name == SLIDER);
+                for arg in args
+                    .iter()
+                    .skip(skip)
+                    .chain(named_args.iter().map(|arg| &arg.value))
+                {
+                    check_names(arg, &sliders, &ctx.functions).map_err(|call| {
+                        undefined_name(
+                            format!("Undefined name `{}`", call.name),
+                            call,
+                            &sliders,
+                            &ctx.functions,
+                        )
+                    })?;
+                }
+            }
+            TopLevel::Expr(expr) => {
+                check_names(expr, &sliders, &ctx.functions).map_err(|call| {
+                    undefined_name(
+                        format!("Undefined name `{}`", call.name),
+                        call,
+                        &sliders,
+                        &ctx.functions,
+                    )
+                })?
+            }
+            TopLevel::TypeDecl(_) | TopLevel::Error(_) => {}
+        }
+    }
+
+    // Cycles, and evaluation of constants in dependency order
+    let mut state = HashMap::new();
+    let mut order = Vec::new();
+    for f in &definitions {
+        visit(
+            f.name,
+            &ctx.functions,
+            &mut state,
+            &mut Vec::new(),
+            &mut order,
+        )?;
+    }
+
+    for name in order {
+        let f = ctx.functions[name];
+        if f.params.is_empty()
+            && let Some(value) = const_eval(&f.body, &ctx.constants)
+        {
+            ctx.constants.insert(name, value);
+        }
+    }
+
+    Ok(ctx)
+}
+
+pub(crate) fn param_names<'s>(params: &[Param<'s>]) -> Vec<&'s str> {
+    params.iter().map(|Param(name, _)| *name).collect()
+}
+
+/// Check that every name referenced in `expr` is a parameter in `scope`, a builtin or a user
+/// function. Returns the first reference to an undefined name
+fn check_names<'e, 's>(
+    expr: &'e Expr<'s>,
+    scope: &[&'s str],
+    functions: &HashMap<&'s str, &Function<'s>>,
+) -> Result<(), &'e FunctionCall<'s>> {
+    match expr {
+        Expr::Literal(..) => Ok(()),
+        Expr::Lambda(Lambda { params, body, .. }) => {
+            let scope = [scope, &param_names(params)].concat();
+            check_names(body, &scope, functions)
+        }
+        Expr::List(items, _) => items
+            .iter()
+            .try_for_each(|item| check_names(item, scope, functions)),
+        Expr::FunctionCall(
+            call @ FunctionCall {
+                name,
+                args,
+                named_args,
+                ..
+            },
+        ) => {
+            let is_param = args.is_empty() && scope.contains(name);
+            if !is_param && !BUILTINS.contains(name) && !functions.contains_key(name) {
+                return Err(call);
+            }
+
+            args.iter()
+                .chain(named_args.iter().map(|arg| &arg.value))
+                .try_for_each(|arg| check_names(arg, scope, functions))
+        }
+    }
+}
+
+fn undefined_name(
+    message: String,
+    call: &FunctionCall,
+    scope: &[&str],
+    functions: &HashMap<&str, &Function>,
+) -> Diagnostic {
+    let diagnostic = Diagnostic::error(message)
+        .with_code(codes::UNDEFINED_NAME)
+        .with_primary(call.span.into(), "not found");
+    let Some(similar) = similar_name(call.name, scope, functions) else {
+        return diagnostic;
+    };
+    let help = format!("a name with a similar spelling exists: `{similar}`");
+    if call.is_negated {
+        // The span starts at the sign, not the name
+        return diagnostic.with_help(help);
+    }
+    let name_span = mth_diagnostics::Span::new(call.span.start, call.span.start + call.name.len());
+    diagnostic.with_suggestion(help, name_span, similar)
+}
+
+/// The visible name closest to the misspelled `name`, if any is close enough
+fn similar_name<'n>(
+    name: &str,
+    scope: &[&'n str],
+    functions: &HashMap<&'n str, &Function>,
+) -> Option<&'n str> {
+    let max_distance = (name.chars().count() / 3).max(1);
+    let mut candidates: Vec<&str> = scope
+        .iter()
+        .chain(functions.keys())
+        .chain(
+            BUILTINS
+                .iter()
+                .filter(|b| b.chars().all(char::is_alphabetic)),
+        )
+        .copied()
+        .collect();
+    candidates.sort_unstable();
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Edit distance between `a` and `b` in chars, where swapping two adjacent chars counts as
+/// one edit
+fn edit_distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    d[0] = (0..=b.len()).collect();
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum VisitState {
+    InProgress,
+    Done,
+}
+
+/// Depth first search over the user functions `name` depends on. Pushes every function to
+/// `order` after its dependencies
+fn visit<'s>(
+    name: &'s str,
+    functions: &HashMap<&'s str, &Function<'s>>,
+    state: &mut HashMap<&'s str, VisitState>,
+    path: &mut Vec<&'s str>,
+    order: &mut Vec<&'s str>,
+) -> Result<(), Diagnostic> {
+    match state.get(name) {
+        Some(VisitState::Done) => return Ok(()),
+        Some(VisitState::InProgress) => {
+            let start = path.iter().position(|n| *n == name).unwrap_or(0);
+            let cycle = path[start..].join(" -> ");
+            return Err(
+                Diagnostic::error(format!("Cyclic definition: {cycle} -> {name}"))
+                    .with_code(codes::CYCLIC_DEFINITION)
+                    .with_primary(functions[name].span.into(), "depends on itself"),
+            );
+        }
+        None => {}
+    }
+
+    state.insert(name, VisitState::InProgress);
+    path.push(name);
+
+    let f = functions[name];
+    let mut deps = Vec::new();
+    dependencies(&f.body, &param_names(&f.params), functions, &mut deps);
+    for dep in deps {
+        visit(dep, functions, state, path, order)?;
+    }
+
+    path.pop();
+    state.insert(name, VisitState::Done);
+    order.push(name);
+    Ok(())
+}
+
+/// User functions referenced in `expr`, in order of appearance
+pub(crate) fn dependencies<'s>(
+    expr: &Expr<'s>,
+    scope: &[&'s str],
+    functions: &HashMap<&'s str, &Function<'s>>,
+    deps: &mut Vec<&'s str>,
+) {
+    match expr {
+        Expr::Literal(..) => {}
+        Expr::Lambda(Lambda { params, body, .. }) => {
+            let scope = [scope, &param_names(params)].concat();
+            dependencies(body, &scope, functions, deps);
+        }
+        Expr::List(items, _) => {
+            for item in items {
+                dependencies(item, scope, functions, deps);
+            }
+        }
+        Expr::FunctionCall(FunctionCall {
+            name,
+            args,
+            named_args,
+            ..
+        }) => {
+            let is_param = args.is_empty() && scope.contains(name);
+            if !is_param && functions.contains_key(name) && !deps.contains(name) {
+                deps.push(name);
+            }
+
+            for arg in args.iter().chain(named_args.iter().map(|arg| &arg.value)) {
+                dependencies(arg, scope, functions, deps);
+            }
+        }
+    }
+}
@@ -1,13 +1,27 @@
-use mth_ast::{function_call, int, Expr, Function, FunctionCall, Literal, Module, TopLevel};
-use mth_common::{inst, ops::*, N_PLOTS, PLOT_TYPE_EQUATION, PLOT_TYPE_FN_GRAPH};
+use std::collections::HashMap;
 
-use crate::codegen::{compile_expr, compile_s_expr};
+use mth_ast::{
+    Expr, Function, FunctionCall, Literal, Module, NamedArg, Param, Span, TopLevel, Type, TypeDecl,
+    function_call, int, lambda, varref,
+};
+use mth_common::{
+    MAX_PARAMS, PLOT_TYPE_EQUATION, PLOT_TYPE_FN_GRAPH, PLOT_TYPE_INVERSE, PLOT_TYPE_PARAMETRIC,
+    PLOT_TYPE_POLAR, STACK_SIZE, inst, ops::*, spow,
+};
+use mth_diagnostics::codes;
+
+use crate::{
+    CompiledModule, PALETTE, REGION_FILL,
+    codegen::{Ctx, compile_expr, compile_s_expr},
+    consteval::const_eval,
+    typeck::{Signature, Ty},
+};
 
 #[test]
 fn test_compile_literal() {
-    let expr = &Expr::Literal(Literal::Int(42));
+    let expr = &Expr::Literal(Literal::Int(42), Span::default());
     let mut buf = Vec::new();
-    let result = compile_expr(expr, &mut buf).unwrap();
+    let result = compile_expr(expr, &mut Ctx::default(), &mut buf).unwrap();
     assert_eq!(result, (1, PLOT_TYPE_FN_GRAPH));
     assert_eq!(buf, vec![inst!(OP_CONST, 42.0)]);
 }
@@ -16,7 +30,7 @@ fn test_compile_literal() {
 fn test_compile_add() {
     let expr = function_call("+", vec![int(1), int(2)]);
     let mut buf = Vec::new();
-    let result = compile_expr(&expr, &mut buf).unwrap();
+    let result = compile_expr(&expr, &mut Ctx::default(), &mut buf).unwrap();
     assert_eq!(result, (3, PLOT_TYPE_FN_GRAPH));
     assert_eq!(
         buf,
@@ -28,7 +42,7 @@ fn test_compile_add() {
 fn test_compile_mul() {
     let expr = function_call("*", vec![int(3), int(4)]);
     let mut buf = Vec::new();
-    let result = compile_expr(&expr, &mut buf).unwrap();
+    let result = compile_expr(&expr, &mut Ctx::default(), &mut buf).unwrap();
     assert_eq!(result, (3, PLOT_TYPE_FN_GRAPH));
     assert_eq!(
         buf,
@@ -40,7 +54,7 @@ fn test_compile_mul() {
 fn test_compile_sub() {
     let expr = function_call("-", vec![int(5), int(2)]);
     let mut buf = Vec::new();
-    let result = compile_expr(&expr, &mut buf).unwrap();
+    let result = compile_expr(&expr, &mut Ctx::default(), &mut buf).unwrap();
     assert_eq!(result, (3, PLOT_TYPE_FN_GRAPH));
     assert_eq!(
         buf,
@@ -52,7 +66,7 @@ fn test_compile_sub() {
 fn test_compile_div() {
     let expr = function_call("/", vec![int(8), int(2)]);
     let mut buf = Vec::new();
-    let result = compile_expr(&expr, &mut buf).unwrap();
+    let result = compile_expr(&expr, &mut Ctx::default(), &mut buf).unwrap();
     assert_eq!(result, (3, PLOT_TYPE_FN_GRAPH));
     assert_eq!(
         buf,
@@ -64,7 +78,7 @@ fn test_compile_div() {
 fn test_compile_sin() {
     let expr = function_call("sin", vec![int(0)]);
     let mut buf = Vec::new();
-    let result = compile_expr(&expr, &mut buf).unwrap();
+    let result = compile_expr(&expr, &mut Ctx::default(), &mut buf).unwrap();
     assert_eq!(result, (2, PLOT_TYPE_FN_GRAPH));
     assert_eq!(buf, vec![inst!(OP_CONST, 0.0), inst!(OP_SIN)]);
 }
@@ -73,7 +87,7 @@ fn test_compile_sin() {
 fn test_compile_cos() {
     let expr = function_call("cos", vec![int(0)]);
     let mut buf = Vec::new();
-    let result = compile_expr(&expr, &mut buf).unwrap();
+    let result = compile_expr(&expr, &mut Ctx::default(), &mut buf).unwrap();
     assert_eq!(result, (2, PLOT_TYPE_FN_GRAPH));
     assert_eq!(buf, vec![inst!(OP_CONST, 0.0), inst!(OP_COS)]);
 }
@@ -82,7 +96,7 @@ fn test_compile_cos() {
 fn test_compile_pow() {
     let expr = function_call("^", vec![int(2), int(3)]);
     let mut buf = Vec::new();
-    let result = compile_expr(&expr, &mut buf).unwrap();
+    let result = compile_expr(&expr, &mut Ctx::default(), &mut buf).unwrap();
     assert_eq!(result, (3, PLOT_TYPE_FN_GRAPH));
     assert_eq!(
         buf,
@@ -95,7 +109,7 @@ fn test_compile_nested_expression() {
     let inner = function_call("+", vec![int(1), int(2)]);
     let expr = function_call("sin", vec![inner]);
     let mut buf = Vec::new();
-    let result = compile_expr(&expr, &mut buf).unwrap();
+    let result = compile_expr(&expr, &mut Ctx::default(), &mut buf).unwrap();
     assert_eq!(result, (4, PLOT_TYPE_FN_GRAPH));
     assert_eq!(
         buf,
@@ -114,7 +128,7 @@ fn test_compile_deeply_nested_expression() {
     let inner_sin = function_call("sin", vec![int(0)]);
     let expr = function_call("^", vec![inner_add, inner_sin]);
     let mut buf = Vec::new();
-    let result = compile_expr(&expr, &mut buf).unwrap();
+    let result = compile_expr(&expr, &mut Ctx::default(), &mut buf).unwrap();
     assert_eq!(result, (6, PLOT_TYPE_FN_GRAPH));
     assert_eq!(
         buf,
@@ -135,7 +149,7 @@ fn test_compile_function_call_direct() {
         panic!("Expected Expr::FunctionCall")
     };
     let mut buf = Vec::new();
-    let result = compile_s_expr(&function_call, &mut buf).unwrap();
+    let result = compile_s_expr(&function_call, &mut Ctx::default(), &mut buf).unwrap();
     assert_eq!(result, (3, PLOT_TYPE_FN_GRAPH));
     assert_eq!(
         buf,
@@ -147,7 +161,7 @@ fn test_compile_function_call_direct() {
 fn test_single_literal_instruction_count() {
     let expr = function_call("sin", vec![int(0)]);
     let mut buf = Vec::new();
-    let result = compile_expr(&expr, &mut buf).unwrap();
+    let result = compile_expr(&expr, &mut Ctx::default(), &mut buf).unwrap();
     assert_eq!(result, (2, PLOT_TYPE_FN_GRAPH));
     assert_eq!(buf[0], inst!(OP_CONST, 0.0));
     assert_eq!(buf[1], inst!(OP_SIN));
@@ -157,7 +171,7 @@ fn test_single_literal_instruction_count() {
 fn test_constant_zero_instruction_count() {
     let expr = int(0);
     let mut buf = Vec::new();
-    let result = compile_expr(&expr, &mut buf).unwrap();
+    let result = compile_expr(&expr, &mut Ctx::default(), &mut buf).unwrap();
     assert_eq!(result, (1, PLOT_TYPE_FN_GRAPH));
     assert_eq!(buf[0], inst!(OP_CONST, 0.0));
 }
@@ -167,7 +181,7 @@ fn test_complex_expression_instruction_count() {
     let inner = function_call("+", vec![int(1), int(2)]);
     let expr = function_call("sin", vec![inner]);
     let mut buf = Vec::new();
-    let result = compile_expr(&expr, &mut buf).unwrap();
+    let result = compile_expr(&expr, &mut Ctx::default(), &mut buf).unwrap();
     assert_eq!(result, (4, PLOT_TYPE_FN_GRAPH));
 }
 
@@ -177,6 +191,8 @@ fn test_compile_mapping_with_literal() {
         name: "a",
         params: vec![],
         body: int(0),
+        span: Span::default(),
+        doc: vec![],
     };
 
     let module = Module {
@@ -188,14 +204,22 @@ fn test_compile_mapping_with_literal() {
                 args: vec![Expr::FunctionCall(FunctionCall {
                     name: "a",
                     args: vec![],
+                    named_args: vec![],
                     is_negated: false,
+                    span: Span::default(),
                 })],
+                named_args: vec![],
                 is_negated: false,
+                span: Span::default(),
             })),
         ],
     };
 
-    let (instructions, plot_descs) = crate::compile_module(&module).unwrap();
+    let CompiledModule {
+        instructions,
+        plot_descs,
+        ..
+    } = crate::compile_module(&module).unwrap();
     assert_eq!(instructions, vec![inst!(OP_CONST, 0.0)]);
     assert_eq!(plot_descs[0].length, 1);
     assert_eq!(plot_descs[0].type_id, PLOT_TYPE_FN_GRAPH);
@@ -207,6 +231,8 @@ fn test_compile_mapping_with_expression() {
         name: "a",
         params: vec![],
         body: function_call("-", vec![int(1), int(1)]),
+        span: Span::default(),
+        doc: vec![],
     };
 
     let module = Module {
@@ -218,19 +244,26 @@ fn test_compile_mapping_with_expression() {
                 args: vec![Expr::FunctionCall(FunctionCall {
                     name: "a",
                     args: vec![],
+                    named_args: vec![],
                     is_negated: false,
+                    span: Span::default(),
                 })],
+                named_args: vec![],
                 is_negated: false,
+                span: Span::default(),
             })),
         ],
     };
 
-    let (instructions, plot_descs) = crate::compile_module(&module).unwrap();
-    assert_eq!(
+    let CompiledModule {
         instructions,
-        vec![inst!(OP_CONST, 1.0), inst!(OP_CONST, 1.0), inst!(OP_SUB)]
-    );
-    assert_eq!(plot_descs[0].length, 3);
+        plot_descs,
+        saved_instructions,
+        ..
+    } = crate::compile_module(&module).unwrap();
+    assert_eq!(instructions, vec![inst!(OP_CONST, 0.0)]);
+    assert_eq!(saved_instructions, 2);
+    assert_eq!(plot_descs[0].length, 1);
     assert_eq!(plot_descs[0].type_id, PLOT_TYPE_FN_GRAPH);
 }
 
@@ -240,6 +273,8 @@ fn test_compile_negated_plot() {
         name: "a",
         params: vec![],
         body: int(5),
+        span: Span::default(),
+        doc: vec![],
     };
 
     let module = Module {
@@ -251,19 +286,24 @@ fn test_compile_negated_plot() {
                 args: vec![Expr::FunctionCall(FunctionCall {
                     name: "a",
                     args: vec![],
+                    named_args: vec![],
                     is_negated: false,
+                    span: Span::default(),
                 })],
+                named_args: vec![],
                 is_negated: true,
+                span: Span::default(),
             })),
         ],
     };
 
-    let (instructions, plot_descs) = crate::compile_module(&module).unwrap();
-    assert_eq!(
+    let CompiledModule {
         instructions,
-        vec![inst!(OP_CONST, 5.0), inst!(OP_CONST, -1.0), inst!(OP_MUL)]
-    );
-    assert_eq!(plot_descs[0].length, 3);
+        plot_descs,
+        ..
+    } = crate::compile_module(&module).unwrap();
+    assert_eq!(instructions, vec![inst!(OP_CONST, -5.0)]);
+    assert_eq!(plot_descs[0].length, 1);
     assert_eq!(plot_descs[0].type_id, PLOT_TYPE_FN_GRAPH);
 }
 
@@ -273,6 +313,8 @@ fn test_compile_equation() {
         name: "eq",
         params: vec![],
         body: function_call("<", vec![int(1), int(2)]),
+        span: Span::default(),
+        doc: vec![],
     };
 
     let module = Module {
@@ -284,14 +326,22 @@ fn test_compile_equation() {
                 args: vec![Expr::FunctionCall(FunctionCall {
                     name: "eq",
                     args: vec![],
+                    named_args: vec![],
                     is_negated: false,
+                    span: Span::default(),
                 })],
+                named_args: vec![],
                 is_negated: false,
+                span: Span::default(),
             })),
         ],
     };
 
-    let (instructions, plot_descs) = crate::compile_module(&module).unwrap();
+    let CompiledModule {
+        instructions,
+        plot_descs,
+        ..
+    } = crate::compile_module(&module).unwrap();
     assert_eq!(
         instructions,
         vec![inst!(OP_CONST, 1.0), inst!(OP_CONST, 2.0), inst!(OP_LT)]
@@ -306,11 +356,15 @@ fn test_compile_multiple_plots() {
         name: "a",
         params: vec![],
         body: int(1),
+        span: Span::default(),
+        doc: vec![],
     };
     let mapping_b = Function {
         name: "b",
         params: vec![],
         body: int(2),
+        span: Span::default(),
+        doc: vec![],
     };
 
     let module = Module {
@@ -323,23 +377,35 @@ fn test_compile_multiple_plots() {
                 args: vec![Expr::FunctionCall(FunctionCall {
                     name: "a",
                     args: vec![],
+                    named_args: vec![],
                     is_negated: false,
+                    span: Span::default(),
                 })],
+                named_args: vec![],
                 is_negated: false,
+                span: Span::default(),
             })),
             TopLevel::Expr(Expr::FunctionCall(FunctionCall {
                 name: "plot",
                 args: vec![Expr::FunctionCall(FunctionCall {
                     name: "b",
                     args: vec![],
+                    named_args: vec![],
                     is_negated: false,
+                    span: Span::default(),
                 })],
+                named_args: vec![],
                 is_negated: false,
+                span: Span::default(),
             })),
         ],
     };
 
-    let (instructions, plot_descs) = crate::compile_module(&module).unwrap();
+    let CompiledModule {
+        instructions,
+        plot_descs,
+        ..
+    } = crate::compile_module(&module).unwrap();
     assert_eq!(
         instructions,
         vec![inst!(OP_CONST, 1.0), inst!(OP_CONST, 2.0)]
@@ -348,8 +414,1818 @@ fn test_compile_multiple_plots() {
     assert_eq!(plot_descs[0].type_id, PLOT_TYPE_FN_GRAPH);
     assert_eq!(plot_descs[1].length, 1);
     assert_eq!(plot_descs[1].type_id, PLOT_TYPE_FN_GRAPH);
-    for i in 2..N_PLOTS {
-        assert_eq!(plot_descs[i].length, 0);
-        assert_eq!(plot_descs[i].type_id, 0);
-    }
+    assert_eq!(plot_descs.len(), 2);
+}
+
+#[test]
+fn test_compile_user_function_call() {
+    let add = Function {
+        name: "add",
+        params: vec![Param::new("a"), Param::new("b")],
+        body: function_call("+", vec![varref("a"), varref("b")]),
+        span: Span::default(),
+        doc: vec![],
+    };
+    let mut ctx = Ctx::default();
+    ctx.functions.insert("add", &add);
+
+    let expr = function_call("add", vec![varref("x"), int(2)]);
+    let mut buf = Vec::new();
+    let result = compile_expr(&expr, &mut ctx, &mut buf).unwrap();
+    assert_eq!(result, (3, PLOT_TYPE_FN_GRAPH));
+    assert_eq!(buf, vec![inst!(OP_X), inst!(OP_CONST, 2.0), inst!(OP_ADD)]);
+}
+
+#[test]
+fn test_compile_argument_named_like_param() {
+    // f(x) = x + 1; f(2 x) must not substitute the `x` of the argument again
+    let f = Function {
+        name: "f",
+        params: vec![Param::new("x")],
+        body: function_call("+", vec![varref("x"), int(1)]),
+        span: Span::default(),
+        doc: vec![],
+    };
+    let mut ctx = Ctx::default();
+    ctx.functions.insert("f", &f);
+
+    let expr = function_call("f", vec![function_call("*", vec![int(2), varref("x")])]);
+    let mut buf = Vec::new();
+    compile_expr(&expr, &mut ctx, &mut buf).unwrap();
+    assert_eq!(
+        buf,
+        vec![
+            inst!(OP_CONST, 2.0),
+            inst!(OP_X),
+            inst!(OP_MUL),
+            inst!(OP_CONST, 1.0),
+            inst!(OP_ADD),
+        ]
+    );
+}
+
+#[test]
+fn test_compile_negated_param() {
+    let neg = Function {
+        name: "neg",
+        params: vec![Param::new("v")],
+        body: -varref("v"),
+        span: Span::default(),
+        doc: vec![],
+    };
+    let mut ctx = Ctx::default();
+    ctx.functions.insert("neg", &neg);
+
+    let expr = function_call("neg", vec![function_call("sin", vec![varref("x")])]);
+    let mut buf = Vec::new();
+    compile_expr(&expr, &mut ctx, &mut buf).unwrap();
+    assert_eq!(buf, vec![inst!(OP_X), inst!(OP_SIN), inst!(OP_NEG),]);
+}
+
+#[test]
+fn test_compile_user_function_wrong_arg_count() {
+    let f = Function {
+        name: "f",
+        params: vec![Param::new("x")],
+        body: varref("x"),
+        span: Span::default(),
+        doc: vec![],
+    };
+    let mut ctx = Ctx::default();
+    ctx.functions.insert("f", &f);
+
+    let expr = function_call("f", vec![int(1), int(2)]);
+    let err = compile_expr(&expr, &mut ctx, &mut Vec::new()).unwrap_err();
+    assert!(
+        err.message.contains("Wrong number of arguments for f"),
+        "{err}"
+    );
+}
+
+#[test]
+fn test_compile_recursive_function() {
+    let f = Function {
+        name: "f",
+        params: vec![Param::new("x")],
+        body: function_call("f", vec![varref("x")]),
+        span: Span::default(),
+        doc: vec![],
+    };
+    let mut ctx = Ctx::default();
+    ctx.functions.insert("f", &f);
+
+    let expr = function_call("f", vec![int(1)]);
+    let err = compile_expr(&expr, &mut ctx, &mut Vec::new()).unwrap_err();
+    assert!(err.message.contains("Recursive call to f"), "{err}");
+}
+
+#[test]
+fn test_compile_mutually_recursive_functions() {
+    let f = Function {
+        name: "f",
+        params: vec![Param::new("x")],
+        body: function_call("g", vec![varref("x")]),
+        span: Span::default(),
+        doc: vec![],
+    };
+    let g = Function {
+        name: "g",
+        params: vec![Param::new("x")],
+        body: function_call("f", vec![varref("x")]),
+        span: Span::default(),
+        doc: vec![],
+    };
+    let mut ctx = Ctx::default();
+    ctx.functions.insert("f", &f);
+    ctx.functions.insert("g", &g);
+
+    let expr = function_call("f", vec![int(1)]);
+    let err = compile_expr(&expr, &mut ctx, &mut Vec::new()).unwrap_err();
+    assert_eq!(err.code, Some(codes::RECURSIVE_CALL));
+    assert_eq!(
+        err.message,
+        "Recursive call to f cannot be inlined: f -> g -> f"
+    );
+}
+
+#[test]
+fn test_compile_call_in_own_arguments() {
+    // h(x) = x * 2; g(a, b) = a - b;
+    let h = Function {
+        name: "h",
+        params: vec![Param::new("x")],
+        body: function_call("*", vec![varref("x"), int(2)]),
+        span: Span::default(),
+        doc: vec![],
+    };
+    let g = Function {
+        name: "g",
+        params: vec![Param::new("a"), Param::new("b")],
+        body: function_call("-", vec![varref("a"), varref("b")]),
+        span: Span::default(),
+        doc: vec![],
+    };
+    let mut ctx = Ctx::default();
+    ctx.functions.insert("h", &h);
+    ctx.functions.insert("g", &g);
+
+    // h(h(x))
+    let mut buf = Vec::new();
+    let expr = function_call("h", vec![function_call("h", vec![varref("x")])]);
+    compile_expr(&expr, &mut ctx, &mut buf).unwrap();
+    assert_eq!(
+        buf,
+        vec![
+            inst!(OP_X),
+            inst!(OP_CONST, 2.0),
+            inst!(OP_MUL),
+            inst!(OP_CONST, 2.0),
+            inst!(OP_MUL),
+        ]
+    );
+
+    // g(h(x), h(1))
+    let mut buf = Vec::new();
+    let expr = function_call(
+        "g",
+        vec![
+            function_call("h", vec![varref("x")]),
+            function_call("h", vec![int(1)]),
+        ],
+    );
+    compile_expr(&expr, &mut ctx, &mut buf).unwrap();
+    assert_eq!(
+        buf,
+        vec![
+            inst!(OP_X),
+            inst!(OP_CONST, 2.0),
+            inst!(OP_MUL),
+            inst!(OP_CONST, 1.0),
+            inst!(OP_CONST, 2.0),
+            inst!(OP_MUL),
+            inst!(OP_SUB),
+        ]
+    );
+}
+
+#[test]
+fn test_compile_composed_plot() {
+    // f(x) = x^2; g(x) = f(2x) + f(x - 1); plot(g);
+    let module = Module {
+        name: None,
+        top_level: vec![
+            TopLevel::Function(Function {
+                name: "f",
+                params: vec![Param::new("x")],
+                body: function_call("^", vec![varref("x"), int(2)]),
+                span: Span::default(),
+                doc: vec![],
+            }),
+            TopLevel::Function(Function {
+                name: "g",
+                params: vec![Param::new("x")],
+                body: function_call(
+                    "+",
+                    vec![
+                        function_call("f", vec![function_call("*", vec![int(2), varref("x")])]),
+                        function_call("f", vec![function_call("-", vec![varref("x"), int(1)])]),
+                    ],
+                ),
+                span: Span::default(),
+                doc: vec![],
+            }),
+            TopLevel::Expr(function_call("plot", vec![varref("g")])),
+        ],
+    };
+
+    let CompiledModule {
+        instructions,
+        plot_descs,
+        ..
+    } = crate::compile_module(&module).unwrap();
+    assert_eq!(
+        instructions,
+        vec![
+            inst!(OP_CONST, 2.0),
+            inst!(OP_X),
+            inst!(OP_MUL),
+            inst!(OP_CONST, 2.0),
+            inst!(OP_POW),
+            inst!(OP_X),
+            inst!(OP_CONST, 1.0),
+            inst!(OP_SUB),
+            inst!(OP_CONST, 2.0),
+            inst!(OP_POW),
+            inst!(OP_ADD),
+        ]
+    );
+    assert_eq!(plot_descs[0].length, 11);
+    assert_eq!(plot_descs[0].type_id, PLOT_TYPE_FN_GRAPH);
+}
+
+#[test]
+fn test_compile_plot_binds_params_to_inputs() {
+    // h(a, b) = a < b; plot(h);
+    let module = Module {
+        name: None,
+        top_level: vec![
+            TopLevel::Function(Function {
+                name: "h",
+                params: vec![Param::new("a"), Param::new("b")],
+                body: function_call("<", vec![varref("a"), varref("b")]),
+                span: Span::default(),
+                doc: vec![],
+            }),
+            TopLevel::Expr(function_call("plot", vec![varref("h")])),
+        ],
+    };
+
+    let CompiledModule {
+        instructions,
+        plot_descs,
+        ..
+    } = crate::compile_module(&module).unwrap();
+    assert_eq!(instructions, vec![inst!(OP_X), inst!(OP_Y), inst!(OP_LT)]);
+    assert_eq!(plot_descs[0].type_id, PLOT_TYPE_EQUATION);
+}
+
+#[test]
+fn test_compile_global_constant() {
+    // a = 5; b = 2 a; f(x) = b x; plot(f);
+    let module = Module {
+        name: None,
+        top_level: vec![
+            TopLevel::Function(Function {
+                name: "a",
+                params: vec![],
+                body: int(5),
+                span: Span::default(),
+                doc: vec![],
+            }),
+            TopLevel::Function(Function {
+                name: "b",
+                params: vec![],
+                body: function_call("*", vec![int(2), varref("a")]),
+                span: Span::default(),
+                doc: vec![],
+            }),
+            TopLevel::Function(Function {
+                name: "f",
+                params: vec![Param::new("x")],
+                body: function_call("*", vec![varref("b"), varref("x")]),
+                span: Span::default(),
+                doc: vec![],
+            }),
+            TopLevel::Expr(function_call("plot", vec![varref("f")])),
+        ],
+    };
+
+    let CompiledModule {
+        instructions,
+        plot_descs,
+        ..
+    } = crate::compile_module(&module).unwrap();
+    assert_eq!(
+        instructions,
+        vec![inst!(OP_CONST, 10.0), inst!(OP_X), inst!(OP_MUL)]
+    );
+    assert_eq!(plot_descs[0].length, 3);
+}
+
+#[test]
+fn test_compile_negated_constant() {
+    // a = pi / 2; f(x) = -a; plot(f);
+    let module = Module {
+        name: None,
+        top_level: vec![
+            TopLevel::Function(Function {
+                name: "a",
+                params: vec![],
+                body: function_call("/", vec![varref("pi"), int(2)]),
+                span: Span::default(),
+                doc: vec![],
+            }),
+            TopLevel::Function(Function {
+                name: "f",
+                params: vec![Param::new("x")],
+                body: -varref("a"),
+                span: Span::default(),
+                doc: vec![],
+            }),
+            TopLevel::Expr(function_call("plot", vec![varref("f")])),
+        ],
+    };
+
+    let CompiledModule { instructions, .. } = crate::compile_module(&module).unwrap();
+    assert_eq!(
+        instructions,
+        vec![inst!(OP_CONST, -std::f32::consts::FRAC_PI_2)]
+    );
+}
+
+#[test]
+fn test_const_eval_folds_in_f32() {
+    // sin(0.1) ^ 3
+    let expr = function_call(
+        "^",
+        vec![
+            function_call(
+                "sin",
+                vec![Expr::Literal(Literal::Float(0.1), Span::default())],
+            ),
+            int(3),
+        ],
+    );
+    assert_eq!(
+        const_eval(&expr, &HashMap::new()),
+        Some(spow(0.1_f32.sin(), 3.0))
+    );
+}
+
+#[test]
+fn test_compile_variable_depending_on_input() {
+    // s = sin(x); f(x) = 2 s; plot(f);
+    let module = Module {
+        name: None,
+        top_level: vec![
+            TopLevel::Function(Function {
+                name: "s",
+                params: vec![],
+                body: function_call("sin", vec![varref("x")]),
+                span: Span::default(),
+                doc: vec![],
+            }),
+            TopLevel::Function(Function {
+                name: "f",
+                params: vec![Param::new("x")],
+                body: function_call("*", vec![int(2), varref("s")]),
+                span: Span::default(),
+                doc: vec![],
+            }),
+            TopLevel::Expr(function_call("plot", vec![varref("f")])),
+        ],
+    };
+
+    let CompiledModule { instructions, .. } = crate::compile_module(&module).unwrap();
+    assert_eq!(
+        instructions,
+        vec![
+            inst!(OP_CONST, 2.0),
+            inst!(OP_X),
+            inst!(OP_SIN),
+            inst!(OP_MUL)
+        ]
+    );
+}
+
+#[test]
+fn test_compile_undefined_name() {
+    let module = Module {
+        name: None,
+        top_level: vec![
+            TopLevel::Function(Function {
+                name: "f",
+                params: vec![Param::new("x")],
+                body: function_call("*", vec![varref("a"), varref("x")]),
+                span: Span::default(),
+                doc: vec![],
+            }),
+            TopLevel::Expr(function_call("plot", vec![varref("f")])),
+        ],
+    };
+
+    let err = crate::compile_module(&module).unwrap_err();
+    assert_eq!(err.message, "Undefined name `a` in definition of `f`");
+    assert_eq!(err.code, Some(codes::UNDEFINED_NAME));
+}
+
+#[test]
+fn test_compile_undefined_name_similar() {
+    let module = Module {
+        name: None,
+        top_level: vec![TopLevel::Expr(function_call(
+            "plot",
+            vec![function_call("sni", vec![varref("x")])],
+        ))],
+    };
+
+    let err = crate::compile_module(&module).unwrap_err();
+    assert_eq!(err.message, "Undefined name `sni`");
+    assert_eq!(
+        err.suggestions[0].message,
+        "a name with a similar spelling exists: `sin`"
+    );
+}
+
+#[test]
+fn test_compile_cyclic_definition() {
+    let module = Module {
+        name: None,
+        top_level: vec![
+            TopLevel::Function(Function {
+                name: "a",
+                params: vec![],
+                body: function_call("+", vec![varref("b"), int(1)]),
+                span: Span::default(),
+                doc: vec![],
+            }),
+            TopLevel::Function(Function {
+                name: "b",
+                params: vec![],
+                body: varref("a"),
+                span: Span::default(),
+                doc: vec![],
+            }),
+            TopLevel::Expr(function_call("plot", vec![varref("a")])),
+        ],
+    };
+
+    let err = crate::compile_module(&module).unwrap_err();
+    assert_eq!(err.message, "Cyclic definition: a -> b -> a");
+    assert_eq!(err.code, Some(codes::CYCLIC_DEFINITION));
+}
+
+#[test]
+fn test_compile_redefined_builtin() {
+    let module = Module {
+        name: None,
+        top_level: vec![TopLevel::Function(Function {
+            name: "pi",
+            params: vec![],
+            body: int(3),
+            span: Span::default(),
+            doc: vec![],
+        })],
+    };
+
+    let err = crate::compile_module(&module).unwrap_err();
+    assert_eq!(err.message, "`pi` is a builtin and cannot be redefined");
+}
+
+#[test]
+fn test_compile_plot_inline_expression() {
+    // plot(sin(x) * x);
+    let module = Module {
+        name: None,
+        top_level: vec![TopLevel::Expr(function_call(
+            "plot",
+            vec![function_call(
+                "*",
+                vec![function_call("sin", vec![varref("x")]), varref("x")],
+            )],
+        ))],
+    };
+
+    let CompiledModule {
+        instructions,
+        plot_descs,
+        ..
+    } = crate::compile_module(&module).unwrap();
+    assert_eq!(
+        instructions,
+        vec![inst!(OP_X), inst!(OP_SIN), inst!(OP_X), inst!(OP_MUL)]
+    );
+    assert_eq!(plot_descs[0].length, 4);
+    assert_eq!(plot_descs[0].type_id, PLOT_TYPE_FN_GRAPH);
+}
+
+#[test]
+fn test_compile_plot_inline_equation() {
+    // plot(x^2 + y^2 == 1);
+    let module = Module {
+        name: None,
+        top_level: vec![TopLevel::Expr(function_call(
+            "plot",
+            vec![function_call(
+                "==",
+                vec![
+                    function_call(
+                        "+",
+                        vec![
+                            function_call("^", vec![varref("x"), int(2)]),
+                            function_call("^", vec![varref("y"), int(2)]),
+                        ],
+                    ),
+                    int(1),
+                ],
+            )],
+        ))],
+    };
+
+    let CompiledModule {
+        instructions,
+        plot_descs,
+        ..
+    } = crate::compile_module(&module).unwrap();
+    assert_eq!(instructions.len(), 9);
+    assert_eq!(instructions[8], inst!(OP_EQ));
+    assert_eq!(plot_descs[0].length, 9);
+    assert_eq!(plot_descs[0].type_id, PLOT_TYPE_EQUATION);
+}
+
+#[test]
+fn test_compile_plot_lambda() {
+    // plot(t -> 2 t);
+    let module = Module {
+        name: None,
+        top_level: vec![TopLevel::Expr(function_call(
+            "plot",
+            vec![lambda(
+                &["t"],
+                function_call("*", vec![int(2), varref("t")]),
+            )],
+        ))],
+    };
+
+    let CompiledModule { instructions, .. } = crate::compile_module(&module).unwrap();
+    assert_eq!(
+        instructions,
+        vec![inst!(OP_CONST, 2.0), inst!(OP_X), inst!(OP_MUL)]
+    );
+}
+
+#[test]
+fn test_compile_plot_multiple_arguments() {
+    // f(x) = x; plot(f, -f, 2);
+    let module = Module {
+        name: None,
+        top_level: vec![
+            TopLevel::Function(Function {
+                name: "f",
+                params: vec![Param::new("x")],
+                body: varref("x"),
+                span: Span::default(),
+                doc: vec![],
+            }),
+            TopLevel::Expr(function_call(
+                "plot",
+                vec![varref("f"), -varref("f"), int(2)],
+            )),
+        ],
+    };
+
+    let CompiledModule {
+        instructions,
+        plot_descs,
+        ..
+    } = crate::compile_module(&module).unwrap();
+    assert_eq!(
+        instructions,
+        vec![
+            inst!(OP_X),
+            inst!(OP_X),
+            inst!(OP_NEG),
+            inst!(OP_CONST, 2.0),
+        ]
+    );
+    assert_eq!(plot_descs[0].length, 1);
+    assert_eq!(plot_descs[1].length, 2);
+    assert_eq!(plot_descs[2].length, 1);
+    assert_eq!(plot_descs.len(), 3);
+}
+
+#[test]
+fn test_compile_plot_without_arguments() {
+    let module = Module {
+        name: None,
+        top_level: vec![TopLevel::Expr(function_call("plot", vec![]))],
+    };
+
+    let err = crate::compile_module(&module).unwrap_err();
+    assert_eq!(err.message, "`plot` requires at least one argument");
+}
+
+#[test]
+fn test_compile_many_plots() {
+    let module = Module {
+        name: None,
+        top_level: vec![TopLevel::Expr(function_call(
+            "plot",
+            (0..100).map(int).collect(),
+        ))],
+    };
+
+    let CompiledModule {
+        instructions,
+        plot_descs,
+        ..
+    } = crate::compile_module(&module).unwrap();
+    assert_eq!(instructions.len(), 100);
+    assert_eq!(plot_descs.len(), 100);
+    assert_eq!(instructions[99], inst!(OP_CONST, 99.0));
+}
+
+#[test]
+fn test_compile_lambda_outside_plot() {
+    let module = Module {
+        name: None,
+        top_level: vec![
+            TopLevel::Function(Function {
+                name: "f",
+                params: vec![],
+                body: lambda(&["t"], varref("t")),
+                span: Span::default(),
+                doc: vec![],
+            }),
+            TopLevel::Expr(function_call("plot", vec![varref("f")])),
+        ],
+    };
+
+    let err = crate::compile_module(&module).unwrap_err();
+    assert_eq!(
+        err.message,
+        "Lambdas can only be used as arguments of `plot`"
+    );
+}
+
+#[test]
+fn test_compile_skips_error_nodes() {
+    // f(x) = sin(x;
+    // plot(x);
+    let module = Module {
+        name: None,
+        top_level: vec![
+            TopLevel::Error(Span::default()),
+            TopLevel::Expr(function_call("plot", vec![varref("x")])),
+        ],
+    };
+
+    let CompiledModule {
+        instructions,
+        plot_descs,
+        ..
+    } = crate::compile_module(&module).unwrap();
+    assert_eq!(instructions, vec![inst!(OP_X)]);
+    assert_eq!(plot_descs[0].length, 1);
+}
+
+#[test]
+fn test_compile_error_span() {
+    // plot(sin(x, y));
+    let span = Span {
+        start: 5,
+        end: 14,
+        line: 1,
+        col: 6,
+    };
+    let call = function_call("sin", vec![varref("x"), varref("y")]).with_span(span);
+    let module = Module {
+        name: None,
+        top_level: vec![TopLevel::Expr(function_call("plot", vec![call]))],
+    };
+
+    let err = crate::compile_module(&module).unwrap_err();
+    assert_eq!(err.code, Some(codes::WRONG_ARGUMENT_COUNT));
+    assert_eq!(err.primary_span(), Some(mth_diagnostics::Span::new(5, 14)));
+    assert_eq!(err.labels[0].message, "expected 1 argument, got 2");
+}
+
+fn decl<'s>(name: &'s str, params: &[Type]) -> TopLevel<'s> {
+    TopLevel::TypeDecl(TypeDecl {
+        name,
+        params: params.to_vec(),
+        span: Span::default(),
+    })
+}
+
+#[test]
+fn test_typeck_declared_signature() {
+    // lt :: number -> number -> bool;
+    // lt(a, b) = a < b;
+    // plot(lt);
+    let module = Module {
+        name: None,
+        top_level: vec![
+            decl("lt", &[Type::Number, Type::Int, Type::Bool]),
+            TopLevel::Function(Function {
+                name: "lt",
+                params: vec![Param::new("a"), Param::new("b")],
+                body: function_call("<", vec![varref("a"), varref("b")]),
+                span: Span::default(),
+                doc: vec![],
+            }),
+            TopLevel::Expr(function_call("plot", vec![varref("lt")])),
+        ],
+    };
+
+    let CompiledModule { instructions, .. } = crate::compile_module(&module).unwrap();
+    assert_eq!(instructions, vec![inst!(OP_X), inst!(OP_Y), inst!(OP_LT)]);
+}
+
+#[test]
+fn test_typeck_declared_result_mismatch() {
+    // f :: number -> bool;
+    // f(a) = a + 1;
+    let module = Module {
+        name: None,
+        top_level: vec![
+            decl("f", &[Type::Number, Type::Bool]),
+            TopLevel::Function(Function {
+                name: "f",
+                params: vec![Param::new("a")],
+                body: function_call("+", vec![varref("a"), int(1)]),
+                span: Span::default(),
+                doc: vec![],
+            }),
+        ],
+    };
+
+    let err = crate::compile_module(&module).unwrap_err();
+    assert_eq!(err.code, Some(codes::TYPE_MISMATCH));
+    assert_eq!(
+        err.message,
+        "Mismatched types: expected `bool`, found `number`"
+    );
+    assert_eq!(err.labels[1].message, "`f` is declared here");
+}
+
+#[test]
+fn test_typeck_infers_parameter_types() {
+    // both(a, b) = a and b;
+    // plot(both(x, y > 0));
+    let module = Module {
+        name: None,
+        top_level: vec![
+            TopLevel::Function(Function {
+                name: "both",
+                params: vec![Param::new("a"), Param::new("b")],
+                body: function_call("and", vec![varref("a"), varref("b")]),
+                span: Span::default(),
+                doc: vec![],
+            }),
+            TopLevel::Expr(function_call(
+                "plot",
+                vec![function_call(
+                    "both",
+                    vec![varref("x"), function_call(">", vec![varref("y"), int(0)])],
+                )],
+            )),
+        ],
+    };
+
+    let err = crate::compile_module(&module).unwrap_err();
+    assert_eq!(err.code, Some(codes::TYPE_MISMATCH));
+    assert_eq!(err.labels[0].message, "expected `bool`, found `number`");
+    assert_eq!(err.labels[1].message, "parameter `a` of `both`");
+
+    let ctx = crate::symbols::resolve(&module).unwrap();
+    let module_without_plot = Module {
+        name: None,
+        top_level: module.top_level[..1].to_vec(),
+    };
+    let signatures = crate::typeck::check(&module_without_plot, &ctx).unwrap();
+    assert_eq!(
+        signatures["both"],
+        Signature {
+            params: vec![Ty::Bool, Ty::Bool],
+            ret: Ty::Bool,
+        }
+    );
+}
+
+#[test]
+fn test_typeck_unconstrained_parameter_is_number() {
+    // id(a) = a;
+    let module = Module {
+        name: None,
+        top_level: vec![TopLevel::Function(Function {
+            name: "id",
+            params: vec![Param::new("a")],
+            body: varref("a"),
+            span: Span::default(),
+            doc: vec![],
+        })],
+    };
+
+    let ctx = crate::symbols::resolve(&module).unwrap();
+    let signatures = crate::typeck::check(&module, &ctx).unwrap();
+    assert_eq!(
+        signatures["id"],
+        Signature {
+            params: vec![Ty::Number],
+            ret: Ty::Number,
+        }
+    );
+}
+
+#[test]
+fn test_typeck_negated_bool() {
+    // plot(-(x < 1));
+    let module = Module {
+        name: None,
+        top_level: vec![TopLevel::Expr(function_call(
+            "plot",
+            vec![-function_call("<", vec![varref("x"), int(1)])],
+        ))],
+    };
+
+    let err = crate::compile_module(&module).unwrap_err();
+    assert_eq!(err.message, "Cannot negate a `bool`");
+}
+
+#[test]
+fn test_typeck_plot_bool_parameter() {
+    // f :: bool -> number;
+    // f(a) = 1;
+    // plot(f);
+    let module = Module {
+        name: None,
+        top_level: vec![
+            decl("f", &[Type::Bool, Type::Number]),
+            TopLevel::Function(Function {
+                name: "f",
+                params: vec![Param::new("a")],
+                body: int(1),
+                span: Span::default(),
+                doc: vec![],
+            }),
+            TopLevel::Expr(function_call("plot", vec![varref("f")])),
+        ],
+    };
+
+    let err = crate::compile_module(&module).unwrap_err();
+    assert!(err.message.starts_with("Cannot plot `f`"), "{err}");
+}
+
+#[test]
+fn test_typeck_declaration_errors() {
+    let f = TopLevel::Function(Function {
+        name: "f",
+        params: vec![Param::new("a")],
+        body: varref("a"),
+        span: Span::default(),
+        doc: vec![],
+    });
+
+    let signature_mismatch = Module {
+        name: None,
+        top_level: vec![decl("f", &[Type::Int, Type::Int, Type::Int]), f.clone()],
+    };
+    let err = crate::compile_module(&signature_mismatch).unwrap_err();
+    assert_eq!(err.code, Some(codes::SIGNATURE_MISMATCH));
+    assert_eq!(
+        err.message,
+        "`f` is declared with 2 parameters, but defined with 1 parameter"
+    );
+
+    let missing_definition = Module {
+        name: None,
+        top_level: vec![decl("g", &[Type::Int])],
+    };
+    let err = crate::compile_module(&missing_definition).unwrap_err();
+    assert_eq!(err.code, Some(codes::MISSING_DEFINITION));
+
+    let duplicate = Module {
+        name: None,
+        top_level: vec![
+            decl("f", &[Type::Int, Type::Int]),
+            decl("f", &[Type::Int, Type::Int]),
+            f,
+        ],
+    };
+    let err = crate::compile_module(&duplicate).unwrap_err();
+    assert_eq!(err.code, Some(codes::DUPLICATE_DECLARATION));
+}
+
+#[test]
+fn test_optimize_folds_constants() {
+    // 2 * 3 + x
+    let program = [
+        inst!(OP_CONST, 2.0),
+        inst!(OP_CONST, 3.0),
+        inst!(OP_MUL),
+        inst!(OP_X),
+        inst!(OP_ADD),
+    ];
+    assert_eq!(
+        crate::optimize(&program),
+        vec![inst!(OP_CONST, 6.0), inst!(OP_X), inst!(OP_ADD)]
+    );
+
+    // Division by zero is left to the GPU
+    let program = [inst!(OP_CONST, 1.0), inst!(OP_CONST, 0.0), inst!(OP_DIV)];
+    assert_eq!(crate::optimize(&program), program);
+}
+
+#[test]
+fn test_optimize_identities() {
+    let x_and = |c: f32, opcode| vec![inst!(OP_X), inst!(OP_CONST, c), inst!(opcode)];
+    for program in [
+        x_and(0.0, OP_ADD),
+        x_and(0.0, OP_SUB),
+        x_and(1.0, OP_MUL),
+        x_and(1.0, OP_DIV),
+        x_and(1.0, OP_POW),
+        vec![inst!(OP_CONST, 1.0), inst!(OP_X), inst!(OP_MUL)],
+        vec![inst!(OP_X), inst!(OP_NEG), inst!(OP_NEG)],
+    ] {
+        assert_eq!(crate::optimize(&program), vec![inst!(OP_X)], "{program:?}");
+    }
+
+    for program in [
+        x_and(-1.0, OP_MUL),
+        vec![inst!(OP_CONST, 0.0), inst!(OP_X), inst!(OP_SUB)],
+    ] {
+        assert_eq!(
+            crate::optimize(&program),
+            vec![inst!(OP_X), inst!(OP_NEG)],
+            "{program:?}"
+        );
+    }
+
+    // x - -y
+    let program = [inst!(OP_X), inst!(OP_Y), inst!(OP_NEG), inst!(OP_SUB)];
+    assert_eq!(
+        crate::optimize(&program),
+        vec![inst!(OP_X), inst!(OP_Y), inst!(OP_ADD)]
+    );
+
+    // Not an identity for infinite and NaN `x`
+    let program = x_and(0.0, OP_MUL);
+    assert_eq!(crate::optimize(&program), program);
+}
+
+#[test]
+fn test_optimize_keeps_malformed_programs() {
+    for program in [
+        vec![inst!(OP_ADD)],
+        vec![inst!(OP_X), inst!(OP_Y)],
+        vec![inst!(OP_CONST, 1.0), inst!(99)],
+    ] {
+        assert_eq!(crate::optimize(&program), program);
+    }
+}
+
+#[test]
+fn test_compile_module_reports_saved_instructions() {
+    // plot(x * 1 + (1 + 1));
+    let module = Module {
+        name: None,
+        top_level: vec![TopLevel::Expr(function_call(
+            "plot",
+            vec![function_call(
+                "+",
+                vec![
+                    function_call("*", vec![varref("x"), int(1)]),
+                    function_call("+", vec![int(1), int(1)]),
+                ],
+            )],
+        ))],
+    };
+
+    let compiled = crate::compile_module(&module).unwrap();
+    assert_eq!(
+        compiled.instructions,
+        vec![inst!(OP_X), inst!(OP_CONST, 2.0), inst!(OP_ADD)]
+    );
+    assert_eq!(compiled.plot_descs[0].length, 3);
+    assert_eq!(compiled.saved_instructions, 4);
+}
+
+#[test]
+fn test_cse_stores_repeated_subtrees() {
+    // sin(x)^2 + sin(x) * cos(x)
+    let sin_x = [inst!(OP_X), inst!(OP_SIN)];
+    let program = [
+        &sin_x[..],
+        &[inst!(OP_CONST, 2.0), inst!(OP_POW)],
+        &sin_x,
+        &[inst!(OP_X), inst!(OP_COS), inst!(OP_MUL), inst!(OP_ADD)],
+    ]
+    .concat();
+
+    assert_eq!(
+        crate::eliminate_common_subexpressions(&program),
+        vec![
+            inst!(OP_X),
+            inst!(OP_SIN),
+            inst!(OP_TEE, 0.0),
+            inst!(OP_CONST, 2.0),
+            inst!(OP_POW),
+            inst!(OP_LOAD, 0.0),
+            inst!(OP_X),
+            inst!(OP_COS),
+            inst!(OP_MUL),
+            inst!(OP_ADD),
+        ]
+    );
+}
+
+#[test]
+fn test_cse_duplicates_values_reused_right_away() {
+    // (x + 1) * (x + 1)
+    let x_plus_1 = [inst!(OP_X), inst!(OP_CONST, 1.0), inst!(OP_ADD)];
+    let program = [&x_plus_1[..], &x_plus_1, &[inst!(OP_MUL)]].concat();
+
+    assert_eq!(
+        crate::eliminate_common_subexpressions(&program),
+        vec![
+            inst!(OP_X),
+            inst!(OP_CONST, 1.0),
+            inst!(OP_ADD),
+            inst!(OP_DUP),
+            inst!(OP_MUL),
+        ]
+    );
+}
+
+#[test]
+fn test_cse_loads_outermost_repeated_subtree() {
+    // abs(sin(x)) + abs(sin(x)), sin(x) is only evaluated as part of abs(sin(x))
+    let abs_sin_x = [inst!(OP_X), inst!(OP_SIN), inst!(OP_ABS)];
+    let program = [&abs_sin_x[..], &abs_sin_x, &[inst!(OP_ADD)]].concat();
+
+    assert_eq!(
+        crate::eliminate_common_subexpressions(&program),
+        vec![
+            inst!(OP_X),
+            inst!(OP_SIN),
+            inst!(OP_ABS),
+            inst!(OP_DUP),
+            inst!(OP_ADD),
+        ]
+    );
+
+    // Leaves are pushed again
+    let program = [inst!(OP_X), inst!(OP_X), inst!(OP_MUL)];
+    assert_eq!(crate::eliminate_common_subexpressions(&program), program);
+}
+
+#[test]
+fn test_compile_module_eliminates_subexpressions_of_inlined_calls() {
+    // f(t) = sin(t) * sin(t); plot(f(x) + f(x));
+    let module = Module {
+        name: None,
+        top_level: vec![
+            TopLevel::Function(Function {
+                name: "f",
+                params: vec![Param::new("t")],
+                body: function_call(
+                    "*",
+                    vec![
+                        function_call("sin", vec![varref("t")]),
+                        function_call("sin", vec![varref("t")]),
+                    ],
+                ),
+                span: Span::default(),
+                doc: vec![],
+            }),
+            TopLevel::Expr(function_call(
+                "plot",
+                vec![function_call(
+                    "+",
+                    vec![
+                        function_call("f", vec![varref("x")]),
+                        function_call("f", vec![varref("x")]),
+                    ],
+                )],
+            )),
+        ],
+    };
+
+    let compiled = crate::compile_module(&module).unwrap();
+    assert_eq!(
+        compiled.instructions,
+        vec![
+            inst!(OP_X),
+            inst!(OP_SIN),
+            inst!(OP_DUP),
+            inst!(OP_MUL),
+            inst!(OP_DUP),
+            inst!(OP_ADD),
+        ]
+    );
+    assert_eq!(compiled.saved_instructions, 5);
+}
+
+/// x op (x op (x op ... x)), with `depth` operations
+fn right_nested(op: &'static str, depth: usize) -> Expr<'static> {
+    (0..depth).fold(varref("x"), |inner, _| {
+        function_call(op, vec![varref("x"), inner])
+    })
+}
+
+#[test]
+fn test_max_stack_depth() {
+    assert_eq!(crate::max_stack_depth(&[]), 0);
+    assert_eq!(crate::max_stack_depth(&[inst!(OP_X), inst!(OP_SIN)]), 1);
+    let program = [
+        inst!(OP_X),
+        inst!(OP_DUP),
+        inst!(OP_Y),
+        inst!(OP_ADD),
+        inst!(OP_MUL),
+    ];
+    assert_eq!(crate::max_stack_depth(&program), 3);
+}
+
+#[test]
+fn test_reorder_operands() {
+    // x + sin(y * y) evaluates the deeper right operand first
+    let program = [
+        inst!(OP_X),
+        inst!(OP_Y),
+        inst!(OP_Y),
+        inst!(OP_MUL),
+        inst!(OP_SIN),
+        inst!(OP_ADD),
+    ];
+    let reordered = crate::reorder_operands(&program);
+    assert_eq!(
+        reordered,
+        vec![
+            inst!(OP_Y),
+            inst!(OP_Y),
+            inst!(OP_MUL),
+            inst!(OP_SIN),
+            inst!(OP_X),
+            inst!(OP_ADD),
+        ]
+    );
+    assert_eq!(crate::max_stack_depth(&program), 3);
+    assert_eq!(crate::max_stack_depth(&reordered), 2);
+
+    // x < y * y becomes y * y > x
+    let program = [
+        inst!(OP_X),
+        inst!(OP_Y),
+        inst!(OP_Y),
+        inst!(OP_MUL),
+        inst!(OP_LT),
+    ];
+    assert_eq!(
+        crate::reorder_operands(&program),
+        vec![
+            inst!(OP_Y),
+            inst!(OP_Y),
+            inst!(OP_MUL),
+            inst!(OP_X),
+            inst!(OP_GT),
+        ]
+    );
+
+    // The operands of `-` stay in place
+    let program = [
+        inst!(OP_X),
+        inst!(OP_Y),
+        inst!(OP_Y),
+        inst!(OP_MUL),
+        inst!(OP_SUB),
+    ];
+    assert_eq!(crate::reorder_operands(&program), program);
+}
+
+#[test]
+fn test_compile_deep_commutative_expression() {
+    let module = Module {
+        name: None,
+        top_level: vec![TopLevel::Expr(function_call(
+            "plot",
+            vec![right_nested("+", 40)],
+        ))],
+    };
+
+    let compiled = crate::compile_module(&module).unwrap();
+    assert_eq!(crate::max_stack_depth(&compiled.instructions), 2);
+}
+
+#[test]
+fn test_compile_stack_overflow() {
+    let span = Span {
+        start: 5,
+        end: 100,
+        line: 1,
+        col: 6,
+    };
+    let module = Module {
+        name: None,
+        top_level: vec![TopLevel::Expr(function_call(
+            "plot",
+            vec![right_nested("-", STACK_SIZE).with_span(span)],
+        ))],
+    };
+
+    let err = crate::compile_module(&module).unwrap_err();
+    assert_eq!(err.code, Some(codes::STACK_OVERFLOW));
+    assert_eq!(
+        err.message,
+        "This plot needs a stack of 17 values, but the GPU can hold at most 15"
+    );
+    assert_eq!(err.primary_span(), Some(mth_diagnostics::Span::new(5, 100)));
+
+    // The deepest stack that fits
+    let module = Module {
+        name: None,
+        top_level: vec![TopLevel::Expr(function_call(
+            "plot",
+            vec![right_nested("-", STACK_SIZE - 2)],
+        ))],
+    };
+    assert!(crate::compile_module(&module).is_ok());
+}
+
+#[test]
+fn test_compile_parametric_plot() {
+    // plot_parametric(t -> cos(t), t -> 2 sin(t), 0, pi);
+    let module = Module {
+        name: None,
+        top_level: vec![TopLevel::Expr(function_call(
+            "plot_parametric",
+            vec![
+                lambda(&["t"], function_call("cos", vec![varref("t")])),
+                lambda(
+                    &["t"],
+                    function_call("*", vec![int(2), function_call("sin", vec![varref("t")])]),
+                ),
+                int(0),
+                varref("pi"),
+            ],
+        ))],
+    };
+
+    let CompiledModule {
+        instructions,
+        plot_descs,
+        ..
+    } = crate::compile_module(&module).unwrap();
+    // Both components are evaluated one after the other, `x` on top of `y`
+    assert_eq!(
+        instructions,
+        vec![
+            inst!(OP_X),
+            inst!(OP_COS),
+            inst!(OP_CONST, 2.0),
+            inst!(OP_X),
+            inst!(OP_SIN),
+            inst!(OP_MUL),
+        ]
+    );
+    assert_eq!(plot_descs.len(), 1);
+    assert_eq!(plot_descs[0].type_id, PLOT_TYPE_PARAMETRIC);
+    assert_eq!(plot_descs[0].length, 6);
+    assert_eq!(plot_descs[0].range, [0.0, std::f32::consts::PI]);
+}
+
+#[test]
+fn test_compile_polar_plot() {
+    // r(θ) = θ / 2; plot_polar(r);
+    let module = Module {
+        name: None,
+        top_level: vec![
+            TopLevel::Function(Function {
+                name: "r",
+                params: vec![Param::new("θ")],
+                body: function_call("/", vec![varref("θ"), int(2)]),
+                span: Span::default(),
+                doc: vec![],
+            }),
+            TopLevel::Expr(function_call("plot_polar", vec![varref("r")])),
+        ],
+    };
+
+    let CompiledModule {
+        instructions,
+        plot_descs,
+        ..
+    } = crate::compile_module(&module).unwrap();
+    assert_eq!(
+        instructions,
+        vec![inst!(OP_X), inst!(OP_CONST, 2.0), inst!(OP_DIV)]
+    );
+    assert_eq!(plot_descs[0].type_id, PLOT_TYPE_POLAR);
+    assert_eq!(plot_descs[0].range, [0.0, std::f32::consts::TAU]);
+}
+
+#[test]
+fn test_compile_inverse_plot() {
+    // plot_inverse(s -> s^2, 2 y);
+    let module = Module {
+        name: None,
+        top_level: vec![TopLevel::Expr(function_call(
+            "plot_inverse",
+            vec![
+                lambda(&["s"], function_call("^", vec![varref("s"), int(2)])),
+                function_call("*", vec![int(2), varref("y")]),
+            ],
+        ))],
+    };
+
+    let CompiledModule {
+        instructions,
+        plot_descs,
+        ..
+    } = crate::compile_module(&module).unwrap();
+    // The parameter is bound to `y`
+    assert_eq!(
+        instructions,
+        vec![
+            inst!(OP_Y),
+            inst!(OP_CONST, 2.0),
+            inst!(OP_POW),
+            inst!(OP_CONST, 2.0),
+            inst!(OP_Y),
+            inst!(OP_MUL),
+        ]
+    );
+    assert_eq!(plot_descs.len(), 2);
+    assert!(
+        plot_descs
+            .iter()
+            .all(|desc| desc.type_id == PLOT_TYPE_INVERSE)
+    );
+}
+
+#[test]
+fn test_compile_curve_with_too_many_parameters() {
+    // plot_polar((a, b) -> a);
+    let module = Module {
+        name: None,
+        top_level: vec![TopLevel::Expr(function_call(
+            "plot_polar",
+            vec![lambda(&["a", "b"], varref("a"))],
+        ))],
+    };
+
+    let err = crate::compile_module(&module).unwrap_err();
+    assert_eq!(err.code, Some(codes::TOO_MANY_PARAMETERS));
+    assert_eq!(
+        err.notes,
+        vec!["the parameters of a plotted function are bound to `x`".to_string()]
+    );
+}
+
+#[test]
+fn test_compile_curve_argument_count() {
+    // plot_parametric(t -> t, t -> t, 0);
+    let module = Module {
+        name: None,
+        top_level: vec![TopLevel::Expr(function_call(
+            "plot_parametric",
+            vec![
+                lambda(&["t"], varref("t")),
+                lambda(&["t"], varref("t")),
+                int(0),
+            ],
+        ))],
+    };
+
+    let err = crate::compile_module(&module).unwrap_err();
+    assert_eq!(err.code, Some(codes::WRONG_ARGUMENT_COUNT));
+    assert_eq!(err.labels[0].message, "expected 2 or 4 arguments, got 3");
+}
+
+#[test]
+fn test_compile_curve_range() {
+    let polar = |start, end| Module {
+        name: None,
+        top_level: vec![
+            TopLevel::Function(Function {
+                name: "a",
+                params: vec![],
+                body: int(3),
+                span: Span::default(),
+                doc: vec![],
+            }),
+            TopLevel::Expr(function_call(
+                "plot_polar",
+                vec![lambda(&["t"], varref("t")), start, end],
+            )),
+        ],
+    };
+
+    // Constants are evaluated at compile time
+    let compiled = crate::compile_module(&polar(
+        -varref("a"),
+        function_call("*", vec![int(2), varref("a")]),
+    ))
+    .unwrap();
+    assert_eq!(compiled.plot_descs[0].range, [-3.0, 6.0]);
+
+    let err = crate::compile_module(&polar(int(0), varref("x"))).unwrap_err();
+    assert_eq!(err.code, Some(codes::NON_CONSTANT_RANGE));
+    assert_eq!(
+        err.message,
+        "The range of `plot_polar` must be known at compile time"
+    );
+
+    let err = crate::compile_module(&polar(int(1), int(1))).unwrap_err();
+    assert_eq!(err.code, Some(codes::INVALID_RANGE));
+    assert_eq!(err.message, "Invalid range from 1 to 1 for `plot_polar`");
+}
+
+#[test]
+fn test_compile_view() {
+    let module = |statements: Vec<Expr<'static>>| Module {
+        name: None,
+        top_level: [TopLevel::Function(Function {
+            name: "a",
+            params: vec![],
+            body: int(3),
+            span: Span::default(),
+            doc: vec![],
+        })]
+        .into_iter()
+        .chain(statements.into_iter().map(TopLevel::Expr))
+        .collect(),
+    };
+    let view = |args| function_call("view", args);
+
+    // Constants are evaluated at compile time
+    let compiled = crate::compile_module(&module(vec![view(vec![
+        -varref("a"),
+        varref("a"),
+        int(0),
+        function_call("*", vec![int(2), varref("a")]),
+    ])]))
+    .unwrap();
+    assert_eq!(compiled.view, Some([[-3.0, 3.0], [0.0, 6.0]]));
+    assert!(compiled.plot_descs.is_empty());
+
+    let compiled = crate::compile_module(&module(vec![])).unwrap();
+    assert_eq!(compiled.view, None);
+
+    let err = crate::compile_module(&module(vec![view(vec![int(0), int(1), int(0)])])).unwrap_err();
+    assert_eq!(err.code, Some(codes::WRONG_ARGUMENT_COUNT));
+    assert_eq!(err.labels[0].message, "expected 4 arguments, got 3");
+
+    let err = crate::compile_module(&module(vec![view(vec![
+        int(0),
+        int(1),
+        varref("x"),
+        int(1),
+    ])]))
+    .unwrap_err();
+    assert_eq!(err.code, Some(codes::NON_CONSTANT_RANGE));
+
+    let err = crate::compile_module(&module(vec![view(vec![int(1), int(0), int(0), int(1)])]))
+        .unwrap_err();
+    assert_eq!(err.code, Some(codes::INVALID_RANGE));
+    assert_eq!(err.message, "Invalid range from 1 to 0 for `view`");
+
+    let ranges = || vec![int(0), int(1), int(0), int(1)];
+    let err = crate::compile_module(&module(vec![view(ranges()), view(ranges())])).unwrap_err();
+    assert_eq!(err.code, Some(codes::DUPLICATE_VIEW));
+
+    let mut with_option = view(ranges());
+    if let Expr::FunctionCall(call) = &mut with_option {
+        call.named_args.push(NamedArg {
+            name: "color",
+            value: int(1),
+            span: Span::default(),
+        });
+    }
+    let err = crate::compile_module(&module(vec![with_option])).unwrap_err();
+    assert_eq!(err.code, Some(codes::UNKNOWN_OPTION));
+}
+
+#[test]
+fn test_compile_combined_conditions_are_equations() {
+    // plot(y < x and x < 1);
+    let module = Module {
+        name: None,
+        top_level: vec![TopLevel::Expr(function_call(
+            "plot",
+            vec![function_call(
+                "and",
+                vec![
+                    function_call("<", vec![varref("y"), varref("x")]),
+                    function_call("<", vec![varref("x"), int(1)]),
+                ],
+            )],
+        ))],
+    };
+
+    let CompiledModule { plot_descs, .. } = crate::compile_module(&module).unwrap();
+    assert_eq!(plot_descs[0].type_id, PLOT_TYPE_EQUATION);
+}
+
+/// `plot(args..., name = value, ...)`
+fn plot_with<'s>(args: Vec<Expr<'s>>, options: Vec<(&'s str, Expr<'s>)>) -> TopLevel<'s> {
+    TopLevel::Expr(Expr::FunctionCall(FunctionCall {
+        name: "plot",
+        args,
+        named_args: options
+            .into_iter()
+            .map(|(name, value)| NamedArg {
+                name,
+                value,
+                span: Span::default(),
+            })
+            .collect(),
+        is_negated: false,
+        span: Span::default(),
+    }))
+}
+
+fn string(s: &str) -> Expr<'static> {
+    Expr::Literal(Literal::String(s.to_string()), Span::default())
+}
+
+#[test]
+fn test_compile_default_style() {
+    // plot(x, y < x); plot(1);
+    let module = Module {
+        name: None,
+        top_level: vec![
+            plot_with(
+                vec![
+                    varref("x"),
+                    function_call("<", vec![varref("y"), varref("x")]),
+                ],
+                vec![],
+            ),
+            plot_with(vec![int(1)], vec![]),
+        ],
+    };
+
+    let CompiledModule { plot_descs, .. } = crate::compile_module(&module).unwrap();
+    let colors: Vec<_> = plot_descs.iter().map(|desc| desc.color).collect();
+    assert_eq!(colors, PALETTE[..3]);
+    assert_eq!(plot_descs[0].width, 1.0);
+    assert_eq!(plot_descs[0].dash, [0.0, 0.0]);
+
+    // Only conditions are filled
+    assert_eq!(plot_descs[0].fill, 0.0);
+    assert_eq!(plot_descs[1].fill, REGION_FILL);
+}
+
+#[test]
+fn test_compile_style_options() {
+    // c = "#ff8000"; w = 2; plot(x, y, color = c, width = w + 1, dash = [4, 2], fill = 0.3);
+    let constant = |name, body| {
+        TopLevel::Function(Function {
+            name,
+            params: vec![],
+            body,
+            span: Span::default(),
+            doc: vec![],
+        })
+    };
+    let module = Module {
+        name: None,
+        top_level: vec![
+            constant("c", string("#ff8000")),
+            constant("w", int(2)),
+            plot_with(
+                vec![varref("x"), varref("y")],
+                vec![
+                    ("color", varref("c")),
+                    ("width", function_call("+", vec![varref("w"), int(1)])),
+                    ("dash", Expr::List(vec![int(4), int(2)], Span::default())),
+                    ("fill", Expr::Literal(Literal::Float(0.3), Span::default())),
+                ],
+            ),
+        ],
+    };
+
+    let CompiledModule { plot_descs, .. } = crate::compile_module(&module).unwrap();
+    assert_eq!(plot_descs.len(), 2);
+    for desc in plot_descs {
+        assert_eq!(desc.color, [1.0, 128.0 / 255.0, 0.0]);
+        assert_eq!(desc.width, 3.0);
+        assert_eq!(desc.dash, [4.0, 2.0]);
+        assert_eq!(desc.fill, 0.3);
+    }
+
+    let module = Module {
+        name: None,
+        top_level: vec![plot_with(
+            vec![varref("x")],
+            vec![("color", string("Blue"))],
+        )],
+    };
+    let CompiledModule { plot_descs, .. } = crate::compile_module(&module).unwrap();
+    assert_eq!(plot_descs[0].color, PALETTE[2]);
+}
+
+#[test]
+fn test_compile_invalid_style_options() {
+    let compile = |options| {
+        let module = Module {
+            name: None,
+            top_level: vec![plot_with(vec![varref("x")], options)],
+        };
+        crate::compile_module(&module).unwrap_err()
+    };
+
+    let err = compile(vec![("colour", string("red"))]);
+    assert_eq!(err.code, Some(codes::UNKNOWN_OPTION));
+    assert_eq!(err.message, "`plot` has no option named `colour`");
+
+    for (option, value, label) in [
+        ("color", string("reddish"), "unknown color"),
+        ("color", string("#ff80"), "unknown color"),
+        ("color", int(1), "not a string"),
+        ("width", varref("x"), "not a constant"),
+        ("width", int(0), "not a positive number"),
+        ("dash", int(4), "not a list"),
+        (
+            "dash",
+            Expr::List(vec![int(4)], Span::default()),
+            "expected 2 lengths, got 1",
+        ),
+        ("fill", int(2), "not between 0 and 1"),
+    ] {
+        let err = compile(vec![(option, value)]);
+        assert_eq!(err.code, Some(codes::INVALID_OPTION), "{option}");
+        assert_eq!(
+            err.message,
+            format!("Invalid value for the option `{option}`")
+        );
+        assert_eq!(err.labels[0].message, label);
+
+        // Only values that aren't constant are explained by the evaluation at compile time
+        let explains_compile_time = err
+            .notes
+            .iter()
+            .any(|note| note.starts_with("options are evaluated at compile time"));
+        assert_eq!(explains_compile_time, label == "not a constant", "{option}");
+    }
+
+    let err = compile(vec![("width", int(2)), ("width", int(3))]);
+    assert_eq!(err.code, Some(codes::INVALID_OPTION));
+    assert_eq!(err.message, "`width` is given more than once");
+}
+
+#[test]
+fn test_compile_named_args_and_lists_outside_of_plots() {
+    // plot(sin(x, scale = 2));
+    let call = Expr::FunctionCall(FunctionCall {
+        name: "sin",
+        args: vec![varref("x")],
+        named_args: vec![NamedArg {
+            name: "scale",
+            value: int(2),
+            span: Span::default(),
+        }],
+        is_negated: false,
+        span: Span::default(),
+    });
+    let module = Module {
+        name: None,
+        top_level: vec![plot_with(vec![call], vec![])],
+    };
+    let err = crate::compile_module(&module).unwrap_err();
+    assert_eq!(err.code, Some(codes::UNKNOWN_OPTION));
+    assert_eq!(err.message, "`sin` takes no named arguments");
+
+    // plot([1, 2]);
+    let module = Module {
+        name: None,
+        top_level: vec![plot_with(
+            vec![Expr::List(vec![int(1), int(2)], Span::default())],
+            vec![],
+        )],
+    };
+    let err = crate::compile_module(&module).unwrap_err();
+    assert_eq!(err.code, Some(codes::MISPLACED_LIST));
+}
+
+#[test]
+fn test_compile_slider() {
+    let slider = |args| TopLevel::Expr(function_call("slider", args));
+    let module = |top_level| Module {
+        name: None,
+        top_level,
+    };
+    let float = |value| Expr::Literal(Literal::Float(value), Span::default());
+
+    // slider(a, 0, 4, 0.5); slider(b, -1, 1, 0.25); plot(a x + b);
+    let compiled = crate::compile_module(&module(vec![
+        slider(vec![varref("a"), int(0), int(4), float(0.5)]),
+        slider(vec![varref("b"), -int(1), int(1), float(0.25)]),
+        TopLevel::Expr(function_call(
+            "plot",
+            vec![function_call(
+                "+",
+                vec![
+                    function_call("*", vec![varref("a"), varref("x")]),
+                    varref("b"),
+                ],
+            )],
+        )),
+    ]))
+    .unwrap();
+    assert_eq!(
+        compiled.instructions,
+        vec![
+            inst!(OP_PARAM, 0.0),
+            inst!(OP_X),
+            inst!(OP_MUL),
+            inst!(OP_PARAM, 1.0),
+            inst!(OP_ADD)
+        ]
+    );
+    let [a, b] = &compiled.sliders[..] else {
+        panic!("expected two sliders, got {:?}", compiled.sliders);
+    };
+    assert_eq!((a.name.as_str(), a.range, a.step), ("a", [0.0, 4.0], 0.5));
+    assert_eq!((a.value, b.value), (2.0, 0.0));
+    assert_eq!(a.snap(1.2), 1.0);
+    assert_eq!(a.snap(5.0), 4.0);
+
+    // The name must be an identifier, and the step positive
+    let err = crate::compile_module(&module(vec![slider(vec![int(1), int(0), int(1), int(1)])]))
+        .unwrap_err();
+    assert_eq!(err.code, Some(codes::INVALID_SLIDER));
+    let err = crate::compile_module(&module(vec![slider(vec![
+        varref("a"),
+        int(0),
+        int(1),
+        int(0),
+    ])]))
+    .unwrap_err();
+    assert_eq!(err.code, Some(codes::INVALID_SLIDER));
+    let err = crate::compile_module(&module(vec![slider(vec![varref("a"), int(0), int(1)])]))
+        .unwrap_err();
+    assert_eq!(err.code, Some(codes::WRONG_ARGUMENT_COUNT));
+
+    // Sliders are names like definitions
+    let err = crate::compile_module(&module(vec![slider(vec![
+        varref("x"),
+        int(0),
+        int(1),
+        int(1),
+    ])]))
+    .unwrap_err();
+    assert_eq!(err.code, Some(codes::BUILTIN_REDEFINITION));
+    let ranges = || vec![varref("a"), int(0), int(1), int(1)];
+    let err = crate::compile_module(&module(vec![slider(ranges()), slider(ranges())])).unwrap_err();
+    assert_eq!(err.code, Some(codes::DUPLICATE_DEFINITION));
+
+    let err = crate::compile_module(&module(
+        (0..=MAX_PARAMS)
+            .map(|i| {
+                let name: &'static str = format!("a{i}").leak();
+                slider(vec![varref(name), int(0), int(1), int(1)])
+            })
+            .collect(),
+    ))
+    .unwrap_err();
+    assert_eq!(err.code, Some(codes::TOO_MANY_SLIDERS));
+}
+
+#[test]
+fn test_compile_time() {
+    let expr = function_call("-", vec![varref("x"), varref("t")]);
+    let mut buf = Vec::new();
+    compile_expr(&expr, &mut Ctx::default(), &mut buf).unwrap();
+    assert_eq!(buf, vec![inst!(OP_X), inst!(OP_T), inst!(OP_SUB)]);
+
+    // Parameters named `t` shadow it
+    let module = Module {
+        name: None,
+        top_level: vec![
+            TopLevel::Function(Function {
+                name: "f",
+                params: vec![Param("t", Span::default())],
+                body: function_call("*", vec![int(2), varref("t")]),
+                span: Span::default(),
+                doc: vec![],
+            }),
+            TopLevel::Expr(function_call("plot", vec![varref("f")])),
+        ],
+    };
+    let compiled = crate::compile_module(&module).unwrap();
+    assert!(!compiled.instructions.iter().any(|op| op.opcode == OP_T));
 }
@@ -0,0 +1,414 @@
+use std::{collections::HashMap, fmt};
+
+use mth_ast::{
+    Expr, Function, FunctionCall, Lambda, Literal, Module, Param, TopLevel, Type, TypeDecl,
+};
+use mth_diagnostics::{Diagnostic, Span, codes};
+
+use crate::codegen::{
+    Ctx, PlotKind, SLIDER, VIEW, wrong_argument_count, wrong_user_argument_count,
+};
+
+/// Type of a value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ty {
+    Number,
+    Bool,
+    String,
+}
+
+impl From<Type> for Ty {
+    fn from(ty: Type) -> Self {
+        match ty {
+            // Integers are computed as floats like every other number
+            Type::Number | Type::Int => Ty::Number,
+            Type::Bool => Ty::Bool,
+            Type::String => Ty::String,
+        }
+    }
+}
+
+impl fmt::Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Ty::Number => "number",
+            Ty::Bool => "bool",
+            Ty::String => "string",
+        })
+    }
+}
+
+/// Parameter and result types of a function
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    pub params: Vec<Ty>,
+    pub ret: Ty,
+}
+
+/// Signatures of all definitions of a module, declared or inferred
+pub type Signatures<'s> = HashMap<&'s str, Signature>;
+
+/// Check the types of the definitions and plots of `module`, whose names are already resolved
+/// into `ctx`.
+///
+/// Definitions with a type declaration (`f :: number -> bool;`) are checked against it, the
+/// types of all others are inferred from their bodies. Parameters whose type nothing
+/// constrains are numbers
+pub fn check<'s>(module: &Module<'s>, ctx: &Ctx<'_, 's>) -> Result<Signatures<'s>, Diagnostic> {
+    let mut checker = Checker {
+        functions: &ctx.functions,
+        sliders: &ctx.sliders,
+        declarations: HashMap::new(),
+        signatures: HashMap::new(),
+        vars: Vec::new(),
+    };
+
+    for top_level in &module.top_level {
+        if let TopLevel::TypeDecl(decl) = top_level
+            && let Some(first) = checker.declarations.insert(decl.name, decl)
+        {
+            return Err(
+                Diagnostic::error(format!("`{}` is declared more than once", decl.name))
+                    .with_code(codes::DUPLICATE_DECLARATION)
+                    .with_primary(decl.span.into(), "declared again here")
+                    .with_secondary(first.span.into(), "first declaration"),
+            );
+        }
+    }
+
+    for top_level in &module.top_level {
+        let TopLevel::TypeDecl(decl) = top_level else {
+            continue;
+        };
+        let Some(f) = ctx.functions.get(decl.name) else {
+            return Err(Diagnostic::error(format!(
+                "`{}` is declared but never defined",
+                decl.name
+            ))
+            .with_code(codes::MISSING_DEFINITION)
+            .with_primary(decl.span.into(), ""));
+        };
+        let declared = decl.params.len() - 1;
+        if declared != f.params.len() {
+            return Err(Diagnostic::error(format!(
+                "`{}` is declared with {}, but defined with {}",
+                f.name,
+                parameters(declared),
+                parameters(f.params.len())
+            ))
+            .with_code(codes::SIGNATURE_MISMATCH)
+            .with_primary(
+                f.span.into(),
+                format!("defined with {}", parameters(f.params.len())),
+            )
+            .with_secondary(
+                decl.span.into(),
+                format!("declared with {}", parameters(declared)),
+            ));
+        }
+    }
+
+    for top_level in &module.top_level {
+        match top_level {
+            TopLevel::Function(f) => {
+                checker.signature(f)?;
+            }
+            TopLevel::Expr(Expr::FunctionCall(FunctionCall { name, args, .. }))
+                if PlotKind::from_name(name).is_some() =>
+            {
+                // The arguments after the components of a curve are its range
+                let n = PlotKind::from_name(name)
+                    .and_then(PlotKind::components)
+                    .unwrap_or(args.len());
+                for (i, arg) in args.iter().enumerate() {
+                    if i < n {
+                        checker.check_plot(arg)?;
+                    } else {
+                        checker.expect(arg, Slot::Known(Ty::Number), &Scope::new())?;
+                    }
+                }
+            }
+            TopLevel::Expr(Expr::FunctionCall(FunctionCall { name, args, .. }))
+                if *name == VIEW =>
+            {
+                for arg in args {
+                    checker.expect(arg, Slot::Known(Ty::Number), &Scope::new())?;
+                }
+            }
+            TopLevel::Expr(Expr::FunctionCall(FunctionCall { name, args, .. }))
+                if *name == SLIDER =>
+            {
+                // The first argument is the name of the slider
+                for arg in args.iter().skip(1) {
+                    checker.expect(arg, Slot::Known(Ty::Number), &Scope::new())?;
+                }
+            }
+            // Rejected by code generation
+            TopLevel::Expr(_) => {}
+            TopLevel::TypeDecl(_) | TopLevel::Error(_) => {}
+        }
+    }
+
+    Ok(checker.signatures)
+}
+
+/// Type of an expression during inference: known, or a variable that is resolved later
+#[derive(Debug, Clone, Copy)]
+enum Slot {
+    Known(Ty),
+    Var(usize),
+}
+
+type Scope<'s> = HashMap<&'s str, Slot>;
+
+struct Checker<'c, 'a, 's> {
+    functions: &'c HashMap<&'s str, &'a Function<'s>>,
+    sliders: &'c HashMap<&'s str, u32>,
+    declarations: HashMap<&'s str, &'c TypeDecl<'s>>,
+    signatures: Signatures<'s>,
+
+    /// Union-find over the type variables: the parent of each variable, and the type of the
+    /// root once it is known
+    vars: Vec<(usize, Option<Ty>)>,
+}
+
+impl<'s> Checker<'_, '_, 's> {
+    /// The signature of `f`, checked against its declaration or inferred from its body
+    fn signature(&mut self, f: &Function<'s>) -> Result<Signature, Diagnostic> {
+        if let Some(signature) = self.signatures.get(f.name) {
+            return Ok(signature.clone());
+        }
+
+        let decl = self.declarations.get(f.name).copied();
+        let (params, ret): (Vec<Slot>, Slot) = match decl {
+            Some(decl) => {
+                let (ret, params) = decl
+                    .params
+                    .split_last()
+                    .expect("parsed with at least one type");
+                (
+                    params.iter().map(|ty| Slot::Known((*ty).into())).collect(),
+                    Slot::Known((*ret).into()),
+                )
+            }
+            None => (
+                f.params.iter().map(|_| self.fresh()).collect(),
+                self.fresh(),
+            ),
+        };
+
+        let scope = f
+            .params
+            .iter()
+            .map(|Param(name, _)| *name)
+            .zip(params.iter().copied())
+            .collect();
+        self.expect(&f.body, ret, &scope).map_err(|e| match decl {
+            Some(decl) => {
+                e.with_secondary(decl.span.into(), format!("`{}` is declared here", f.name))
+            }
+            None => e,
+        })?;
+
+        let signature = Signature {
+            params: params.into_iter().map(|slot| self.finish(slot)).collect(),
+            ret: self.finish(ret),
+        };
+        self.signatures.insert(f.name, signature.clone());
+        Ok(signature)
+    }
+
+    /// Check an argument of `plot`. Plotted functions get numbers for their parameters
+    fn check_plot(&mut self, arg: &Expr<'s>) -> Result<(), Diagnostic> {
+        match arg {
+            Expr::Lambda(Lambda { params, body, .. }) => {
+                let scope = params
+                    .iter()
+                    .map(|Param(name, _)| (*name, Slot::Known(Ty::Number)))
+                    .collect();
+                self.infer(body, &scope)?;
+            }
+            Expr::FunctionCall(FunctionCall {
+                name,
+                args,
+                is_negated,
+                span,
+                ..
+            }) if args.is_empty() && self.functions.contains_key(name) => {
+                let f = self.functions[name];
+                let signature = self.signature(f)?;
+                for (ty, Param(param, param_span)) in signature.params.iter().zip(&f.params) {
+                    if *ty != Ty::Number {
+                        return Err(Diagnostic::error(format!(
+                            "Cannot plot `{name}`: parameter `{param}` is a `{ty}`, but the inputs of a plot are numbers"
+                        ))
+                        .with_code(codes::TYPE_MISMATCH)
+                        .with_primary((*span).into(), "plotted here")
+                        .with_secondary((*param_span).into(), format!("`{param}` has type `{ty}`")));
+                    }
+                }
+                if *is_negated && signature.ret != Ty::Number {
+                    return Err(cannot_negate((*span).into(), signature.ret));
+                }
+            }
+            expr => {
+                self.infer(expr, &Scope::new())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn infer(&mut self, expr: &Expr<'s>, scope: &Scope<'s>) -> Result<Slot, Diagnostic> {
+        let call = match expr {
+            Expr::Literal(Literal::Int(_) | Literal::Float(_), _) => {
+                return Ok(Slot::Known(Ty::Number));
+            }
+            Expr::Literal(Literal::Bool(_), _) => return Ok(Slot::Known(Ty::Bool)),
+            Expr::Literal(Literal::String(_), _) => return Ok(Slot::Known(Ty::String)),
+            // Only valid as an argument of `plot` or the value of a named argument, anywhere
+            // else code generation rejects them
+            Expr::Lambda(_) | Expr::List(..) => return Ok(self.fresh()),
+            Expr::FunctionCall(call) => call,
+        };
+
+        let ty = if call.args.is_empty()
+            && let Some(slot) = scope.get(call.name)
+        {
+            *slot
+        } else if call.args.is_empty() && self.sliders.contains_key(call.name) {
+            Slot::Known(Ty::Number)
+        } else if let Some((params, ret)) = builtin_signature(call.name) {
+            if call.args.len() != params.len() {
+                return Err(wrong_argument_count(call, params.len()));
+            }
+            for (arg, ty) in call.args.iter().zip(params) {
+                self.expect(arg, Slot::Known(*ty), scope)?;
+            }
+            Slot::Known(ret)
+        } else if let Some(f) = self.functions.get(call.name).copied() {
+            if call.args.len() != f.params.len() {
+                return Err(wrong_user_argument_count(f, call));
+            }
+            let signature = self.signature(f)?;
+            for ((arg, ty), Param(param, param_span)) in
+                call.args.iter().zip(&signature.params).zip(&f.params)
+            {
+                self.expect(arg, Slot::Known(*ty), scope).map_err(|e| {
+                    e.with_secondary(
+                        (*param_span).into(),
+                        format!("parameter `{param}` of `{}`", f.name),
+                    )
+                })?;
+            }
+            Slot::Known(signature.ret)
+        } else {
+            // Undefined names are reported by `symbols::resolve`
+            self.fresh()
+        };
+
+        if call.is_negated {
+            self.unify(ty, Slot::Known(Ty::Number))
+                .map_err(|(found, _)| cannot_negate(call.span.into(), found))?;
+        }
+        Ok(ty)
+    }
+
+    /// Infer the type of `expr` and require it to be `expected`
+    fn expect(
+        &mut self,
+        expr: &Expr<'s>,
+        expected: Slot,
+        scope: &Scope<'s>,
+    ) -> Result<(), Diagnostic> {
+        let found = self.infer(expr, scope)?;
+        self.unify(found, expected).map_err(|(found, expected)| {
+            Diagnostic::error(format!(
+                "Mismatched types: expected `{expected}`, found `{found}`"
+            ))
+            .with_code(codes::TYPE_MISMATCH)
+            .with_primary(
+                expr.span().into(),
+                format!("expected `{expected}`, found `{found}`"),
+            )
+        })
+    }
+
+    fn fresh(&mut self) -> Slot {
+        let var = self.vars.len();
+        self.vars.push((var, None));
+        Slot::Var(var)
+    }
+
+    fn root(&self, mut var: usize) -> usize {
+        while self.vars[var].0 != var {
+            var = self.vars[var].0;
+        }
+        var
+    }
+
+    fn resolve(&self, slot: Slot) -> Slot {
+        match slot {
+            Slot::Known(_) => slot,
+            Slot::Var(var) => {
+                let root = self.root(var);
+                match self.vars[root].1 {
+                    Some(ty) => Slot::Known(ty),
+                    None => Slot::Var(root),
+                }
+            }
+        }
+    }
+
+    /// Make `found` and `expected` the same type. Fails with both types if they differ
+    fn unify(&mut self, found: Slot, expected: Slot) -> Result<(), (Ty, Ty)> {
+        match (self.resolve(found), self.resolve(expected)) {
+            (Slot::Known(a), Slot::Known(b)) if a != b => Err((a, b)),
+            (Slot::Known(_), Slot::Known(_)) => Ok(()),
+            (Slot::Var(var), Slot::Known(ty)) | (Slot::Known(ty), Slot::Var(var)) => {
+                self.vars[var].1 = Some(ty);
+                Ok(())
+            }
+            (Slot::Var(a), Slot::Var(b)) => {
+                self.vars[a].0 = b;
+                Ok(())
+            }
+        }
+    }
+
+    /// The type of `slot` after inference, numbers if nothing constrained it
+    fn finish(&mut self, slot: Slot) -> Ty {
+        match self.resolve(slot) {
+            Slot::Known(ty) => ty,
+            Slot::Var(var) => {
+                self.vars[var].1 = Some(Ty::Number);
+                Ty::Number
+            }
+        }
+    }
+}
+
+/// Parameter and result types of the builtin `name`
+fn builtin_signature(name: &str) -> Option<(&'static [Ty], Ty)> {
+    use Ty::*;
+    let signature: (&[Ty], Ty) = match name {
+        "+" | "-" | "*" | "/" | "^" | "bitwise_or" | "bitwise_xor" | "bitwise_and" => {
+            (&[Number, Number], Number)
+        }
+        "or" | "and" => (&[Bool, Bool], Bool),
+        "==" | "!=" | "<" | "<=" | ">" | ">=" => (&[Number, Number], Bool),
+        "sin" | "cos" | "tan" | "log" | "abs" => (&[Number], Number),
+        "pi" | "x" | "y" | "t" => (&[], Number),
+        _ => return None,
+    };
+    Some(signature)
+}
+
+fn cannot_negate(span: Span, ty: Ty) -> Diagnostic {
+    Diagnostic::error(format!("Cannot negate a `{ty}`"))
+        .with_code(codes::TYPE_MISMATCH)
+        .with_primary(span, "only numbers can be negated")
+}
+
+fn parameters(n: usize) -> String {
+    format!("{n} parameter{}", if n == 1 { "" } else { "s" })
+}
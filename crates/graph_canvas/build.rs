@@ -0,0 +1,65 @@
+//! Generates the opcode constants and `execute_instruction` of the shader from
+//! `mth_common::ops::OPS`
+
+use std::{env, fmt::Write as _, fs, path::PathBuf};
+
+use mth_common::ops::{OPS, OpInfo};
+
+/// Names of the popped values in the WGSL expressions of [`OpInfo::results`]
+const OPERANDS: [&str; 3] = ["a", "b", "c"];
+
+fn main() {
+    let mut src =
+        String::from("// Generated by build.rs of graph_canvas from `mth_common::ops::OPS`\n\n");
+
+    for info in OPS {
+        _ = writeln!(src, "const OP_{}: u32 = {}u;", info.name, info.opcode);
+    }
+
+    src += "
+fn execute_instruction(op: Instruction, x: f32, y: f32, sp: ptr<function, u32>, stack: ptr<function, array<f32, STACK_SIZE>>, locals: ptr<function, array<f32, N_LOCALS>>) {
+    switch op.opcode {
+";
+    for info in OPS {
+        src += &case(info);
+    }
+    src += "        default: {}
+    }
+}
+";
+
+    let out = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join("ops.wgsl");
+    fs::write(out, src).unwrap();
+    println!("cargo::rerun-if-changed=build.rs");
+}
+
+/// The switch case of `execute_instruction` for one opcode
+fn case(info: &OpInfo) -> String {
+    let pops = info.pops as usize;
+    assert!(
+        pops <= OPERANDS.len(),
+        "OP_{} pops too many values",
+        info.name
+    );
+
+    let mut case = format!("        case OP_{}: {{\n", info.name);
+    for (i, operand) in OPERANDS[..pops].iter().enumerate() {
+        _ = writeln!(
+            case,
+            "            let {operand} = stack[*sp - {}u];",
+            pops - i
+        );
+    }
+    if pops > 0 {
+        _ = writeln!(case, "            *sp = *sp - {pops}u;");
+    }
+    if !info.effect.is_empty() {
+        _ = writeln!(case, "            {}", info.effect);
+    }
+    for result in info.results {
+        _ = writeln!(case, "            stack[*sp] = {result};");
+        case += "            *sp = *sp + 1u;\n";
+    }
+    case += "        }\n";
+    case
+}
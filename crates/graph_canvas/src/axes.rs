@@ -0,0 +1,168 @@
+//! Grid lines, tick marks and labels drawn over the graph by an iced canvas, see [`grid`]
+
+use glam::{DVec2, dvec2};
+use iced::{
+    Color, Pixels, Point, Rectangle, Renderer, Theme, alignment, mouse,
+    widget::{
+        canvas::{self, Frame, Geometry, Path, Stroke},
+        text,
+    },
+};
+
+use crate::{
+    controls::Controls,
+    grid::{AxisScale, Ticks},
+};
+
+const MAJOR_LINE: Color = Color::from_rgba(1.0, 1.0, 1.0, 0.14);
+const MINOR_LINE: Color = Color::from_rgba(1.0, 1.0, 1.0, 0.05);
+const TICK: Color = Color::from_rgb(0.5, 0.5, 0.5);
+const LABEL: Color = Color::from_rgb(0.75, 0.75, 0.75);
+
+/// Length of the tick marks across the axes, in logical pixels
+const MAJOR_TICK: f32 = 6.0;
+const MINOR_TICK: f32 = 3.0;
+
+const LABEL_SIZE: f32 = 12.0;
+
+/// Distance of the labels from their axis, and from the edges they are pinned to
+const LABEL_GAP: f32 = 4.0;
+
+/// Labels of the y axis are pinned to the left edge when the axis is closer to it than this
+const LABEL_ROOM: f32 = 40.0;
+
+/// The grid of a graph drawn with `controls`, to be stacked on top of its shader widget
+#[derive(Debug, Clone, Copy)]
+pub struct Axes {
+    controls: Controls,
+}
+
+impl Axes {
+    pub fn new(controls: Controls) -> Self {
+        Self { controls }
+    }
+}
+
+impl<Message> canvas::Program<Message> for Axes {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let size = dvec2(bounds.width.into(), bounds.height.into());
+        let controls = &self.controls;
+        let [x_scale, y_scale] = controls.axes;
+
+        let top_left = controls.to_view(DVec2::ZERO, size);
+        let bottom_right = controls.to_view(size, size);
+        let ratio = controls.pixel_ratio();
+        let x_ticks = Ticks::new(x_scale, ratio.x);
+        let y_ticks = Ticks::new(y_scale, ratio.y);
+
+        // Where the axes cross the screen, `None` for log axes which don't reach 0
+        let origin = controls.to_screen(DVec2::ZERO, size).as_vec2();
+        let x_axis = (y_scale != AxisScale::Log).then_some(origin.y);
+        let y_axis = (x_scale != AxisScale::Log).then_some(origin.x);
+
+        // Labels stay on screen when their axis is scrolled out of view
+        let x_labels_at = x_axis
+            .map_or(bounds.height, |y| y + LABEL_GAP)
+            .min(bounds.height - LABEL_SIZE - LABEL_GAP)
+            .max(LABEL_GAP);
+        let (y_labels_at, y_labels_align) = match y_axis {
+            Some(x) if x >= LABEL_ROOM && x <= bounds.width => {
+                (x - LABEL_GAP, text::Alignment::Right)
+            }
+            Some(x) if x > bounds.width => (bounds.width - LABEL_GAP, text::Alignment::Right),
+            _ => (LABEL_GAP, text::Alignment::Left),
+        };
+
+        // The origin is labelled once, below the x axis and left of the y axis
+        let origin_label = x_axis.is_some() && y_axis.is_some();
+
+        for tick in x_ticks.between(top_left.x, bottom_right.x) {
+            let x = controls.to_screen(dvec2(tick.value, 0.0), size).x as f32;
+            line(&mut frame, [x, 0.0], [x, bounds.height], tick.major);
+            if let Some(y) = x_axis {
+                let length = if tick.major { MAJOR_TICK } else { MINOR_TICK };
+                frame.stroke(
+                    &Path::line(Point::new(x, y - length), Point::new(x, y + length)),
+                    Stroke::default().with_color(TICK),
+                );
+            }
+
+            if tick.major && !(origin_label && tick.value == 0.0) {
+                label(
+                    &mut frame,
+                    x_ticks.label(tick.value),
+                    Point::new(x, x_labels_at),
+                    text::Alignment::Center,
+                    alignment::Vertical::Top,
+                );
+            }
+        }
+
+        for tick in y_ticks.between(bottom_right.y, top_left.y) {
+            let y = controls.to_screen(dvec2(0.0, tick.value), size).y as f32;
+            line(&mut frame, [0.0, y], [bounds.width, y], tick.major);
+            if let Some(x) = y_axis {
+                let length = if tick.major { MAJOR_TICK } else { MINOR_TICK };
+                frame.stroke(
+                    &Path::line(Point::new(x - length, y), Point::new(x + length, y)),
+                    Stroke::default().with_color(TICK),
+                );
+            }
+
+            if tick.major {
+                let (y, align_y) = if origin_label && tick.value == 0.0 {
+                    (y + LABEL_GAP, alignment::Vertical::Top)
+                } else {
+                    (y, alignment::Vertical::Center)
+                };
+                label(
+                    &mut frame,
+                    y_ticks.label(tick.value),
+                    Point::new(y_labels_at, y),
+                    y_labels_align,
+                    align_y,
+                );
+            }
+        }
+
+        vec![frame.into_geometry()]
+    }
+}
+
+fn line(frame: &mut Frame, [ax, ay]: [f32; 2], [bx, by]: [f32; 2], major: bool) {
+    let color = if major { MAJOR_LINE } else { MINOR_LINE };
+    frame.stroke(
+        &Path::line(Point::new(ax, ay), Point::new(bx, by)),
+        Stroke::default().with_color(color),
+    );
+}
+
+fn label(
+    frame: &mut Frame,
+    content: String,
+    position: Point,
+    align_x: text::Alignment,
+    align_y: alignment::Vertical,
+) {
+    frame.fill_text(canvas::Text {
+        content,
+        position,
+        color: LABEL,
+        size: Pixels(LABEL_SIZE),
+        align_x,
+        align_y,
+        // For π
+        shaping: text::Shaping::Advanced,
+        ..canvas::Text::default()
+    });
+}
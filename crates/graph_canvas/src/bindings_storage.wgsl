@@ -0,0 +1,15 @@
+// Plot descriptions and instructions in storage buffers, which grow with the program
+
+@group(0) @binding(1)
+var<storage, read> plot_desc: array<PlotDesc>;
+
+@group(1) @binding(0)
+var<storage, read> instructions: array<Instruction>;
+
+fn get_plot_desc(index: u32) -> PlotDesc {
+    return plot_desc[index];
+}
+
+fn get_instruction(index: u32) -> Instruction {
+    return instructions[index];
+}
@@ -0,0 +1,33 @@
+// Plot descriptions and instructions in uniform buffers of a fixed size, for backends without
+// storage buffers in fragment shaders (WebGL). `MAX_PLOTS` and `MAX_INSTRUCTIONS` are
+// added by `shader_source` in `graph_shader_pipeline.rs`
+
+@group(0) @binding(1)
+var<uniform> plot_desc: array<PlotDesc, MAX_PLOTS>;
+
+// Elements of arrays in uniform buffers are 16 byte aligned, so every vec4 holds two
+// instructions of 8 bytes
+@group(1) @binding(0)
+var<uniform> instructions: array<vec4<u32>, MAX_INSTRUCTIONS / 2u>;
+
+fn get_plot_desc(index: u32) -> PlotDesc {
+    return plot_desc[index];
+}
+
+// Unpack the instruction at `index`
+fn get_instruction(index: u32) -> Instruction {
+    let vec_idx = index / 2u;
+    let is_second = index % 2u;
+    
+    let raw_data = instructions[vec_idx];
+    
+    // If is_second is 0, we read X/Y. If 1, we read Z/W.
+    // We multiply by 2 to shift our access to the correct half of the vec4.
+    let base_component = is_second * 2u; 
+    
+    var inst: Instruction;
+    inst.opcode = raw_data[base_component];
+    inst.a = bitcast<f32>(raw_data[base_component + 1u]);
+    
+    return inst;
+}
@@ -1,24 +1,146 @@
-use glam::DVec2;
+use glam::{DVec2, dvec2};
+use mth_common::{ops::Instruction, plot_desc::PlotDesc};
 
-use crate::graph_shader_pipeline::ZOOM_PIXELS_FACTOR;
+use crate::{fit, grid::AxisScale};
+
+/// Room around the plots fitted by [`Controls::fit`], as a share of the size of the graph
+pub const FIT_MARGIN: f64 = 0.1;
 
 #[derive(Debug, Clone, Copy)]
 pub struct Controls {
-    pub zoom: f64,
+    /// Zoom levels of the x and y axis, which are scaled independently
+    pub zoom: DVec2,
     pub offset: DVec2,
+
+    /// Scales of the x and y axis
+    pub axes: [AxisScale; 2],
 }
 
 impl Controls {
-    pub fn pixel_ratio(&self) -> f64 {
-        1.0 / 2.0_f64.powf(self.zoom) / ZOOM_PIXELS_FACTOR
+    /// Width and height of a logical pixel in view units, see [`mth_common::pixel_ratio`]. The
+    /// graph looks the same at every scale factor of the window
+    pub fn pixel_ratio(&self) -> DVec2 {
+        dvec2(
+            mth_common::pixel_ratio(self.zoom.x),
+            mth_common::pixel_ratio(self.zoom.y),
+        )
+    }
+
+    /// View coordinates of the position `pos` in logical pixels from the top left corner of a
+    /// graph of the size `size`. They are the graph coordinates of linear axes, and their
+    /// logarithms for [`AxisScale::Log`]
+    pub fn to_view(&self, pos: DVec2, size: DVec2) -> DVec2 {
+        let scaled = (pos - size * 0.5) * self.pixel_ratio() + self.offset;
+        dvec2(scaled.x, -scaled.y)
+    }
+
+    /// Position in logical pixels of the view coordinates `v`, the inverse of
+    /// [`Controls::to_view`]
+    pub fn to_screen(&self, v: DVec2, size: DVec2) -> DVec2 {
+        (dvec2(v.x, -v.y) - self.offset) / self.pixel_ratio() + size * 0.5
+    }
+
+    /// Move the graph by `delta` logical pixels, like dragging it
+    pub fn pan(&mut self, delta: DVec2) {
+        self.offset -= delta * self.pixel_ratio();
+    }
+
+    /// Zoom in by `delta` zoom levels along the x and y axis, keeping the point at the position
+    /// `pos` in place
+    pub fn zoom_at(&mut self, pos: DVec2, size: DVec2, delta: DVec2) {
+        let prev_ratio = self.pixel_ratio();
+        self.zoom += delta;
+        self.offset += (pos - size * 0.5) * (prev_ratio - self.pixel_ratio());
+    }
+
+    /// Go back to the view at startup, keeping the scales of the axes
+    pub fn reset(&mut self) {
+        *self = Self {
+            axes: self.axes,
+            ..Self::default()
+        };
+    }
+
+    /// Move and zoom so that the graph coordinates `x` and `y`, each from the smallest to the
+    /// largest, exactly fill a graph of the size `size`. Ranges that are empty, or that a log
+    /// axis can't show, leave the view along their axis as it is
+    pub fn show(&mut self, x: [f64; 2], y: [f64; 2], size: DVec2) {
+        for (axis, [min, max]) in [x, y].into_iter().enumerate() {
+            let [min, max] = [min, max].map(|value| self.axes[axis].to_view(value));
+            if !(min.is_finite() && max.is_finite() && min < max && size[axis] > 0.0) {
+                continue;
+            }
+            self.zoom[axis] = mth_common::zoom_for_pixel_ratio((max - min) / size[axis]);
+            // The offset points down like the screen
+            let center = (min + max) * 0.5;
+            self.offset[axis] = if axis == 0 { center } else { -center };
+        }
+    }
+
+    /// Move and zoom so that all plots of a program are in view, with [`FIT_MARGIN`] around
+    /// them. Function graphs are sampled across the visible range of their argument, which
+    /// stays in view, and both axes are zoomed independently to fill the graph. Only curves
+    /// keep the aspect ratio of the axes the same, so that circles stay round. Returns `false`
+    /// if there was nothing to fit, like for equations
+    pub fn fit(
+        &mut self,
+        instructions: &[Instruction],
+        plot_descs: &[PlotDesc],
+        size: DVec2,
+    ) -> bool {
+        let top_left = self.to_view(DVec2::ZERO, size);
+        let bottom_right = self.to_view(size, size);
+        let visible = [[top_left.x, bottom_right.x], [bottom_right.y, top_left.y]];
+        let bounds = fit::bounds(
+            instructions,
+            plot_descs,
+            self.axes,
+            dvec2(top_left.x, bottom_right.y),
+            dvec2(bottom_right.x, top_left.y),
+        );
+        if bounds.values == [None; 2] {
+            return false;
+        }
+
+        // The pixel ratio each axis needs, 0.0 if all plots are single points along it
+        let mut ratio = self.pixel_ratio();
+        let mut center = self.to_view(size * 0.5, size);
+        for axis in 0..2 {
+            let range = match (bounds.values[axis], bounds.keep[axis]) {
+                (Some([min, max]), true) => {
+                    let [lo, hi] = [min.min(visible[axis][0]), max.max(visible[axis][1])];
+                    ratio[axis] = (hi - lo) / size[axis];
+                    [lo, hi]
+                }
+                (None, true) => visible[axis],
+                (Some([min, max]), false) => {
+                    ratio[axis] = (max - min) / (size[axis] * (1.0 - 2.0 * FIT_MARGIN));
+                    [min, max]
+                }
+                (None, false) => continue,
+            };
+            center[axis] = (range[0] + range[1]) * 0.5;
+        }
+
+        if bounds.keep == [false; 2] {
+            ratio = DVec2::splat(ratio.max_element());
+        }
+        for axis in 0..2 {
+            if ratio[axis] > 0.0 {
+                self.zoom[axis] = mth_common::zoom_for_pixel_ratio(ratio[axis]);
+            }
+        }
+        self.offset = dvec2(center.x, -center.y);
+        true
     }
 }
 
 impl Default for Controls {
     fn default() -> Self {
         Self {
-            zoom: 1.,
+            zoom: DVec2::ONE,
             offset: DVec2::ZERO,
+            axes: [AxisScale::Linear; 2],
         }
     }
 }
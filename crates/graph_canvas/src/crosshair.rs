@@ -0,0 +1,351 @@
+//! The coordinates under the cursor and the values of the plots there, from a CPU evaluation
+//! of their programs, drawn over the graph by an iced canvas
+
+use std::sync::{Arc, Mutex};
+
+use glam::{DVec2, dvec2};
+use iced::{
+    Color, Pixels, Point, Rectangle, Renderer, Size, Theme, alignment, mouse,
+    widget::{
+        Action,
+        canvas::{self, Event, Frame, Geometry, Path, Stroke},
+        text,
+    },
+};
+use mth_common::{
+    CURVE_SEGMENTS, MAX_PARAMS, PLOT_TYPE_FN_GRAPH, PLOT_TYPE_INVERSE, PLOT_TYPE_PARAMETRIC,
+    PLOT_TYPE_POLAR, ops::Instruction, plot_desc::PlotDesc,
+};
+use mth_interpreter::{bind_parameters, curve_point, eval, plots};
+
+use crate::{
+    controls::Controls,
+    grid::{AxisScale, format_number},
+};
+
+/// The crosshair snaps to points of plots closer to the cursor than this, in logical pixels
+pub const SNAP_DISTANCE: f64 = 12.0;
+
+const LINE: Color = Color::from_rgba(1.0, 1.0, 1.0, 0.3);
+const BACKGROUND: Color = Color::from_rgba(0.0, 0.0, 0.0, 0.75);
+const TEXT: Color = Color::from_rgb(0.9, 0.9, 0.9);
+
+const TEXT_SIZE: f32 = 12.0;
+const LINE_HEIGHT: f32 = 16.0;
+
+/// Rough width of a character of the text, to keep the box of the readings on screen
+const CHAR_WIDTH: f32 = 7.0;
+
+/// Distance of the box of the readings from the crosshair, and of its text from its border
+const BOX_GAP: f32 = 12.0;
+const BOX_PADDING: f32 = 6.0;
+
+/// Radius of the marker at the point the crosshair snapped to
+const MARKER_RADIUS: f32 = 4.0;
+
+/// Longer names of plots are cut off
+const MAX_NAME_CHARS: usize = 24;
+
+/// The point of a plot at the crosshair
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reading {
+    /// Index of the plot in the plot descriptions
+    pub plot: usize,
+    pub type_id: u32,
+
+    /// Graph coordinates of the point: `(x, f(x))` at the x coordinate of the crosshair for
+    /// function graphs, `(g(y), y)` at its y coordinate for inverse ones, and the sample
+    /// closest to it for parametric and polar curves
+    pub point: DVec2,
+}
+
+/// What is under the cursor, see [`hover`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hover {
+    /// Graph coordinates of the crosshair
+    pub point: DVec2,
+
+    /// The reading the crosshair snapped to, an index into `readings`
+    pub snapped: Option<usize>,
+
+    /// The plots that have a value at the crosshair, in the order of the plot descriptions.
+    /// Equations and plots that failed to evaluate have none
+    pub readings: Vec<Reading>,
+}
+
+/// The crosshair at the position `pos` in logical pixels of a graph of the size `size`. It is
+/// at the cursor, or at the closest point of a plot within [`SNAP_DISTANCE`]
+pub fn hover(
+    instructions: &[Instruction],
+    plot_descs: &[PlotDesc],
+    controls: &Controls,
+    pos: DVec2,
+    size: DVec2,
+) -> Hover {
+    let at_cursor = to_graph(controls, controls.to_view(pos, size));
+    let cursor_readings = readings(instructions, plot_descs, controls, at_cursor, size);
+
+    let snapped = cursor_readings
+        .iter()
+        .map(|reading| to_screen(controls, reading.point, size).distance(pos))
+        .enumerate()
+        .filter(|(_, distance)| *distance < SNAP_DISTANCE)
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(i, _)| cursor_readings[i]);
+    let Some(snapped) = snapped else {
+        return Hover {
+            point: at_cursor,
+            snapped: None,
+            readings: cursor_readings,
+        };
+    };
+
+    // The other plots are read where the crosshair snapped to
+    let readings = readings(instructions, plot_descs, controls, snapped.point, size);
+    Hover {
+        point: snapped.point,
+        snapped: readings
+            .iter()
+            .position(|reading| reading.plot == snapped.plot),
+        readings,
+    }
+}
+
+/// The points of the plots at the graph coordinates `at`
+fn readings(
+    instructions: &[Instruction],
+    plot_descs: &[PlotDesc],
+    controls: &Controls,
+    at: DVec2,
+    size: DVec2,
+) -> Vec<Reading> {
+    let at_screen = to_screen(controls, at, size);
+    plots(plot_descs)
+        .enumerate()
+        .filter_map(|(i, plot)| {
+            let program = instructions.get(plot.offset..plot.offset + plot.len)?;
+            let point = match plot.type_id {
+                PLOT_TYPE_FN_GRAPH => {
+                    let value = eval(program, at.x as f32, 0.0).ok()?;
+                    dvec2(at.x, value.into())
+                }
+                PLOT_TYPE_INVERSE => {
+                    let value = eval(program, 0.0, at.y as f32).ok()?;
+                    dvec2(value.into(), at.y)
+                }
+                PLOT_TYPE_PARAMETRIC | PLOT_TYPE_POLAR => (0..=CURVE_SEGMENTS)
+                    .map_while(|i| curve_point(instructions, &plot, i).ok())
+                    .map(|[x, y]| dvec2(x.into(), y.into()))
+                    .filter(|point| to_screen(controls, *point, size).is_finite())
+                    .min_by(|a, b| {
+                        let distance = |p| to_screen(controls, p, size).distance(at_screen);
+                        distance(*a).total_cmp(&distance(*b))
+                    })?,
+                _ => return None,
+            };
+            point.is_finite().then_some(Reading {
+                plot: i,
+                type_id: plot.type_id,
+                point,
+            })
+        })
+        .collect()
+}
+
+fn to_graph(controls: &Controls, v: DVec2) -> DVec2 {
+    dvec2(
+        controls.axes[0].to_graph(v.x),
+        controls.axes[1].to_graph(v.y),
+    )
+}
+
+/// Position in logical pixels of the graph coordinates `p`, not finite where a log axis can't
+/// show them
+fn to_screen(controls: &Controls, p: DVec2, size: DVec2) -> DVec2 {
+    let v = dvec2(controls.axes[0].to_view(p.x), controls.axes[1].to_view(p.y));
+    controls.to_screen(v, size)
+}
+
+/// Crosshair over a graph drawn with `controls`, to be stacked on top of its shader widget.
+/// It reads the plots of the same program as the graph with the values `params` of its
+/// sliders at the time `time`, and labels them with `names`
+#[derive(Debug, Clone)]
+pub struct Crosshair {
+    controls: Controls,
+    params: [f32; MAX_PARAMS],
+    time: f32,
+    instructions: Arc<Mutex<Vec<Instruction>>>,
+    plot_descs: Arc<[PlotDesc]>,
+    names: Arc<[String]>,
+}
+
+impl Crosshair {
+    pub fn new(
+        controls: Controls,
+        params: [f32; MAX_PARAMS],
+        time: f32,
+        instructions: Arc<Mutex<Vec<Instruction>>>,
+        plot_descs: Arc<[PlotDesc]>,
+        names: Arc<[String]>,
+    ) -> Self {
+        Self {
+            controls,
+            params,
+            time,
+            instructions,
+            plot_descs,
+            names,
+        }
+    }
+
+    /// The name of the plot `plot`, or its number without one
+    fn name(&self, plot: usize) -> String {
+        match self.names.get(plot) {
+            Some(name) if name.chars().count() > MAX_NAME_CHARS => {
+                let cut: String = name.chars().take(MAX_NAME_CHARS - 1).collect();
+                format!("{cut}…")
+            }
+            Some(name) => name.clone(),
+            None => format!("plot {}", plot + 1),
+        }
+    }
+
+    /// The graph coordinate `value` of `axis` to the precision of a pixel
+    fn format(&self, axis: usize, value: f64) -> String {
+        let ratio = self.controls.pixel_ratio()[axis];
+        let step = match self.controls.axes[axis] {
+            // A pixel is a factor, not a distance. Values of 0 are printed as such
+            AxisScale::Log => {
+                (value.abs() * ratio * std::f64::consts::LN_10).max(f64::MIN_POSITIVE)
+            }
+            _ => ratio,
+        };
+        format_number(value, step)
+    }
+
+    /// A line of text per reading, `x = …, y = …` first
+    fn lines(&self, hover: &Hover) -> Vec<(String, Color)> {
+        let point = hover.point;
+        let mut lines = vec![(
+            format!(
+                "x = {}, y = {}",
+                self.format(0, point.x),
+                self.format(1, point.y)
+            ),
+            TEXT,
+        )];
+        for reading in &hover.readings {
+            let name = self.name(reading.plot);
+            let p = reading.point;
+            let line = match reading.type_id {
+                PLOT_TYPE_FN_GRAPH => format!("{name} = {}", self.format(1, p.y)),
+                PLOT_TYPE_INVERSE => format!("{name} = {}", self.format(0, p.x)),
+                _ => format!("{name}: ({}, {})", self.format(0, p.x), self.format(1, p.y)),
+            };
+            let [r, g, b] = self.plot_descs[reading.plot].color;
+            lines.push((line, Color::from_rgb(r, g, b)));
+        }
+        lines
+    }
+}
+
+impl<Message> canvas::Program<Message> for Crosshair {
+    type State = ();
+
+    fn update(
+        &self,
+        _state: &mut Self::State,
+        event: &Event,
+        _bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Option<Action<Message>> {
+        match event {
+            Event::Mouse(mouse::Event::CursorMoved { .. } | mouse::Event::CursorLeft) => {
+                Some(Action::request_redraw())
+            }
+            _ => None,
+        }
+    }
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let Some(pos) = cursor.position_in(bounds) else {
+            return Vec::new();
+        };
+        let mut frame = Frame::new(renderer, bounds.size());
+        let size = dvec2(bounds.width.into(), bounds.height.into());
+
+        let hover = {
+            let instructions = bind_parameters(
+                &self
+                    .instructions
+                    .lock()
+                    .expect("Could not lock instructions mutex in Crosshair::draw"),
+                &self.params,
+                self.time,
+            );
+            hover(
+                &instructions,
+                &self.plot_descs,
+                &self.controls,
+                dvec2(pos.x.into(), pos.y.into()),
+                size,
+            )
+        };
+
+        let at = to_screen(&self.controls, hover.point, size).as_vec2();
+        let at = Point::new(at.x, at.y);
+        let stroke = Stroke::default().with_color(LINE);
+        frame.stroke(
+            &Path::line(Point::new(at.x, 0.0), Point::new(at.x, bounds.height)),
+            stroke,
+        );
+        frame.stroke(
+            &Path::line(Point::new(0.0, at.y), Point::new(bounds.width, at.y)),
+            stroke,
+        );
+        if let Some(snapped) = hover.snapped {
+            let [r, g, b] = self.plot_descs[hover.readings[snapped].plot].color;
+            frame.fill(&Path::circle(at, MARKER_RADIUS), Color::from_rgb(r, g, b));
+        }
+
+        // Below and right of the crosshair, unless that is off screen
+        let lines = self.lines(&hover);
+        let longest = lines.iter().map(|(line, _)| line.chars().count()).max();
+        let box_size = Size::new(
+            longest.unwrap_or(0) as f32 * CHAR_WIDTH + 2.0 * BOX_PADDING,
+            lines.len() as f32 * LINE_HEIGHT + 2.0 * BOX_PADDING,
+        );
+        let mut corner = Point::new(at.x + BOX_GAP, at.y + BOX_GAP);
+        if corner.x + box_size.width > bounds.width {
+            corner.x = at.x - BOX_GAP - box_size.width;
+        }
+        if corner.y + box_size.height > bounds.height {
+            corner.y = at.y - BOX_GAP - box_size.height;
+        }
+        frame.fill_rectangle(corner, box_size, BACKGROUND);
+        for (i, (content, color)) in lines.into_iter().enumerate() {
+            frame.fill_text(canvas::Text {
+                content,
+                position: Point::new(
+                    corner.x + BOX_PADDING,
+                    corner.y + BOX_PADDING + i as f32 * LINE_HEIGHT,
+                ),
+                color,
+                size: Pixels(TEXT_SIZE),
+                align_y: alignment::Vertical::Top,
+                // For π and names in other scripts
+                shaping: text::Shaping::Advanced,
+                ..canvas::Text::default()
+            });
+        }
+
+        vec![frame.into_geometry()]
+    }
+}
@@ -0,0 +1,107 @@
+//! Where the plots of a program are, from samples of their values, to choose a view that shows
+//! all of them
+
+use glam::DVec2;
+use mth_common::{
+    CURVE_SEGMENTS, PLOT_TYPE_FN_GRAPH, PLOT_TYPE_INVERSE, PLOT_TYPE_PARAMETRIC, PLOT_TYPE_POLAR,
+    ops::Instruction, plot_desc::PlotDesc,
+};
+use mth_interpreter::{curve_point, eval, plots};
+
+use crate::grid::AxisScale;
+
+/// Samples of a function graph across the visible range
+const SAMPLES: usize = 512;
+
+/// Share of the samples of a function graph left out at each end of its values, so that
+/// asymptotes don't take up the whole view
+const OUTLIERS: f64 = 0.02;
+
+/// The extent of the plots along each axis in view coordinates
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct Bounds {
+    /// Smallest and largest sampled coordinate of the x and y axis, `None` without samples
+    pub(crate) values: [Option<[f64; 2]>; 2],
+
+    /// Whether a function graph spans the visible range of the x or y axis, which is kept
+    pub(crate) keep: [bool; 2],
+}
+
+impl Bounds {
+    fn include(&mut self, axis: usize, [min, max]: [f64; 2]) {
+        self.values[axis] = Some(match self.values[axis] {
+            Some([lo, hi]) => [lo.min(min), hi.max(max)],
+            None => [min, max],
+        });
+    }
+}
+
+/// Sample the plots between the view coordinates `min` and `max`. Function graphs are sampled
+/// across the visible x range, inverse ones across the y range, parametric and polar curves
+/// over their whole parameter range. Equations aren't sampled
+pub(crate) fn bounds(
+    instructions: &[Instruction],
+    plot_descs: &[PlotDesc],
+    axes: [AxisScale; 2],
+    min: DVec2,
+    max: DVec2,
+) -> Bounds {
+    let mut bounds = Bounds::default();
+    for plot in plots(plot_descs) {
+        let Some(program) = instructions.get(plot.offset..plot.offset + plot.len) else {
+            continue;
+        };
+
+        match plot.type_id {
+            PLOT_TYPE_FN_GRAPH | PLOT_TYPE_INVERSE => {
+                // The axis of the argument and the one of the values
+                let (along, across) = match plot.type_id {
+                    PLOT_TYPE_FN_GRAPH => (0, 1),
+                    _ => (1, 0),
+                };
+                let values: Vec<f64> = (0..SAMPLES)
+                    .filter_map(|i| {
+                        let v = min[along]
+                            + (max[along] - min[along]) * i as f64 / (SAMPLES - 1) as f64;
+                        let arg = axes[along].to_graph(v) as f32;
+                        let value = match along {
+                            0 => eval(program, arg, 0.0),
+                            _ => eval(program, 0.0, arg),
+                        };
+                        value.ok().map(|value| axes[across].to_view(value.into()))
+                    })
+                    .filter(|value| value.is_finite())
+                    .collect();
+
+                bounds.keep[along] = true;
+                if let Some(range) = trimmed_range(values) {
+                    bounds.include(across, range);
+                }
+            }
+            PLOT_TYPE_PARAMETRIC | PLOT_TYPE_POLAR => {
+                for i in 0..=CURVE_SEGMENTS {
+                    let Ok(point) = curve_point(instructions, &plot, i) else {
+                        break;
+                    };
+                    let v = [0, 1].map(|axis| axes[axis].to_view(point[axis].into()));
+                    if v.iter().all(|c| c.is_finite()) {
+                        bounds.include(0, [v[0], v[0]]);
+                        bounds.include(1, [v[1], v[1]]);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    bounds
+}
+
+/// The range of `values` without the [`OUTLIERS`] at both ends
+fn trimmed_range(mut values: Vec<f64>) -> Option<[f64; 2]> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f64::total_cmp);
+    let skip = (values.len() as f64 * OUTLIERS) as usize;
+    Some([values[skip], values[values.len() - 1 - skip]])
+}
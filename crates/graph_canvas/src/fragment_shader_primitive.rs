@@ -1,34 +1,43 @@
 use std::sync::{Arc, Mutex};
 
-use glam::{vec2, Vec2};
-use iced::{wgpu, widget::shader, Rectangle};
+use glam::{Vec2, vec2};
+use iced::{Rectangle, wgpu, widget::shader};
 
 use crate::{
     controls::Controls,
-    graph_shader_pipeline::{FragmentShaderPipeline, Uniforms, N_INSTRUCTIONS},
+    graph_shader_pipeline::{FragmentShaderPipeline, Uniforms},
+    grid::AxisScale,
 };
-use mth_common::{ops::Instruction, plot_desc::PlotDesc, N_PLOTS};
+use mth_common::{MAX_PARAMS, ops::Instruction, plot_desc::PlotDesc};
 
 #[derive(Debug)]
 pub struct FragmentShaderPrimitive {
     controls: Controls,
-    instructions: Arc<Mutex<[Instruction; N_INSTRUCTIONS]>>,
-    plot_desc: [PlotDesc; N_PLOTS],
-    pub instructions_dirty: bool,
+    params: [f32; MAX_PARAMS],
+    time: f32,
+    instructions: Arc<Mutex<Vec<Instruction>>>,
+    plot_desc: Arc<[PlotDesc]>,
+
+    /// Generation of the program in `instructions`, which changes whenever a new one is written
+    program_generation: u64,
 }
 
 impl FragmentShaderPrimitive {
     pub fn new(
         controls: Controls,
-        instructions: Arc<Mutex<[Instruction; N_INSTRUCTIONS]>>,
-        plot_desc: [PlotDesc; N_PLOTS],
-        instructions_dirty: bool,
+        params: [f32; MAX_PARAMS],
+        time: f32,
+        instructions: Arc<Mutex<Vec<Instruction>>>,
+        plot_desc: Arc<[PlotDesc]>,
+        program_generation: u64,
     ) -> Self {
         Self {
             controls,
+            params,
+            time,
             instructions,
             plot_desc,
-            instructions_dirty,
+            program_generation,
         }
     }
 }
@@ -39,20 +48,26 @@ impl shader::Primitive for FragmentShaderPrimitive {
     fn prepare(
         &self,
         pipeline: &mut Self::Pipeline,
-        _device: &wgpu::Device,
+        device: &wgpu::Device,
         queue: &wgpu::Queue,
         bounds: &Rectangle,
         viewport: &shader::Viewport,
     ) {
         let scale_factor = viewport.scale_factor();
-        let viewport_size = Vec2::new(
-            bounds.width * scale_factor as f32,
-            bounds.height * scale_factor as f32,
-        );
-        let viewport_origin = vec2(
-            bounds.x * scale_factor as f32,
-            bounds.y * scale_factor as f32,
-        );
+        let viewport_size = Vec2::new(bounds.width * scale_factor, bounds.height * scale_factor);
+        let viewport_origin = vec2(bounds.x * scale_factor, bounds.y * scale_factor);
+
+        // Upload each program once, before the uniforms which hold its counts. Moving a slider
+        // or advancing the time only changes the uniforms
+        if pipeline.program_generation() != self.program_generation {
+            pipeline.update_program(
+                device,
+                queue,
+                self.program_generation,
+                &self.instructions,
+                &self.plot_desc,
+            );
+        }
 
         // Always update uniforms
         pipeline.update_uniforms(
@@ -61,17 +76,19 @@ impl shader::Primitive for FragmentShaderPrimitive {
                 viewport_origin,
                 viewport_size,
                 pan_offset: self.controls.offset.as_vec2(),
-                pixel_ratio: self.controls.pixel_ratio() as f32,
-                _pad: 0,
+                pixel_ratio: (self.controls.pixel_ratio() / scale_factor as f64).as_vec2(),
+                // Set by the pipeline
+                n_plots: 0,
+                n_instructions: 0,
+                log_scale: self
+                    .controls
+                    .axes
+                    .map(|scale| (scale == AxisScale::Log).into()),
+                params: bytemuck::cast(self.params),
+                time: self.time,
+                _pad: [0.0; 3],
             },
         );
-
-        // Update instructions if necessary
-        if self.instructions_dirty {
-            let n_instructions = self.plot_desc.iter().fold(0, |acc, desc| acc + desc.length);
-            pipeline.update_plot_desc(queue, &self.plot_desc);
-            pipeline.update_program(queue, &self.instructions, n_instructions as usize);
-        }
     }
 
     fn render(
@@ -1,42 +1,12 @@
 
+// `STACK_SIZE`, `N_LOCALS`, `MAX_PARAMS`, `CURVE_SEGMENTS`, the `PLOT_TYPE_*` constants and the bindings of the plot descriptions and
+// instructions are added by `shader_source` in `graph_shader_pipeline.rs`, the `OP_*` constants
+// and `execute_instruction` are generated from `mth_common::ops::OPS` by build.rs
+
 const STROKE_WIDTH: f32 = 1.;
 
-const N_INSTRUCTIONS: u32 = 256u;
-const N_INSTRUCTION_PACKS: u32 = N_INSTRUCTIONS / 2u;
-const N_PLOTS: u32 = 8u;
-const STACK_SIZE: u32 = 16u;
-
-const PLOT_TYPE_NO_PLOT: u32 = 0u;
-const PLOT_TYPE_FN_GRAPH: u32 = 1u;
-const PLOT_TYPE_EQUATION: u32 = 2u;
-
-// OpCodes
-const OP_CONST: u32 = 0;
-const OP_X: u32 = 1;
-const OP_ADD: u32 = 2;
-const OP_SUB: u32 = 3;
-const OP_MUL: u32 = 4;
-const OP_DIV: u32 = 5;
-const OP_POW: u32 = 6;
-const OP_COS: u32 = 7;
-const OP_SIN: u32 = 8;
-const OP_TAN: u32 = 9;
-const OP_LOG: u32 = 10;
-const OP_EQ: u32 =  11;
-const OP_LT: u32 =  12;  // <
-const OP_LE: u32 =  13;  // <=
-const OP_GT: u32 =  14;  // >
-const OP_GE: u32 =  15;  // >=
-const OP_NE: u32 =  16;  // !=
-const OP_Y : u32 =  17;
-const OP_ABS: u32 = 18;
-
-const OP_OR: u32 =  19;
-const OP_AND: u32 = 20;
-
-const OP_BW_OR: u32 =  21;
-const OP_BW_XOR: u32 = 22;
-const OP_BW_AND: u32 = 23;
+// Largest finite f32
+const F32_MAX: f32 = 3.40282347e38;
 
 struct Instruction {
     opcode: u32,
@@ -47,29 +17,28 @@ struct Uniforms {
     viewport_origin: vec2f,
     viewport_size: vec2f,
     pan_offset: vec2f,
-    pixel_ratio: f32,
-    _pad: u32,
+    pixel_ratio: vec2f, // view units per pixel of the x and y axis
+    n_plots: u32,
+    n_instructions: u32,
+    log_scale: vec2u,   // 1 for axes with a log scale
+    params: array<vec4f, MAX_PARAMS / 4u>, // values of the sliders
+    time: f32,          // seconds of the animation
 }
 
 struct PlotDesc {
     length: u32,
     type_id: u32,
-    _pad: vec2f,   // 16-byte alignment
+    range: vec2f,  // parameter range of curves
+    color: vec3f,
+    width: f32,    // in multiples of STROKE_WIDTH
+    dash: vec2f,   // lengths of dashes and gaps in pixels, 0 for solid lines
+    fill: f32,     // opacity of the area of the plot
+    _pad: u32,
 }
 
-// 8 bytes per instruction ==> 256 instructions = 2KB
-alias InstructionArray = array<vec4<u32>, N_INSTRUCTION_PACKS>; 
-alias PlotDescArray = array<PlotDesc, N_PLOTS>;
-
 @group(0) @binding(0)
 var<uniform> u: Uniforms;
 
-@group(0) @binding(1)
-var<uniform> plot_desc: PlotDescArray;
-
-@group(1) @binding(0)
-var<uniform> instructions: InstructionArray;
-
 struct VertexIn {
     @builtin(vertex_index)
     vertex_index: u32,
@@ -89,48 +58,82 @@ fn vs_main(in: VertexIn) -> VertexOut {
 
 @fragment
 fn fs_main(in: VertexOut) -> @location(0) vec4f {
-    // ~= thickness of lines
-    let d = u.pixel_ratio * STROKE_WIDTH;
+    // ~= thickness of lines, in pixels
+    let d = STROKE_WIDTH;
 
     let local_pos = in.position.xy - u.viewport_origin;
-    let scaled_pos = (local_pos - u.viewport_size * 0.5) * u.pixel_ratio;
+    let centered_pos = local_pos - u.viewport_size * 0.5;
 
     // Draw viewport origin indicator
-    if abs(scaled_pos.x) < d && abs(scaled_pos.y) < d {
+    if abs(centered_pos.x) < d && abs(centered_pos.y) < d {
         return vec4f(0.5, 0.5, 0.5, 1.);
     }
 
-    var p = scaled_pos + u.pan_offset;
-    p.y = -p.y; // invert y axis for mathematics
+    var v = centered_pos * u.pixel_ratio + u.pan_offset;
+    v.y = -v.y; // invert y axis for mathematics
 
     // Draw function
-    return draw_graph(p, d);
+    return draw_graph(v, d);
 }
 
+// Graph coordinates of the view coordinates `v`. Axes with a log scale show the powers of ten
+// of their view coordinates. Lines are measured in pixels, see `to_pixels`
+fn to_graph(v: vec2f) -> vec2f {
+    return select(v, pow(vec2f(10.0), v), u.log_scale != vec2u(0u));
+}
 
-fn draw_graph(p: vec2f, d: f32) -> vec4f {
-    var color = 0.0;
+// View coordinates of the graph coordinates `p`, the inverse of `to_graph`. Coordinates at or
+// below 0 of log axes are at -F32_MAX, below everything else
+fn to_view(p: vec2f) -> vec2f {
+    let log = select(vec2f(-F32_MAX), log2(p) / log2(10.0), p > vec2f(0.0));
+    return select(p, log, u.log_scale != vec2u(0u));
+}
+
+// The view coordinates `v` in pixels from the view origin, where distances are the same along
+// both axes whatever their scales
+fn to_pixels(v: vec2f) -> vec2f {
+    return v / u.pixel_ratio;
+}
+
+
+// `v` is in view coordinates, see `to_graph`, and `d` in pixels
+fn draw_graph(v: vec2f, d: f32) -> vec4f {
+    var color = vec3f(0.0);
     var offset: u32 = 0;
 
-    if abs(p.x) < d || abs(p.y) < d {
+    // Log axes don't reach 0
+    let on_axis = abs(to_pixels(v)) < vec2f(d) & u.log_scale == vec2u(0u);
+    if any(on_axis) {
         // x and y axis
-        color = 0.3;
+        color = vec3f(0.3);
     }
 
-    for (var i: u32 = 0; i < N_PLOTS; i = i + 1u) {
+    for (var i: u32 = 0; i < u.n_plots; i = i + 1u) {
 
-        let desc = plot_desc[i];
+        let desc = get_plot_desc(i);
+        let plot_d = d * desc.width;
+
+        // On the line of the plot, and in its area
+        var coverage: vec2f;
         switch desc.type_id {
             case PLOT_TYPE_NO_PLOT: {
-                return vec4f(color, color, color, 1.0);
+                return vec4f(color, 1.0);
             }
 
             case PLOT_TYPE_FN_GRAPH: {
-                color = max(color, is_on_curve(offset, desc.length, p.x, p.y, d));
+                coverage = is_on_curve(desc, offset, v, plot_d);
             }
 
             case PLOT_TYPE_EQUATION: {
-                color = max(color, eval_function(offset, desc.length, p.x, p.y));
+                coverage = draw_equation(desc, offset, v, plot_d);
+            }
+
+            case PLOT_TYPE_PARAMETRIC, PLOT_TYPE_POLAR: {
+                coverage = is_on_parametric_curve(desc, offset, v, plot_d);
+            }
+
+            case PLOT_TYPE_INVERSE: {
+                coverage = is_on_inverse_curve(desc, offset, v, plot_d);
             }
 
             default: {
@@ -140,35 +143,214 @@ fn draw_graph(p: vec2f, d: f32) -> vec4f {
 
         offset = offset + desc.length;
 
-        if color < 0.0 {
+        if coverage.x < 0.0 {
             // magenta == error
             return vec4f(1.0, 0.0, 1.0, 1.0);
         }
+
+        color = mix(color, desc.color, max(coverage.x, desc.fill * coverage.y));
+    }
+
+    return vec4f(color, 1.0);
+}
+
+// 1.0 on the dashes of a line, `s` pixels along it
+fn dash_at(dash: vec2f, s: f32) -> f32 {
+    if dash.y <= 0.0 {
+        return 1.0;
     }
+    let period = dash.x + dash.y;
+    return step(s - floor(s / period) * period, dash.x);
+}
+
+// Position along a line through `q` in the direction `tangent`, all in pixels
+fn along(q: vec2f, tangent: vec2f) -> f32 {
+    return dot(q, tangent / max(length(tangent), 1e-30));
+}
 
-    return vec4f(color, color, color, 1.0);
+// The area of a graph is between the curve and the axis
+fn between_zero_and(v: f32, curve: f32) -> f32 {
+    return select(0.0, 1.0, (0.0 <= v && v <= curve) || (curve <= v && v <= 0.0));
 }
 
 
-fn is_on_curve(offset: u32, len: u32, x: f32, y: f32, d: f32) -> f32 {
-    let curve_y = eval_function(offset, len, x, 0.0); // y coordinate not used for 1D functions
+fn is_on_curve(desc: PlotDesc, offset: u32, v: vec2f, d: f32) -> vec2f {
+    let x = to_graph(v).x;
+    // y coordinate not used for 1D functions
+    let curve_y = to_view(vec2f(1.0, eval_function(offset, desc.length, x, 0.0))).y;
 
-    // Calculate distance from curve //
+    // Calculate distance from curve, in pixels //
+    let q = to_pixels(v);
+    let curve_q = curve_y / u.pixel_ratio.y;
 
     // Central difference (+dx -dx) for more precision
-    let screen_space_deriv_y = dpdxFine(eval_function(offset, len, x, 0.0));
-    let screen_space_deriv_x = dpdxFine(x);
+    let screen_space_deriv_y = dpdxFine(curve_q);
+    let screen_space_deriv_x = dpdxFine(q.x);
     let dy = screen_space_deriv_y / screen_space_deriv_x;
     
     // Vertical distance to the curve
-    let vertical_dist = y - curve_y;
+    let vertical_dist = q.y - curve_q;
 
     // Normalize
     // We divide by length of gradient vector vec2f(1.0, dy)
     // This turns vertical distance into perpendicular distance
     let dist = abs(vertical_dist) / sqrt(1.0 + dy * dy);
 
-    return step(dist, d);
+    let line = step(dist, d) * dash_at(desc.dash, along(q, vec2f(1.0, dy)));
+    return vec2f(line, between_zero_and(v.y, curve_y));
+}
+
+
+// Equations and inequalities are drawn by the distance to where both sides of their final
+// comparison are equal, estimated from the screen-space gradient of the difference of the
+// sides. The area of inequalities and other conditions is where they hold
+// returns -1.0 on error
+fn draw_equation(desc: PlotDesc, offset: u32, v: vec2f, d: f32) -> vec2f {
+    let p = to_graph(v);
+    let len = desc.length;
+    if len == 0u {
+        return vec2f(0.0);
+    }
+
+    let comparison = get_instruction(offset + len - 1u).opcode;
+    switch comparison {
+        case OP_EQ, OP_LT, OP_LE, OP_GT, OP_GE: {}
+        default: {
+            let holds = eval_function(offset, len, p.x, p.y);
+            if holds < 0.0 {
+                return vec2f(-1.0);
+            }
+            return vec2f(0.0, holds);
+        }
+    }
+
+    // Both sides of the comparison are left on the stack
+    var stack: array<f32, STACK_SIZE>;
+    let ok = run_program(offset, len - 1u, p.x, p.y, &stack);
+    let diff = stack[0] - stack[1];
+
+    // Per pixel, so that the distance is in pixels
+    let q = to_pixels(v);
+    let gradient = vec2f(dpdxFine(diff) / dpdxFine(q.x), dpdyFine(diff) / dpdyFine(q.y));
+    let dist = abs(diff) / max(length(gradient), 1e-30);
+    if !ok {
+        return vec2f(-1.0);
+    }
+
+    let line = step(dist, d) * dash_at(desc.dash, along(q, vec2f(-gradient.y, gradient.x)));
+    switch comparison {
+        case OP_LT, OP_LE: {
+            return vec2f(line, select(0.0, 1.0, diff < 0.0));
+        }
+        case OP_GT, OP_GE: {
+            return vec2f(line, select(0.0, 1.0, diff > 0.0));
+        }
+        default: {
+            return vec2f(line, 0.0);
+        }
+    }
+}
+
+
+// Like `is_on_curve` with the roles of x and y swapped, for graphs of x = g(y)
+fn is_on_inverse_curve(desc: PlotDesc, offset: u32, v: vec2f, d: f32) -> vec2f {
+    let y = to_graph(v).y;
+    // x coordinate not used
+    let curve_x = to_view(vec2f(eval_function(offset, desc.length, 0.0, y), 1.0)).x;
+
+    let q = to_pixels(v);
+    let curve_q = curve_x / u.pixel_ratio.x;
+
+    let screen_space_deriv_x = dpdyFine(curve_q);
+    let screen_space_deriv_y = dpdyFine(q.y);
+    let dx = screen_space_deriv_x / screen_space_deriv_y;
+
+    let dist = abs(q.x - curve_q) / sqrt(1.0 + dx * dx);
+
+    let line = step(dist, d) * dash_at(desc.dash, along(q, vec2f(dx, 1.0)));
+    return vec2f(line, between_zero_and(v.x, curve_x));
+}
+
+
+// Distance to the polyline through CURVE_SEGMENTS + 1 samples of the parameter range, dashed
+// by the length along it. The area is inside the polyline closed from its last point back to
+// its first, by the even-odd rule, all in pixels. Segments with an end that isn't finite are
+// skipped
+// returns -1.0 on error
+fn is_on_parametric_curve(desc: PlotDesc, offset: u32, v: vec2f, d: f32) -> vec2f {
+    let q = to_pixels(v);
+    var dist = F32_MAX;
+    var s = 0.0;          // length along the curve at the closest point
+    var curve_length = 0.0;
+    var inside = false;
+
+    var first: vec2f;
+    if !curve_point(desc, offset, 0u, &first) {
+        return vec2f(-1.0);
+    }
+
+    var prev = first;
+    for (var i: u32 = 1u; i <= CURVE_SEGMENTS; i = i + 1u) {
+        var next: vec2f;
+        if !curve_point(desc, offset, i, &next) {
+            return vec2f(-1.0);
+        }
+
+        if is_finite(prev) && is_finite(next) {
+            let h = closest_on_segment(q, prev, next);
+            let segment_dist = length(q - mix(prev, next, h));
+            if segment_dist < dist {
+                dist = segment_dist;
+                s = curve_length + h * length(next - prev);
+            }
+            curve_length = curve_length + length(next - prev);
+            inside = inside != crosses(q, prev, next);
+        }
+        prev = next;
+    }
+    if is_finite(prev) && is_finite(first) {
+        inside = inside != crosses(q, prev, first);
+    }
+
+    let line = step(dist, d) * dash_at(desc.dash, s);
+    return vec2f(line, select(0.0, 1.0, inside));
+}
+
+// The point of a parametric or polar curve at the i-th sample of its range, in pixels from the
+// view origin
+// returns false on error
+fn curve_point(desc: PlotDesc, offset: u32, i: u32, point: ptr<function, vec2f>) -> bool {
+    let t = desc.range.x + (desc.range.y - desc.range.x) * (f32(i) / f32(CURVE_SEGMENTS));
+
+    var stack: array<f32, STACK_SIZE>;
+    if !run_program(offset, desc.length, t, 0.0, &stack) {
+        return false;
+    }
+
+    if desc.type_id == PLOT_TYPE_POLAR {
+        *point = to_pixels(to_view(stack[0] * vec2f(cos(t), sin(t))));
+    } else {
+        *point = to_pixels(to_view(vec2f(stack[0], stack[1])));
+    }
+    return true;
+}
+
+// Where the point of the segment from `a` to `b` closest to `p` is, from 0.0 at `a` to 1.0 at `b`
+fn closest_on_segment(p: vec2f, a: vec2f, b: vec2f) -> f32 {
+    let pa = p - a;
+    let ba = b - a;
+    return clamp(dot(pa, ba) / max(dot(ba, ba), 1e-30), 0.0, 1.0);
+}
+
+// Whether a ray from `p` to the right crosses the segment from `a` to `b`
+fn crosses(p: vec2f, a: vec2f, b: vec2f) -> bool {
+    return (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) * (b.x - a.x) / (b.y - a.y);
+}
+
+// false for infinities and NaN, which fails every comparison, and for the coordinates at or
+// below 0 of log axes
+fn is_finite(v: vec2f) -> bool {
+    return all(abs(v) < vec2f(F32_MAX));
 }
 
 
@@ -176,135 +358,41 @@ fn is_on_curve(offset: u32, len: u32, x: f32, y: f32, d: f32) -> f32 {
 // returns -1.0 on error
 fn eval_function(offset: u32, len: u32, x: f32, y: f32) -> f32 {
     var stack: array<f32, STACK_SIZE>;
+    if !run_program(offset, len, x, y, &stack) {
+        return -1.0;
+    }
+
+    return stack[0];
+}
+
+// Run a program, leaving its results at the bottom of the stack
+// returns false on error
+fn run_program(offset: u32, len: u32, x: f32, y: f32, stack: ptr<function, array<f32, STACK_SIZE>>) -> bool {
+    var locals: array<f32, N_LOCALS>;
     var sp: u32 = 0;
 
-    if offset + len >= N_INSTRUCTIONS {
-        return -1.0;
+    if offset + len > u.n_instructions {
+        return false;
     }
 
     for (var i: u32 = 0u; i < len; i = i + 1u) {
         let op = get_instruction(offset + i);
 
-        execute_instruction(op, x, y, &sp, &stack);
+        execute_instruction(op, x, y, &sp, stack, &locals);
 
         if sp >= STACK_SIZE {
-            return -1.0;
+            return false;
         }
 
     }
 
-    return stack[0];
+    return true;
 }
 
-
-// Extracted function to handle individual instruction execution
-fn execute_instruction(op: Instruction, x: f32, y: f32, sp: ptr<function, u32>, stack: ptr<function, array<f32, 16>>) {
-    switch op.opcode {
-        case OP_CONST: { // => a
-            stack[*sp] = op.a;
-            *sp = *sp + 1u;
-        }
-        case OP_X: { // => x
-            stack[*sp] = x;
-            *sp = *sp + 1u;
-        }
-        case OP_Y: { // => y
-            stack[*sp] = y;
-            *sp = *sp + 1u;
-        }
-        case OP_ADD: { // stack[-2] += stack[-1]
-            let b = stack[*sp - 1u];
-            *sp = *sp - 1u;
-            let a = stack[*sp - 1u];
-            stack[*sp - 1u] = a + b;
-        }
-        case OP_SUB: { // stack[-2] -= stack[-1]
-            let b = stack[*sp - 1u];
-            *sp = *sp - 1u;
-            let a = stack[*sp - 1u];
-            stack[*sp - 1u] = a - b;
-        }
-        case OP_MUL: { // stack[-2] *= stack[-1]
-            let b = stack[*sp - 1u];
-            *sp = *sp - 1u;
-            let a = stack[*sp - 1u];
-            stack[*sp - 1u] = a * b;
-        }
-        case OP_DIV: { // stack[-2] /= stack[-1]
-            let b = stack[*sp - 1u];
-            *sp = *sp - 1u;
-            let a = stack[*sp - 1u];
-            stack[*sp - 1u] = a / b;
-        }
-        case OP_POW: { // stack[-2] **= stack[-1]
-            let b = stack[*sp - 1u];
-            *sp = *sp - 1u;
-            let a = stack[*sp - 1u];
-            stack[*sp - 1u] = spow(a, b);
-        }
-        case OP_COS: { // cos(stack[-1])
-            stack[*sp - 1u] = cos(stack[*sp - 1u]);
-        }
-        case OP_SIN: { // sin(stack[-1])
-            stack[*sp - 1u] = sin(stack[*sp - 1u]);
-        }
-        case OP_TAN: { // tan(stack[-1])
-            stack[*sp - 1u] = tan(stack[*sp - 1u]);
-        }
-        case OP_LOG: { // log(stack[-1])
-            stack[*sp - 1u] = log(stack[*sp - 1u]);
-        }
-        case OP_ABS: { // abs(stack[-1])
-            stack[*sp - 1u] = abs(stack[*sp - 1u]);
-        }
-        case OP_EQ: { // stack[-2] == stack[-1] ? 1.0 : 0.0
-            let b = stack[*sp - 1u];
-            *sp = *sp - 1u;
-            let a = stack[*sp - 1u];
-            stack[*sp - 1u] = step(abs(a - b), 0.01);
-        }
-        case OP_NE: { // stack[-2] != stack[-1] ? 1.0 : 0.0
-            let b = stack[*sp - 1u];
-            *sp = *sp - 1u;
-            let a = stack[*sp - 1u];
-            stack[*sp - 1u] = 1.0 - step(abs(a - b), 0.01);
-        }
-        case OP_LT: { // stack[-2] < stack[-1] ? 1.0 : 0.0
-            let b = stack[*sp - 1u];
-            *sp = *sp - 1u;
-            let a = stack[*sp - 1u];
-            stack[*sp - 1u] = step(a - b, 0.001);
-        }
-        case OP_LE: { // stack[-2] <= stack[-1] ? 1.0 : 0.0
-            let b = stack[*sp - 1u];
-            *sp = *sp - 1u;
-            let a = stack[*sp - 1u];
-            stack[*sp - 1u] = step(a - b + 0.001, 0.001);
-        }
-        case OP_GT: { // stack[-2] > stack[-1] ? 1.0 : 0.0
-            let b = stack[*sp - 1u];
-            *sp = *sp - 1u;
-            let a = stack[*sp - 1u];
-            stack[*sp - 1u] = step(b - a, 0.001);
-        }
-        case OP_GE: { // stack[-2] >= stack[-1] ? 1.0 : 0.0
-            let b = stack[*sp - 1u];
-            *sp = *sp - 1u;
-            let a = stack[*sp - 1u];
-            stack[*sp - 1u] = step(b - a + 0.001, 0.001);
-        }
-        case OP_AND: { // stack[-2] == stack[-1] == 1.0 ? 1.0 : 0.0
-            let s = stack[*sp - 1u] + stack[*sp - 2u];
-            *sp = *sp - 1u;
-            stack[*sp - 1u] = step(1.001, s);
-        }
-        case OP_OR: { // stack[-2] + stack[-1] == 1.0 ? 1.0 : 0.0
-            let s = stack[*sp - 1u] + stack[*sp - 2u];
-            *sp = *sp - 1u;
-            stack[*sp - 1u] = step(0.001, s);
-        }
-        default: {}
-    }
+// Value of the parameter slot `i`, for `OP_PARAM`
+fn param(i: u32) -> f32 {
+    let j = min(i, MAX_PARAMS - 1u);
+    return u.params[j / 4u][j % 4u];
 }
 
 fn spow(a: f32, b: f32) -> f32 {
@@ -319,23 +407,4 @@ fn spow(a: f32, b: f32) -> f32 {
     return select(-abs_pow, abs_pow, b_is_even);
 }
 
-// Unpack the instruction at `index`
-fn get_instruction(index: u32) -> Instruction {
-    let vec_idx = index / 2u;
-    let is_second = index % 2u;
-    
-    let raw_data = instructions[vec_idx];
-    
-    // If is_second is 0, we read X/Y. If 1, we read Z/W.
-    // We multiply by 2 to shift our access to the correct half of the vec4.
-    let base_component = is_second * 2u; 
-    
-    var inst: Instruction;
-    inst.opcode = raw_data[base_component];
-    inst.a = bitcast<f32>(raw_data[base_component + 1u]);
-    
-    return inst;
-}
-
-
 // vim: et sw=4 sts=4 ts=4
@@ -1,46 +1,134 @@
 use std::sync::Mutex;
 
 use glam::Vec2;
-use iced::{
-    wgpu::{self, util::DeviceExt as _},
-    widget::shader,
-    Rectangle,
+use iced::{Rectangle, wgpu, widget::shader};
+
+use mth_common::{
+    CURVE_SEGMENTS, MAX_PARAMS, N_LOCALS, PLOT_TYPE_EQUATION, PLOT_TYPE_FN_GRAPH,
+    PLOT_TYPE_INVERSE, PLOT_TYPE_NO_PLOT, PLOT_TYPE_PARAMETRIC, PLOT_TYPE_POLAR, STACK_SIZE,
+    ops::Instruction, plot_desc::PlotDesc,
 };
 
-pub use mth_common::N_PLOTS;
-use mth_common::{ops::Instruction, plot_desc::PlotDesc};
+/// Plots that fit into the uniform buffer of the WebGL fallback, 12 KiB
+pub const UNIFORM_MAX_PLOTS: usize = 256;
+
+/// Instructions that fit into the uniform buffer of the WebGL fallback, 16 KiB
+pub const UNIFORM_MAX_INSTRUCTIONS: usize = 2048;
 
-pub const ZOOM_PIXELS_FACTOR: f64 = 200.0;
-pub const N_INSTRUCTIONS: usize = 256;
+/// Capacity of the storage buffers before the first program is uploaded
+const INITIAL_CAPACITY: usize = 64;
 
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 #[repr(C)]
 pub struct Uniforms {
-    pub viewport_origin: Vec2, // 8 bytes
-    pub viewport_size: Vec2,   // 8 bytes
-    pub pan_offset: Vec2,      // 8 bytes
-    pub pixel_ratio: f32,      // 4 bytes
-    pub _pad: u32,             // 4 bytes
+    pub viewport_origin: Vec2,              // 8 bytes
+    pub viewport_size: Vec2,                // 8 bytes
+    pub pan_offset: Vec2,                   // 8 bytes
+    pub pixel_ratio: Vec2, // 8 bytes, view units per physical pixel of the x and y axis
+    pub n_plots: u32,      // 4 bytes
+    pub n_instructions: u32, // 4 bytes
+    pub log_scale: [u32; 2], // 8 bytes, 1 for axes with a log scale
+    pub params: [[f32; 4]; MAX_PARAMS / 4], // 64 bytes, values of the sliders
+    pub time: f32,         // 4 bytes, seconds of the animation
+    pub _pad: [f32; 3],    // 12 bytes, the struct is aligned like `params`
+}
+
+/// How the plot descriptions and instructions are bound to the shader
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BufferKind {
+    /// Storage buffers, which are recreated with a larger size when a program doesn't fit
+    Storage,
+
+    /// Uniform buffers of a fixed size, for backends without storage buffers in fragment
+    /// shaders like WebGL. Plots beyond [`UNIFORM_MAX_PLOTS`] aren't drawn, and the ones that
+    /// reach past [`UNIFORM_MAX_INSTRUCTIONS`] are drawn as errors
+    Uniform,
+}
+
+impl BufferKind {
+    pub fn for_device(device: &wgpu::Device) -> Self {
+        if device.limits().max_storage_buffers_per_shader_stage >= 2 {
+            BufferKind::Storage
+        } else {
+            BufferKind::Uniform
+        }
+    }
+
+    fn binding_type(self) -> wgpu::BufferBindingType {
+        match self {
+            BufferKind::Storage => wgpu::BufferBindingType::Storage { read_only: true },
+            BufferKind::Uniform => wgpu::BufferBindingType::Uniform,
+        }
+    }
+
+    fn usage(self) -> wgpu::BufferUsages {
+        let usage = match self {
+            BufferKind::Storage => wgpu::BufferUsages::STORAGE,
+            BufferKind::Uniform => wgpu::BufferUsages::UNIFORM,
+        };
+        usage | wgpu::BufferUsages::COPY_DST
+    }
+}
+
+/// The WGSL source of the shader, with the constants shared with Rust, the opcodes generated
+/// by build.rs and the bindings for `kind`
+pub fn shader_source(kind: BufferKind) -> String {
+    let mut src = format!(
+        "const STACK_SIZE: u32 = {STACK_SIZE}u;\n\
+         const N_LOCALS: u32 = {N_LOCALS}u;\n\
+         const MAX_PARAMS: u32 = {MAX_PARAMS}u;\n\
+         const PLOT_TYPE_NO_PLOT: u32 = {PLOT_TYPE_NO_PLOT}u;\n\
+         const PLOT_TYPE_FN_GRAPH: u32 = {PLOT_TYPE_FN_GRAPH}u;\n\
+         const PLOT_TYPE_EQUATION: u32 = {PLOT_TYPE_EQUATION}u;\n\
+         const PLOT_TYPE_PARAMETRIC: u32 = {PLOT_TYPE_PARAMETRIC}u;\n\
+         const PLOT_TYPE_POLAR: u32 = {PLOT_TYPE_POLAR}u;\n\
+         const PLOT_TYPE_INVERSE: u32 = {PLOT_TYPE_INVERSE}u;\n\
+         const CURVE_SEGMENTS: u32 = {CURVE_SEGMENTS}u;\n\n"
+    );
+    match kind {
+        BufferKind::Storage => src += include_str!("bindings_storage.wgsl"),
+        BufferKind::Uniform => {
+            src += &format!(
+                "const MAX_PLOTS: u32 = {UNIFORM_MAX_PLOTS}u;\n\
+                 const MAX_INSTRUCTIONS: u32 = {UNIFORM_MAX_INSTRUCTIONS}u;\n\n"
+            );
+            src += include_str!("bindings_uniform.wgsl");
+        }
+    }
+    src += "\n";
+    src += include_str!(concat!(env!("OUT_DIR"), "/ops.wgsl"));
+    src += "\n";
+    src += include_str!("graph_shader.wgsl");
+    src
 }
 
 pub struct FragmentShaderPipeline {
     pipeline: wgpu::RenderPipeline,
+    kind: BufferKind,
 
     uniform_buffer: wgpu::Buffer,
     plot_desc_buffer: wgpu::Buffer,
+    bind_group_layout_0: wgpu::BindGroupLayout,
     bind_group_0: wgpu::BindGroup,
 
     instruction_buffer: wgpu::Buffer,
+    bind_group_layout_1: wgpu::BindGroupLayout,
     bind_group_1: wgpu::BindGroup,
+
+    /// Plots and instructions of the uploaded program that the shader evaluates
+    n_plots: u32,
+    n_instructions: u32,
+
+    /// Generation of the uploaded program, 0 before the first upload
+    program_generation: u64,
 }
 
 impl shader::Pipeline for FragmentShaderPipeline {
     fn new(device: &wgpu::Device, _queue: &wgpu::Queue, format: wgpu::TextureFormat) -> Self {
+        let kind = BufferKind::for_device(device);
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("FragmentShaderPipeline shader"),
-            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(include_str!(
-                "graph_shader.wgsl"
-            ))),
+            source: wgpu::ShaderSource::Wgsl(shader_source(kind).into()),
         });
 
         let bind_group_layout_0 =
@@ -61,7 +149,7 @@ impl shader::Pipeline for FragmentShaderPipeline {
                         binding: 1,
                         visibility: wgpu::ShaderStages::FRAGMENT,
                         ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
+                            ty: kind.binding_type(),
                             has_dynamic_offset: false,
                             min_binding_size: None,
                         },
@@ -76,7 +164,7 @@ impl shader::Pipeline for FragmentShaderPipeline {
                     binding: 0,
                     visibility: wgpu::ShaderStages::FRAGMENT,
                     ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
+                        ty: kind.binding_type(),
                         has_dynamic_offset: false,
                         min_binding_size: None,
                     },
@@ -123,82 +211,124 @@ impl shader::Pipeline for FragmentShaderPipeline {
             mapped_at_creation: false,
         });
 
-        let plot_desc_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("PlotDescBuffer"),
-            size: std::mem::size_of::<PlotDesc>() as u64 * N_PLOTS as u64,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
+        let (plot_capacity, instruction_capacity) = match kind {
+            BufferKind::Storage => (INITIAL_CAPACITY, INITIAL_CAPACITY),
+            BufferKind::Uniform => (UNIFORM_MAX_PLOTS, UNIFORM_MAX_INSTRUCTIONS),
+        };
+        let plot_desc_buffer =
+            create_buffer::<PlotDesc>(device, kind, "PlotDescBuffer", plot_capacity);
+        let instruction_buffer =
+            create_buffer::<Instruction>(device, kind, "InstructionBuffer", instruction_capacity);
 
-        // Create buffer with N_INSTRUCTIONS capacity, initialized with nop instructions
-        let buffer_data = [Instruction::default(); N_INSTRUCTIONS];
-        let instruction_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("InstructionBuffer"),
-            contents: bytemuck::cast_slice(&buffer_data),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-
-        let bind_group_0 = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("BindGroup0"),
-            layout: &bind_group_layout_0,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: uniform_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: plot_desc_buffer.as_entire_binding(),
-                },
-            ],
-        });
-        let bind_group_1 = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("BindGroup1"),
-            layout: &bind_group_layout_1,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: instruction_buffer.as_entire_binding(),
-            }],
-        });
+        let bind_group_0 = create_bind_group_0(
+            device,
+            &bind_group_layout_0,
+            &uniform_buffer,
+            &plot_desc_buffer,
+        );
+        let bind_group_1 = create_bind_group_1(device, &bind_group_layout_1, &instruction_buffer);
 
         Self {
             pipeline,
+            kind,
             uniform_buffer,
             plot_desc_buffer,
+            bind_group_layout_0,
             bind_group_0,
             instruction_buffer,
+            bind_group_layout_1,
             bind_group_1,
+            n_plots: 0,
+            n_instructions: 0,
+            program_generation: 0,
         }
     }
 }
 
 impl FragmentShaderPipeline {
+    /// Note: `n_plots` and `n_instructions` of `uniforms` are replaced by the counts of the
+    /// uploaded program
     pub fn update_uniforms(&self, queue: &wgpu::Queue, uniforms: &Uniforms) {
-        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(uniforms));
+        let uniforms = Uniforms {
+            n_plots: self.n_plots,
+            n_instructions: self.n_instructions,
+            ..*uniforms
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniforms));
     }
 
-    pub fn update_plot_desc(&self, queue: &wgpu::Queue, descriptions: &[PlotDesc; N_PLOTS]) {
-        queue.write_buffer(
-            &self.plot_desc_buffer,
-            0,
-            bytemuck::cast_slice(descriptions),
-        );
+    /// Generation of the program passed to the last [`Self::update_program`]
+    pub fn program_generation(&self) -> u64 {
+        self.program_generation
     }
 
-    /// Note: instruction_count also needs to be set correctly in the uniforms
+    /// Upload a compiled program, growing the storage buffers if it doesn't fit
     pub fn update_program(
-        &self,
+        &mut self,
+        device: &wgpu::Device,
         queue: &wgpu::Queue,
-        instructions: &Mutex<[Instruction; N_INSTRUCTIONS]>,
-        instruction_count: usize,
+        generation: u64,
+        instructions: &Mutex<Vec<Instruction>>,
+        plot_descs: &[PlotDesc],
     ) {
         let guard = instructions
             .lock()
             .expect("Failed to lock instruction mutex");
 
-        let slice = bytemuck::cast_slice(&guard[..instruction_count]);
+        let (plot_descs, instructions) = match self.kind {
+            BufferKind::Storage => {
+                self.reserve(device, plot_descs.len(), guard.len());
+                (plot_descs, &guard[..])
+            }
+            BufferKind::Uniform => (
+                &plot_descs[..plot_descs.len().min(UNIFORM_MAX_PLOTS)],
+                &guard[..guard.len().min(UNIFORM_MAX_INSTRUCTIONS)],
+            ),
+        };
+
+        queue.write_buffer(&self.plot_desc_buffer, 0, bytemuck::cast_slice(plot_descs));
+        queue.write_buffer(
+            &self.instruction_buffer,
+            0,
+            bytemuck::cast_slice(instructions),
+        );
+        self.n_plots = plot_descs.len() as u32;
+        self.n_instructions = instructions.len() as u32;
+        self.program_generation = generation;
+    }
+
+    /// Recreate the storage buffers that are too small for `n_plots` and `n_instructions`
+    fn reserve(&mut self, device: &wgpu::Device, n_plots: usize, n_instructions: usize) {
+        let capacity = |buffer: &wgpu::Buffer, size| buffer.size() as usize / size;
+
+        let plots_fit = n_plots <= capacity(&self.plot_desc_buffer, size_of::<PlotDesc>());
+        if !plots_fit {
+            self.plot_desc_buffer = create_buffer::<PlotDesc>(
+                device,
+                self.kind,
+                "PlotDescBuffer",
+                n_plots.next_power_of_two(),
+            );
+            self.bind_group_0 = create_bind_group_0(
+                device,
+                &self.bind_group_layout_0,
+                &self.uniform_buffer,
+                &self.plot_desc_buffer,
+            );
+        }
 
-        queue.write_buffer(&self.instruction_buffer, 0, slice);
+        let instructions_fit =
+            n_instructions <= capacity(&self.instruction_buffer, size_of::<Instruction>());
+        if !instructions_fit {
+            self.instruction_buffer = create_buffer::<Instruction>(
+                device,
+                self.kind,
+                "InstructionBuffer",
+                n_instructions.next_power_of_two(),
+            );
+            self.bind_group_1 =
+                create_bind_group_1(device, &self.bind_group_layout_1, &self.instruction_buffer);
+        }
     }
 
     pub fn render(
@@ -238,3 +368,55 @@ impl FragmentShaderPipeline {
         pass.draw(0..3, 0..1);
     }
 }
+
+/// A zeroed buffer for `capacity` elements of type `T`
+fn create_buffer<T>(
+    device: &wgpu::Device,
+    kind: BufferKind,
+    label: &str,
+    capacity: usize,
+) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        size: (size_of::<T>() * capacity) as u64,
+        usage: kind.usage(),
+        mapped_at_creation: false,
+    })
+}
+
+fn create_bind_group_0(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    uniform_buffer: &wgpu::Buffer,
+    plot_desc_buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("BindGroup0"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: plot_desc_buffer.as_entire_binding(),
+            },
+        ],
+    })
+}
+
+fn create_bind_group_1(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    instruction_buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("BindGroup1"),
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: instruction_buffer.as_entire_binding(),
+        }],
+    })
+}
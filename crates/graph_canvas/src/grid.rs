@@ -0,0 +1,241 @@
+//! Where the grid lines, ticks and labels of an axis go. Positions are in view coordinates,
+//! which are the graph coordinates of linear axes and their logarithms for [`AxisScale::Log`]
+
+use std::f64::consts::PI;
+use std::fmt;
+
+/// Smallest distance between major grid lines in logical pixels, enough room for their labels
+pub const MAJOR_SPACING: f64 = 80.0;
+
+/// Minor grid lines closer than this in logical pixels aren't drawn
+pub const MINOR_SPACING: f64 = 8.0;
+
+/// How an axis maps graph coordinates to the screen, and how its ticks are labelled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AxisScale {
+    /// Ticks at multiples of 1, 2 or 5 times a power of ten
+    #[default]
+    Linear,
+
+    /// Ticks at multiples of π and its fractions, like `π/2` or `3π`. Zoomed in further than
+    /// the fractions of [`PI_STEPS`] allow, ticks fall back to those of [`AxisScale::Linear`]
+    Pi,
+
+    /// Powers of ten are equally spaced, and only positive coordinates are shown
+    Log,
+}
+
+impl AxisScale {
+    pub const ALL: [AxisScale; 3] = [AxisScale::Linear, AxisScale::Pi, AxisScale::Log];
+
+    /// View coordinate of the graph coordinate `value`
+    pub fn to_view(self, value: f64) -> f64 {
+        match self {
+            AxisScale::Log => value.log10(),
+            _ => value,
+        }
+    }
+
+    /// Graph coordinate of the view coordinate `v`, the inverse of [`AxisScale::to_view`]
+    pub fn to_graph(self, v: f64) -> f64 {
+        match self {
+            AxisScale::Log => 10.0_f64.powf(v),
+            _ => v,
+        }
+    }
+}
+
+impl fmt::Display for AxisScale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AxisScale::Linear => "linear",
+            AxisScale::Pi => "π",
+            AxisScale::Log => "log",
+        })
+    }
+}
+
+/// Steps between the major ticks of [`AxisScale::Pi`], as fractions of π
+pub const PI_STEPS: [(u32, u32); 2] = [(1, 4), (1, 2)];
+
+/// A grid line of an axis
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tick {
+    /// View coordinate of the line
+    pub value: f64,
+
+    /// Major ticks are labelled and drawn brighter
+    pub major: bool,
+}
+
+/// The spacing of the ticks of an axis, see [`Ticks::new`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ticks {
+    pub scale: AxisScale,
+
+    /// View units between major ticks
+    pub step: f64,
+
+    /// View units between minor ticks, `None` without minor ticks. The minor ticks of a log
+    /// axis with a major tick every power of ten are at its multiples 2 to 9 instead
+    pub minor: Option<f64>,
+
+    /// Whether the step is a fraction or multiple of π
+    pi: bool,
+}
+
+impl Ticks {
+    /// The ticks of an axis with `pixel_ratio` view units per logical pixel. Major ticks are
+    /// at least [`MAJOR_SPACING`] pixels apart
+    pub fn new(scale: AxisScale, pixel_ratio: f64) -> Self {
+        let target = MAJOR_SPACING * pixel_ratio;
+        let minor_target = MINOR_SPACING * pixel_ratio;
+
+        let (step, minor, pi) = match scale {
+            AxisScale::Pi if target > PI / 2.0 => {
+                let (multiple, minor) = nice_step(target / PI);
+                (multiple * PI, minor * PI, true)
+            }
+            // The smallest fraction would be more than twice as far apart as needed
+            AxisScale::Pi if target > PI / 8.0 => {
+                let step = PI_STEPS
+                    .iter()
+                    .map(|&(num, den)| PI * num as f64 / den as f64)
+                    .find(|&step| step >= target)
+                    .unwrap_or(PI / 2.0);
+                (step, step / 4.0, true)
+            }
+            AxisScale::Linear | AxisScale::Pi => {
+                let (step, minor) = nice_step(target);
+                (step, minor, false)
+            }
+            AxisScale::Log => {
+                // Whole powers of ten
+                let (step, minor) = nice_step(target.max(1.0));
+                let minor = if step == 1.0 {
+                    // The closest multiples are 9 and 10
+                    0.9_f64.log10().abs()
+                } else {
+                    minor.max(1.0)
+                };
+                (step, minor, false)
+            }
+        };
+
+        Self {
+            scale,
+            step,
+            minor: (minor >= minor_target).then_some(minor),
+            pi,
+        }
+    }
+
+    /// The major and minor ticks from `min` to `max` in view coordinates
+    pub fn between(&self, min: f64, max: f64) -> Vec<Tick> {
+        let mut ticks = Vec::new();
+        if !(min.is_finite() && max.is_finite()) || min > max {
+            return ticks;
+        }
+
+        match self.minor {
+            Some(_) if self.scale == AxisScale::Log && self.step == 1.0 => {
+                for decade in min.floor() as i64..=max.floor() as i64 {
+                    for multiple in 1..10 {
+                        let value = decade as f64 + (multiple as f64).log10();
+                        if (min..=max).contains(&value) {
+                            ticks.push(Tick {
+                                value,
+                                major: multiple == 1,
+                            });
+                        }
+                    }
+                }
+            }
+            Some(minor) => {
+                let per_major = (self.step / minor).round() as i64;
+                for i in (min / minor).ceil() as i64..=(max / minor).floor() as i64 {
+                    ticks.push(Tick {
+                        value: i as f64 * minor,
+                        major: i.rem_euclid(per_major) == 0,
+                    });
+                }
+            }
+            None => {
+                for i in (min / self.step).ceil() as i64..=(max / self.step).floor() as i64 {
+                    ticks.push(Tick {
+                        value: i as f64 * self.step,
+                        major: true,
+                    });
+                }
+            }
+        }
+        ticks
+    }
+
+    /// The label of the major tick at the view coordinate `value`
+    pub fn label(&self, value: f64) -> String {
+        match self.scale {
+            AxisScale::Log => {
+                let power = 10.0_f64.powf(value.round());
+                format_number(power, power)
+            }
+            _ if self.pi => format_pi(value),
+            _ => format_number(value, self.step),
+        }
+    }
+}
+
+/// The smallest step of 1, 2 or 5 times a power of ten that is at least `target`, and the
+/// step of the minor ticks between
+fn nice_step(target: f64) -> (f64, f64) {
+    let power = 10.0_f64.powf(target.log10().floor());
+    // Targets that are steps themselves may be off by rounding
+    match target / power * (1.0 - 1e-9) {
+        m if m <= 1.0 => (power, power / 5.0),
+        m if m <= 2.0 => (2.0 * power, power / 2.0),
+        m if m <= 5.0 => (5.0 * power, power),
+        _ => (10.0 * power, 2.0 * power),
+    }
+}
+
+/// `value` with as many decimals as `step` needs, in scientific notation when it is very
+/// large or small
+pub(crate) fn format_number(value: f64, step: f64) -> String {
+    // Multiples of the step that are almost 0 only differ from it by rounding
+    if value.abs() < step * 1e-6 {
+        return "0".to_string();
+    }
+
+    let magnitude = value.abs().log10().floor();
+    if (-4.0..6.0).contains(&magnitude) {
+        let decimals = (-step.log10().floor()).max(0.0) as usize;
+        format!("{value:.decimals$}")
+    } else {
+        let decimals = (magnitude - step.log10().floor()).max(0.0) as usize;
+        format!("{value:.decimals$e}")
+    }
+}
+
+/// `value` as a multiple of a quarter of π, like `-3π/4`
+fn format_pi(value: f64) -> String {
+    let quarters = (value / PI * 4.0).round() as i64;
+    if quarters == 0 {
+        return "0".to_string();
+    }
+
+    let divisor = gcd(quarters.unsigned_abs(), 4) as i64;
+    let (num, den) = (quarters / divisor, 4 / divisor);
+    let num = match num {
+        1 => String::new(),
+        -1 => "-".to_string(),
+        n => n.to_string(),
+    };
+    match den {
+        1 => format!("{num}π"),
+        den => format!("{num}π/{den}"),
+    }
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
@@ -1,5 +1,17 @@
+mod axes;
+pub use axes::Axes;
+
 pub mod controls;
+pub mod crosshair;
+pub use crosshair::Crosshair;
+mod fit;
 mod fragment_shader_primitive;
 mod graph_shader_pipeline;
+pub mod grid;
 pub use fragment_shader_primitive::FragmentShaderPrimitive;
-pub use graph_shader_pipeline::{N_INSTRUCTIONS, N_PLOTS};
+pub use graph_shader_pipeline::{
+    BufferKind, UNIFORM_MAX_INSTRUCTIONS, UNIFORM_MAX_PLOTS, shader_source,
+};
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,405 @@
+use code_generator::{CompiledModule, compile_source};
+use mth_common::{ops::OPS, plot_desc::PlotDesc};
+use naga::{
+    Statement, SwitchValue, TypeInner,
+    valid::{Capabilities, ValidationFlags, Validator},
+};
+
+use glam::{DVec2, dvec2};
+
+use crate::{
+    BufferKind,
+    controls::Controls,
+    crosshair::hover,
+    graph_shader_pipeline::Uniforms,
+    grid::{AxisScale, Tick, Ticks},
+    shader_source,
+};
+
+fn validate(kind: BufferKind) {
+    let src = shader_source(kind);
+    let module =
+        naga::front::wgsl::parse_str(&src).unwrap_or_else(|e| panic!("{}", e.emit_to_string(&src)));
+    Validator::new(ValidationFlags::all(), Capabilities::empty())
+        .validate(&module)
+        .unwrap_or_else(|e| panic!("{}", e.emit_to_string(&src)));
+}
+
+#[test]
+fn test_storage_shader_is_valid() {
+    validate(BufferKind::Storage);
+}
+
+#[test]
+fn test_uniform_shader_is_valid() {
+    validate(BufferKind::Uniform);
+}
+
+#[test]
+fn test_shader_constants_come_from_rust() {
+    let src = shader_source(BufferKind::Uniform);
+    assert!(src.contains(&format!(
+        "const STACK_SIZE: u32 = {}u;",
+        mth_common::STACK_SIZE
+    )));
+    assert!(src.contains(&format!(
+        "const PLOT_TYPE_POLAR: u32 = {}u;",
+        mth_common::PLOT_TYPE_POLAR
+    )));
+    assert!(src.contains(&format!(
+        "const CURVE_SEGMENTS: u32 = {}u;",
+        mth_common::CURVE_SEGMENTS
+    )));
+    assert!(src.contains(&format!(
+        "const MAX_INSTRUCTIONS: u32 = {}u;",
+        crate::UNIFORM_MAX_INSTRUCTIONS
+    )));
+    assert!(!shader_source(BufferKind::Storage).contains("MAX_INSTRUCTIONS"));
+}
+
+/// Size of the struct `name` of the shader
+fn struct_span(name: &str) -> usize {
+    let src = shader_source(BufferKind::Uniform);
+    let module = naga::front::wgsl::parse_str(&src).unwrap();
+    module
+        .types
+        .iter()
+        .find_map(|(_, ty)| match &ty.inner {
+            TypeInner::Struct { span, .. } if ty.name.as_deref() == Some(name) => Some(*span),
+            _ => None,
+        })
+        .unwrap_or_else(|| panic!("the shader has a `{name}` struct")) as usize
+}
+
+#[test]
+fn test_plot_desc_has_the_layout_of_the_shader() {
+    assert_eq!(struct_span("PlotDesc"), size_of::<PlotDesc>());
+    assert_eq!(
+        size_of::<PlotDesc>() % 16,
+        0,
+        "elements of uniform arrays are 16 byte aligned"
+    );
+}
+
+#[test]
+fn test_uniforms_have_the_layout_of_the_shader() {
+    assert_eq!(struct_span("Uniforms"), size_of::<Uniforms>());
+}
+
+#[test]
+fn test_opcodes_are_indices_into_the_table() {
+    for (i, info) in OPS.iter().enumerate() {
+        assert_eq!(info.opcode as usize, i, "OP_{}", info.name);
+    }
+}
+
+#[test]
+fn test_every_opcode_is_implemented_by_the_shader() {
+    let src = shader_source(BufferKind::Storage);
+    let module = naga::front::wgsl::parse_str(&src).unwrap();
+    let (_, execute_instruction) = module
+        .functions
+        .iter()
+        .find(|(_, f)| f.name.as_deref() == Some("execute_instruction"))
+        .expect("the shader has an `execute_instruction` function");
+
+    let cases: Vec<u32> = execute_instruction
+        .body
+        .iter()
+        .filter_map(|statement| match statement {
+            Statement::Switch { cases, .. } => Some(cases),
+            _ => None,
+        })
+        .flatten()
+        .filter(|case| !case.body.is_empty())
+        .filter_map(|case| match case.value {
+            SwitchValue::U32(value) => Some(value),
+            _ => None,
+        })
+        .collect();
+
+    for info in OPS {
+        assert!(
+            cases.contains(&info.opcode),
+            "OP_{} has no case in `execute_instruction`",
+            info.name
+        );
+    }
+}
+
+#[test]
+fn test_controls_map_between_screen_and_view() {
+    let controls = Controls {
+        zoom: dvec2(0.0, 2.0),
+        offset: dvec2(1.0, -2.0),
+        ..Controls::default()
+    };
+    let size = dvec2(400.0, 300.0);
+
+    // The center shows the offset, with y pointing up
+    assert_eq!(controls.to_view(size * 0.5, size), dvec2(1.0, 2.0));
+    let corner = controls.to_view(dvec2(0.0, 0.0), size);
+    assert!(corner.x < 1.0 && corner.y > 2.0);
+    assert!((controls.to_screen(corner, size) - dvec2(0.0, 0.0)).length() < 1e-9);
+}
+
+#[test]
+fn test_zoom_keeps_the_point_under_the_cursor() {
+    let mut controls = Controls::default();
+    let size = dvec2(400.0, 300.0);
+    let pos = dvec2(100.0, 250.0);
+    let before = controls.to_view(pos, size);
+
+    controls.zoom_at(pos, size, DVec2::splat(1.5));
+    assert_eq!(controls.zoom, DVec2::splat(2.5));
+    assert!((controls.to_view(pos, size) - before).length() < 1e-12);
+
+    // Zooming one axis stretches the graph along it only
+    controls.zoom_at(pos, size, dvec2(0.0, -1.0));
+    assert_eq!(controls.zoom, dvec2(2.5, 1.5));
+    assert_eq!(controls.pixel_ratio().y, 2.0 * controls.pixel_ratio().x);
+    assert!((controls.to_view(pos, size) - before).length() < 1e-12);
+
+    controls.pan(dvec2(10.0, 0.0));
+    controls.axes = [AxisScale::Pi, AxisScale::Log];
+    controls.reset();
+    assert_eq!(controls.zoom, Controls::default().zoom);
+    assert_eq!(controls.offset, Controls::default().offset);
+    assert_eq!(controls.axes, [AxisScale::Pi, AxisScale::Log]);
+}
+
+#[test]
+fn test_show_ranges_of_each_axis() {
+    let mut controls = Controls::default();
+    let size = dvec2(400.0, 200.0);
+    controls.show([-1.0, 3.0], [0.0, 100.0], size);
+
+    let top_left = controls.to_view(DVec2::ZERO, size);
+    let bottom_right = controls.to_view(size, size);
+    assert!(
+        (top_left - dvec2(-1.0, 100.0)).length() < 1e-9,
+        "{top_left}"
+    );
+    assert!(
+        (bottom_right - dvec2(3.0, 0.0)).length() < 1e-9,
+        "{bottom_right}"
+    );
+
+    // A log axis can't show 0, so the y axis stays as it was
+    controls.axes[1] = AxisScale::Log;
+    let zoom = controls.zoom;
+    controls.show([1.0, 1000.0], [0.0, 10.0], size);
+    assert_eq!(controls.zoom.y, zoom.y);
+    assert!((controls.to_view(DVec2::ZERO, size).x - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_fit_curves() {
+    let CompiledModule {
+        instructions,
+        plot_descs,
+        ..
+    } = compile_source("plot_parametric(t -> 3 + cos(t), t -> sin(t) - 2);");
+    let mut controls = Controls::default();
+    let size = dvec2(400.0, 200.0);
+    assert!(controls.fit(&instructions, &plot_descs, size));
+
+    // The circle fills the height of the graph but its margins
+    let center = controls.to_view(size * 0.5, size);
+    assert!((center - dvec2(3.0, -2.0)).length() < 1e-3, "{center}");
+    let top = controls.to_screen(dvec2(3.0, -1.0), size).y;
+    assert!((top - 20.0).abs() < 0.1, "{top}");
+}
+
+#[test]
+fn test_fit_function_graphs_in_the_visible_range() {
+    let CompiledModule {
+        instructions,
+        plot_descs,
+        ..
+    } = compile_source("f(x) = x^2 + 10; plot(f);");
+    let mut controls = Controls::default();
+    let size = dvec2(400.0, 400.0);
+    let visible_x = controls.to_view(size, size).x - controls.to_view(DVec2::ZERO, size).x;
+    assert!(controls.fit(&instructions, &plot_descs, size));
+
+    // Zoomed out to show the values across the same x range, which is still visible
+    let left = controls.to_view(DVec2::ZERO, size);
+    let right = controls.to_view(size, size);
+    assert!(right.x - left.x >= visible_x);
+    assert!(left.x < 0.0 && right.x > 0.0);
+    let low = controls.to_screen(dvec2(0.0, 10.0), size).y;
+    assert!(low > 0.0 && low < size.y, "{low}");
+    let high = controls.to_screen(dvec2(visible_x / 2.0, 10.0 + visible_x.powi(2) / 4.0), size);
+    assert!(high.y > 0.0 && high.y < size.y, "{high}");
+
+    // The y axis is zoomed on its own to fill the height but its margins
+    assert_ne!(controls.zoom.y, controls.zoom.x);
+    assert!(low > size.y * 0.85, "{low}");
+    assert!(high.y < size.y * 0.15, "{high}");
+}
+
+#[test]
+fn test_fit_without_samples() {
+    let CompiledModule {
+        instructions,
+        plot_descs,
+        ..
+    } = compile_source("plot(x^2 + y^2 == 1);");
+    let mut controls = Controls::default();
+    assert!(!controls.fit(&instructions, &plot_descs, dvec2(400.0, 400.0)));
+    assert_eq!(controls.offset, DVec2::ZERO);
+}
+
+#[test]
+fn test_hover_snaps_to_the_closest_plot() {
+    let CompiledModule {
+        instructions,
+        plot_descs,
+        ..
+    } = compile_source("f(x) = x^2; plot(f); plot_polar(θ -> 0.2); plot(x^2 + y^2 == 1);");
+    // 200 pixels per unit, from -1 to 1
+    let controls = Controls {
+        zoom: DVec2::ZERO,
+        ..Controls::default()
+    };
+    let size = dvec2(400.0, 400.0);
+    let screen = |x: f64, y: f64| controls.to_screen(dvec2(x, y), size);
+
+    // Close to the graph of f, the circle is read at its closest point. Equations have no
+    // value
+    let hover_f = hover(
+        &instructions,
+        &plot_descs,
+        &controls,
+        screen(0.5, 0.26),
+        size,
+    );
+    assert_eq!(hover_f.snapped, Some(0));
+    assert!((hover_f.point - dvec2(0.5, 0.25)).length() < 1e-6);
+    assert_eq!(hover_f.readings.len(), 2);
+    assert_eq!(hover_f.readings[1].plot, 1);
+    assert!((hover_f.readings[1].point.length() - 0.2).abs() < 1e-3);
+
+    // Closer to the circle than to f, which is read at the x coordinate of the snapped point
+    let hover_circle = hover(
+        &instructions,
+        &plot_descs,
+        &controls,
+        screen(0.205, 0.005),
+        size,
+    );
+    let snapped = hover_circle.readings[hover_circle.snapped.unwrap()];
+    assert_eq!(snapped.plot, 1);
+    assert!((hover_circle.point - dvec2(0.2, 0.0)).length() < 1e-2);
+    let f = hover_circle.readings[0].point;
+    assert!((f.y - f.x * f.x).abs() < 1e-6 && (f.x - hover_circle.point.x).abs() < 1e-9);
+
+    // Far from all plots, the crosshair stays at the cursor
+    let far = hover(
+        &instructions,
+        &plot_descs,
+        &controls,
+        screen(-0.8, 0.9),
+        size,
+    );
+    assert_eq!(far.snapped, None);
+    assert!((far.point - dvec2(-0.8, 0.9)).length() < 1e-9);
+    assert_eq!(far.readings[0].point.y, 0.8_f32.powi(2) as f64);
+}
+
+#[test]
+fn test_linear_ticks() {
+    // 80 pixels are 0.8 units, rounded up to 1 with minor ticks every 0.2
+    let ticks = Ticks::new(AxisScale::Linear, 0.01);
+    assert_eq!(ticks.step, 1.0);
+    assert_eq!(ticks.minor, Some(0.2));
+
+    let ticks = Ticks::new(AxisScale::Linear, 0.015);
+    assert_eq!(ticks.step, 2.0);
+    assert_eq!(ticks.minor, Some(0.5));
+
+    let between = Ticks::new(AxisScale::Linear, 0.05).between(-6.0, 11.0);
+    let majors: Vec<f64> = between
+        .iter()
+        .filter(|t| t.major)
+        .map(|t| t.value)
+        .collect();
+    assert_eq!(majors, [-5.0, 0.0, 5.0, 10.0]);
+    assert_eq!(between.len(), 18);
+}
+
+#[test]
+fn test_decimal_labels() {
+    let label = |step_pixels: f64, value: f64| {
+        Ticks::new(AxisScale::Linear, step_pixels / 80.0).label(value)
+    };
+    assert_eq!(label(1.0, 3.0), "3");
+    assert_eq!(label(0.5, -1.5), "-1.5");
+    assert_eq!(label(0.02, 0.06), "0.06");
+    assert_eq!(label(0.1, 0.1 * 3.0), "0.3");
+    assert_eq!(label(0.1, 1e-17), "0");
+    assert_eq!(label(2e5, 1.2e6), "1.2e6");
+    assert_eq!(label(5e-6, 1.5e-5), "1.5e-5");
+}
+
+#[test]
+fn test_pi_ticks() {
+    let ticks = Ticks::new(AxisScale::Pi, 0.01);
+    assert_eq!(ticks.step, std::f64::consts::FRAC_PI_2);
+    let labels: Vec<String> = ticks
+        .between(-3.2, 5.0)
+        .into_iter()
+        .filter(|t| t.major)
+        .map(|t| ticks.label(t.value))
+        .collect();
+    assert_eq!(labels, ["-π", "-π/2", "0", "π/2", "π", "3π/2"]);
+
+    let ticks = Ticks::new(AxisScale::Pi, 0.005);
+    assert_eq!(ticks.label(3.0 * ticks.step), "3π/4");
+
+    let ticks = Ticks::new(AxisScale::Pi, 0.1);
+    assert_eq!(ticks.label(2.0 * ticks.step), "10π");
+
+    // Too close for quarters of π
+    let ticks = Ticks::new(AxisScale::Pi, 0.001);
+    assert_eq!(ticks.step, 0.1);
+    assert_eq!(ticks.label(0.3), "0.3");
+}
+
+#[test]
+fn test_log_ticks() {
+    // A power of ten every 250 pixels, with minor ticks at its multiples
+    let ticks = Ticks::new(AxisScale::Log, 0.004);
+    assert_eq!(ticks.step, 1.0);
+    let between = ticks.between(-1.0, 1.0);
+    assert_eq!(between.len(), 19);
+    let labels: Vec<String> = between
+        .iter()
+        .filter(|t| t.major)
+        .map(|t| ticks.label(t.value))
+        .collect();
+    assert_eq!(labels, ["0.1", "1", "10"]);
+    assert!((between[1].value - 2.0_f64.log10() + 1.0).abs() < 1e-12);
+
+    // Zoomed out, a major tick every 5 powers of ten with minor ticks at the others
+    let ticks = Ticks::new(AxisScale::Log, 0.05);
+    assert_eq!((ticks.step, ticks.minor), (5.0, Some(1.0)));
+    assert_eq!(ticks.label(-10.0), "1e-10");
+    assert_eq!(ticks.label(5.0), "100000");
+
+    // The multiples 9 and 10 would be 3.6 pixels apart
+    let ticks = Ticks::new(AxisScale::Log, 0.0125);
+    assert_eq!((ticks.step, ticks.minor), (1.0, None));
+    assert_eq!(
+        ticks.between(-1.5, 1.5),
+        [-1.0, 0.0, 1.0].map(|value| Tick { value, major: true })
+    );
+
+    assert_eq!(
+        AxisScale::Log
+            .to_graph(AxisScale::Log.to_view(250.0))
+            .round(),
+        250.0
+    );
+}
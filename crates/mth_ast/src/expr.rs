@@ -3,7 +3,32 @@ use super::*;
 #[derive(Clone, PartialEq)]
 pub enum Expr<'s> {
     FunctionCall(FunctionCall<'s>),
-    Literal(Literal),
+    Literal(Literal, Span),
+    Lambda(Lambda<'s>),
+
+    /// `[a, b, ...]`, only valid as the value of a named argument
+    List(Vec<Expr<'s>>, Span),
+}
+
+impl Expr<'_> {
+    pub fn span(&self) -> Span {
+        match self {
+            Self::FunctionCall(fn_call) => fn_call.span,
+            Self::Literal(_, span) => *span,
+            Self::Lambda(lambda) => lambda.span,
+            Self::List(_, span) => *span,
+        }
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        match &mut self {
+            Self::FunctionCall(fn_call) => fn_call.span = span,
+            Self::Literal(_, s) => *s = span,
+            Self::Lambda(lambda) => lambda.span = span,
+            Self::List(_, s) => *s = span,
+        }
+        self
+    }
 }
 
 impl std::ops::Neg for Expr<'_> {
@@ -11,8 +36,10 @@ impl std::ops::Neg for Expr<'_> {
 
     fn neg(self) -> Self::Output {
         match self {
-            Self::Literal(lit) => Self::Literal(-lit),
+            Self::Literal(lit, span) => Self::Literal(-lit, span),
             Self::FunctionCall(fn_call) => Self::FunctionCall(-fn_call),
+            Self::Lambda(lambda) => Self::Lambda(-lambda),
+            Self::List(items, span) => Self::List(items.into_iter().map(|x| -x).collect(), span),
         }
     }
 }
@@ -21,7 +48,9 @@ impl<'s> std::fmt::Debug for Expr<'s> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::FunctionCall(x) => x.fmt(f),
-            Self::Literal(x) => x.fmt(f),
+            Self::Literal(x, _) => x.fmt(f),
+            Self::Lambda(x) => x.fmt(f),
+            Self::List(x, _) => x.fmt(f),
         }
     }
 }
@@ -30,7 +59,9 @@ pub fn varref<'s>(name: &'s str) -> Expr<'s> {
     Expr::FunctionCall(FunctionCall {
         name,
         args: vec![],
+        named_args: vec![],
         is_negated: false,
+        span: Span::default(),
     })
 }
 
@@ -38,6 +69,16 @@ pub fn function_call<'s>(name: &'s str, args: Vec<Expr<'s>>) -> Expr<'s> {
     Expr::FunctionCall(FunctionCall {
         name,
         args,
+        named_args: vec![],
         is_negated: false,
+        span: Span::default(),
+    })
+}
+
+pub fn lambda<'s>(params: &[&'s str], body: Expr<'s>) -> Expr<'s> {
+    Expr::Lambda(Lambda {
+        params: params.iter().copied().map(Param::new).collect(),
+        body: Box::new(body),
+        span: Span::default(),
     })
 }
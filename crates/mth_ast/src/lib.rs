@@ -1,3 +1,6 @@
+mod span;
+pub use span::Span;
+
 mod module;
 pub use module::Module;
 
@@ -5,16 +8,21 @@ mod top_level;
 pub use top_level::TopLevel;
 
 mod expr;
-pub use expr::{Expr, function_call, varref};
+pub use expr::{Expr, function_call, lambda, varref};
 
 mod type_decl;
 pub use type_decl::{Type, TypeDecl};
 
 mod mapping;
-pub use mapping::{Function, Param};
+pub use mapping::{Function, Lambda, Param};
 
 mod s_expr;
-pub use s_expr::FunctionCall;
+pub use s_expr::{FunctionCall, NamedArg};
 
 mod literal;
 pub use literal::{Literal, int};
+
+#[cfg(feature = "test-support")]
+mod strip_spans;
+#[cfg(feature = "test-support")]
+pub use strip_spans::StripSpans;
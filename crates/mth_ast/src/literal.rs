@@ -5,6 +5,7 @@ pub enum Literal {
     Int(i32),
     Float(f64),
     Bool(bool),
+    String(String),
 }
 
 impl Neg for Literal {
@@ -14,10 +15,11 @@ impl Neg for Literal {
             Self::Int(v) => Self::Int(-v),
             Self::Float(v) => Self::Float(-v),
             Self::Bool(v) => Self::Bool(!v),
+            Self::String(v) => Self::String(v),
         }
     }
 }
 
 pub fn int(x: i32) -> super::Expr<'static> {
-    super::Expr::Literal(Literal::Int(x))
+    super::Expr::Literal(Literal::Int(x), super::Span::default())
 }
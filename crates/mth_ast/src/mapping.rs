@@ -5,7 +5,35 @@ pub struct Function<'s> {
     pub name: &'s str,
     pub params: Vec<Param<'s>>,
     pub body: Expr<'s>,
+    pub span: Span,
+
+    /// Lines of the doc comments (`|| ...`) before the definition
+    pub doc: Vec<&'s str>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param<'s>(pub &'s str, pub Span);
+
+impl<'s> Param<'s> {
+    pub fn new(name: &'s str) -> Self {
+        Self(name, Span::default())
+    }
 }
 
+/// Anonymous function, e.g. `t -> 1 + cos(t)`
 #[derive(Debug, Clone, PartialEq)]
-pub struct Param<'s>(pub &'s str);
+pub struct Lambda<'s> {
+    pub params: Vec<Param<'s>>,
+    pub body: Box<Expr<'s>>,
+    pub span: Span,
+}
+
+impl std::ops::Neg for Lambda<'_> {
+    type Output = Self;
+    fn neg(self) -> Self::Output {
+        Self {
+            body: Box::new(-*self.body),
+            ..self
+        }
+    }
+}
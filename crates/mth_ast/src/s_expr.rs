@@ -6,7 +6,20 @@ use super::*;
 pub struct FunctionCall<'s> {
     pub name: &'s str,
     pub args: Vec<Expr<'s>>,
+
+    /// Arguments passed by name, like `color = "red"`, in the order they are written
+    pub named_args: Vec<NamedArg<'s>>,
+
     pub is_negated: bool,
+    pub span: Span,
+}
+
+/// An argument passed by name, `name = value`
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamedArg<'s> {
+    pub name: &'s str,
+    pub value: Expr<'s>,
+    pub span: Span,
 }
 
 impl Neg for FunctionCall<'_> {
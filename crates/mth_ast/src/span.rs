@@ -0,0 +1,38 @@
+/// Location of a node in the source: the byte range `start..end`, and the line and column of
+/// `start` as counted by the parser's cursor.
+///
+/// Nodes built by hand, e.g. with [`crate::varref`], have the default span. Tests compare them
+/// with parsed nodes after `StripSpans::strip_spans`
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub col: usize,
+}
+
+impl Span {
+    /// Span from the start of `self` to the end of `other`
+    pub fn to(self, other: Span) -> Self {
+        Self {
+            end: other.end.max(self.start),
+            ..self
+        }
+    }
+}
+
+impl std::fmt::Debug for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}..{} ({}:{})",
+            self.start, self.end, self.line, self.col
+        )
+    }
+}
+
+impl From<Span> for mth_diagnostics::Span {
+    fn from(span: Span) -> Self {
+        mth_diagnostics::Span::new(span.start, span.end)
+    }
+}
@@ -0,0 +1,108 @@
+//! Nodes without their positions, for tests that compare parsed nodes with nodes built by hand
+
+use super::*;
+
+/// A node with every span in it replaced by the default span
+pub trait StripSpans {
+    fn strip_spans(self) -> Self;
+}
+
+impl<T: StripSpans> StripSpans for Vec<T> {
+    fn strip_spans(self) -> Self {
+        self.into_iter().map(StripSpans::strip_spans).collect()
+    }
+}
+
+impl StripSpans for Literal {
+    fn strip_spans(self) -> Self {
+        self
+    }
+}
+
+impl StripSpans for Module<'_> {
+    fn strip_spans(self) -> Self {
+        Self {
+            top_level: self.top_level.strip_spans(),
+            ..self
+        }
+    }
+}
+
+impl StripSpans for TopLevel<'_> {
+    fn strip_spans(self) -> Self {
+        match self {
+            Self::TypeDecl(type_decl) => Self::TypeDecl(type_decl.strip_spans()),
+            Self::Function(f) => Self::Function(f.strip_spans()),
+            Self::Expr(expr) => Self::Expr(expr.strip_spans()),
+            Self::Error(_) => Self::Error(Span::default()),
+        }
+    }
+}
+
+impl StripSpans for TypeDecl<'_> {
+    fn strip_spans(self) -> Self {
+        Self {
+            span: Span::default(),
+            ..self
+        }
+    }
+}
+
+impl StripSpans for Function<'_> {
+    fn strip_spans(self) -> Self {
+        Self {
+            params: self.params.strip_spans(),
+            body: self.body.strip_spans(),
+            span: Span::default(),
+            ..self
+        }
+    }
+}
+
+impl StripSpans for Param<'_> {
+    fn strip_spans(self) -> Self {
+        Self(self.0, Span::default())
+    }
+}
+
+impl StripSpans for Lambda<'_> {
+    fn strip_spans(self) -> Self {
+        Self {
+            params: self.params.strip_spans(),
+            body: Box::new(self.body.strip_spans()),
+            span: Span::default(),
+        }
+    }
+}
+
+impl StripSpans for Expr<'_> {
+    fn strip_spans(self) -> Self {
+        match self {
+            Self::FunctionCall(fn_call) => Self::FunctionCall(fn_call.strip_spans()),
+            Self::Literal(literal, _) => Self::Literal(literal, Span::default()),
+            Self::Lambda(lambda) => Self::Lambda(lambda.strip_spans()),
+            Self::List(items, _) => Self::List(items.strip_spans(), Span::default()),
+        }
+    }
+}
+
+impl StripSpans for FunctionCall<'_> {
+    fn strip_spans(self) -> Self {
+        Self {
+            args: self.args.strip_spans(),
+            named_args: self.named_args.strip_spans(),
+            span: Span::default(),
+            ..self
+        }
+    }
+}
+
+impl StripSpans for NamedArg<'_> {
+    fn strip_spans(self) -> Self {
+        Self {
+            value: self.value.strip_spans(),
+            span: Span::default(),
+            ..self
+        }
+    }
+}
@@ -5,4 +5,18 @@ pub enum TopLevel<'s> {
     TypeDecl(TypeDecl<'s>),
     Function(Function<'s>),
     Expr(Expr<'s>),
+
+    /// A statement that failed to parse, up to and including its `;`
+    Error(Span),
+}
+
+impl TopLevel<'_> {
+    pub fn span(&self) -> Span {
+        match self {
+            Self::TypeDecl(type_decl) => type_decl.span,
+            Self::Function(f) => f.span,
+            Self::Expr(expr) => expr.span(),
+            Self::Error(span) => *span,
+        }
+    }
 }
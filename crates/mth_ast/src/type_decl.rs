@@ -1,11 +1,17 @@
+use super::Span;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct TypeDecl<'s> {
     pub name: &'s str,
+
+    /// Types of the parameters, followed by the type of the result
     pub params: Vec<Type>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Type {
+    Number,
     Int,
     String,
     Bool,
@@ -1,8 +1,54 @@
 pub mod ops;
 pub mod plot_desc;
 
-pub const N_PLOTS: usize = 8;
+/// Number of values the VM stack can hold
+pub const STACK_SIZE: usize = 16;
+
+/// Number of local slots of the VM, for `OP_TEE` and `OP_LOAD`
+pub const N_LOCALS: usize = 16;
+
+/// Number of parameter slots of the VM for the values of sliders, read by `OP_PARAM`. A
+/// multiple of 4, the shader packs them into vectors
+pub const MAX_PARAMS: usize = 16;
 
 pub const PLOT_TYPE_NO_PLOT: u32 = 0;
 pub const PLOT_TYPE_FN_GRAPH: u32 = 1;
 pub const PLOT_TYPE_EQUATION: u32 = 2;
+/// A curve `(x(t), y(t))`, the program leaves `x` and `y` on the stack
+pub const PLOT_TYPE_PARAMETRIC: u32 = 3;
+/// A curve `r(θ)` in polar coordinates
+pub const PLOT_TYPE_POLAR: u32 = 4;
+/// The graph of `x = g(y)`
+pub const PLOT_TYPE_INVERSE: u32 = 5;
+
+/// Number of line segments that approximate parametric and polar curves over their range
+pub const CURVE_SEGMENTS: u32 = 256;
+
+/// Screen pixels per graph unit at zoom level 0
+pub const ZOOM_PIXELS_FACTOR: f64 = 200.0;
+
+/// Size of a screen pixel in graph units at the zoom level `zoom`
+pub fn pixel_ratio(zoom: f64) -> f64 {
+    1.0 / 2.0_f64.powf(zoom) / ZOOM_PIXELS_FACTOR
+}
+
+/// The zoom level at which a screen pixel is `pixel_ratio` graph units, the inverse of
+/// [`pixel_ratio`]
+pub fn zoom_for_pixel_ratio(pixel_ratio: f64) -> f64 {
+    -(pixel_ratio * ZOOM_PIXELS_FACTOR).log2()
+}
+
+/// Power with a sign for negative bases, like `spow` in the shader
+pub fn spow(a: f32, b: f32) -> f32 {
+    if a >= 0.0 {
+        return a.powf(b);
+    }
+
+    let abs_pow = (-a).powf(b);
+
+    // If b is even, the result is positive, else negative. `fract` of WGSL rounds towards
+    // negative infinity unlike `f32::fract`
+    let half = b * 0.5;
+    let b_is_even = half - half.floor() == 0.0;
+    if b_is_even { abs_pow } else { -abs_pow }
+}
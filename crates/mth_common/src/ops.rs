@@ -1,3 +1,5 @@
+use std::fmt;
+
 // OpCodes
 pub const OP_CONST: u32 = 0;
 pub const OP_X: u32 = 1;
@@ -26,6 +28,130 @@ pub const OP_BW_OR: u32 = 21;
 pub const OP_BW_XOR: u32 = 22;
 pub const OP_BW_AND: u32 = 23;
 
+pub const OP_NEG: u32 = 24;
+
+// Reuse of values, the argument of `OP_TEE` and `OP_LOAD` is the index of a local slot
+pub const OP_DUP: u32 = 25;
+pub const OP_TEE: u32 = 26;
+pub const OP_LOAD: u32 = 27;
+
+// Values of sliders, the argument of `OP_PARAM` is the index of a parameter slot
+pub const OP_PARAM: u32 = 28;
+
+// Time of an animation in seconds
+pub const OP_T: u32 = 29;
+
+/// Description of an opcode, the source of truth for the code generator, the interpreter and
+/// the `execute_instruction` function that `graph_canvas` generates for the shader
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct OpInfo {
+    pub opcode: u32,
+
+    /// Mnemonic, like `ADD`
+    pub name: &'static str,
+
+    /// Whether the instruction uses its argument `a`
+    pub immediate: bool,
+
+    /// Number of values popped from the stack
+    pub pops: u32,
+
+    /// WGSL expressions of the values pushed onto the stack, in order. The popped values are
+    /// `a`, `b`, ... from the bottom, so `b` is the top of the stack for binary operations.
+    /// The argument of the instruction is `op.a`, the inputs are `x` and `y`, the parameter
+    /// slots are read by `param(i)` and the time is `u.time`
+    pub results: &'static [&'static str],
+
+    /// WGSL statement that runs after the values are popped, empty for none. The local slots
+    /// are `locals`
+    pub effect: &'static str,
+}
+
+impl OpInfo {
+    /// Number of values pushed onto the stack
+    pub const fn pushes(&self) -> u32 {
+        self.results.len() as u32
+    }
+
+    /// Change of the stack size
+    pub const fn stack_effect(&self) -> i32 {
+        self.pushes() as i32 - self.pops as i32
+    }
+}
+
+const fn op(
+    opcode: u32,
+    name: &'static str,
+    pops: u32,
+    results: &'static [&'static str],
+) -> OpInfo {
+    OpInfo {
+        opcode,
+        name,
+        immediate: false,
+        pops,
+        results,
+        effect: "",
+    }
+}
+
+/// All opcodes, indexed by their number
+pub const OPS: &[OpInfo] = &[
+    OpInfo {
+        immediate: true,
+        ..op(OP_CONST, "CONST", 0, &["op.a"])
+    },
+    op(OP_X, "X", 0, &["x"]),
+    op(OP_ADD, "ADD", 2, &["a + b"]),
+    op(OP_SUB, "SUB", 2, &["a - b"]),
+    op(OP_MUL, "MUL", 2, &["a * b"]),
+    op(OP_DIV, "DIV", 2, &["a / b"]),
+    op(OP_POW, "POW", 2, &["spow(a, b)"]),
+    op(OP_COS, "COS", 1, &["cos(a)"]),
+    op(OP_SIN, "SIN", 1, &["sin(a)"]),
+    op(OP_TAN, "TAN", 1, &["tan(a)"]),
+    op(OP_LOG, "LOG", 1, &["log(a)"]),
+    op(OP_EQ, "EQ", 2, &["step(abs(a - b), 0.01)"]),
+    op(OP_LT, "LT", 2, &["step(a - b, 0.001)"]),
+    op(OP_LE, "LE", 2, &["step(a - b + 0.001, 0.001)"]),
+    op(OP_GT, "GT", 2, &["step(b - a, 0.001)"]),
+    op(OP_GE, "GE", 2, &["step(b - a + 0.001, 0.001)"]),
+    op(OP_NE, "NE", 2, &["1.0 - step(abs(a - b), 0.01)"]),
+    op(OP_Y, "Y", 0, &["y"]),
+    op(OP_ABS, "ABS", 1, &["abs(a)"]),
+    op(OP_OR, "OR", 2, &["step(0.001, a + b)"]),
+    op(OP_AND, "AND", 2, &["step(1.001, a + b)"]),
+    op(OP_BW_OR, "BW_OR", 2, &["f32(i32(a) | i32(b))"]),
+    op(OP_BW_XOR, "BW_XOR", 2, &["f32(i32(a) ^ i32(b))"]),
+    op(OP_BW_AND, "BW_AND", 2, &["f32(i32(a) & i32(b))"]),
+    op(OP_NEG, "NEG", 1, &["-a"]),
+    op(OP_DUP, "DUP", 1, &["a", "a"]),
+    OpInfo {
+        immediate: true,
+        effect: "locals[u32(op.a)] = a;",
+        ..op(OP_TEE, "TEE", 1, &["a"])
+    },
+    OpInfo {
+        immediate: true,
+        ..op(OP_LOAD, "LOAD", 0, &["locals[u32(op.a)]"])
+    },
+    OpInfo {
+        immediate: true,
+        ..op(OP_PARAM, "PARAM", 0, &["param(u32(op.a))"])
+    },
+    op(OP_T, "T", 0, &["u.time"]),
+];
+
+/// Description of `opcode`, if it is known
+pub fn op_info(opcode: u32) -> Option<&'static OpInfo> {
+    OPS.get(opcode as usize)
+}
+
+/// Mnemonic of `opcode`, if it is known
+pub fn op_name(opcode: u32) -> Option<&'static str> {
+    op_info(opcode).map(|info| info.name)
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
 #[repr(C)]
 pub struct Instruction {
@@ -33,6 +159,17 @@ pub struct Instruction {
     pub a: f32,
 }
 
+/// Assembly like `CONST 2.5` or `ADD`
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match op_info(self.opcode) {
+            Some(info) if info.immediate => write!(f, "{} {}", info.name, self.a),
+            Some(info) => f.write_str(info.name),
+            None => write!(f, "<unknown opcode {}>", self.opcode),
+        }
+    }
+}
+
 impl Default for Instruction {
     fn default() -> Self {
         Self {
@@ -5,7 +5,25 @@ use crate::PLOT_TYPE_NO_PLOT;
 pub struct PlotDesc {
     pub length: u32,
     pub type_id: u32,
-    pub _pad: u64, // 16-byte alignment
+
+    /// Range of the parameter of parametric and polar curves
+    pub range: [f32; 2],
+
+    /// RGB color of the lines and the filled area of the plot
+    pub color: [f32; 3],
+
+    /// Thickness of the lines, in multiples of the default thickness
+    pub width: f32,
+
+    /// Lengths in pixels of the dashes and the gaps between them, `[0.0, 0.0]` for solid lines
+    pub dash: [f32; 2],
+
+    /// Opacity of the area of the plot: the region where a condition holds, between a graph
+    /// and its axis, or inside a curve
+    pub fill: f32,
+
+    /// The shader aligns the array elements to 16 bytes
+    pub _pad: u32,
 }
 
 impl Default for PlotDesc {
@@ -13,6 +31,11 @@ impl Default for PlotDesc {
         Self {
             length: 0,
             type_id: PLOT_TYPE_NO_PLOT,
+            range: [0.0, 0.0],
+            color: [1.0, 1.0, 1.0],
+            width: 1.0,
+            dash: [0.0, 0.0],
+            fill: 0.0,
             _pad: 0,
         }
     }
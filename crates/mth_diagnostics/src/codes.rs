@@ -0,0 +1,65 @@
+//! Error codes of all diagnostics, grouped by the stage that reports them.
+//!
+//! Codes are never reused: when a diagnostic is removed, its code stays reserved
+
+// Parsing
+/// The source doesn't match the grammar
+pub const SYNTAX_ERROR: &str = "E0001";
+
+// Name resolution
+/// A name is used that is neither a parameter, a builtin nor a definition
+pub const UNDEFINED_NAME: &str = "E0100";
+/// Two top-level definitions with the same name
+pub const DUPLICATE_DEFINITION: &str = "E0101";
+/// A definition with the name of a builtin
+pub const BUILTIN_REDEFINITION: &str = "E0102";
+/// Definitions that depend on themselves
+pub const CYCLIC_DEFINITION: &str = "E0103";
+
+// Code generation
+/// A function is called with the wrong number of arguments
+pub const WRONG_ARGUMENT_COUNT: &str = "E0200";
+/// A user function calls itself, it can't be inlined
+pub const RECURSIVE_CALL: &str = "E0201";
+/// A literal that can't be evaluated on the GPU
+pub const UNSUPPORTED_LITERAL: &str = "E0202";
+/// A lambda anywhere other than as an argument of `plot`
+pub const MISPLACED_LAMBDA: &str = "E0203";
+/// A mapping with more parameters than there are inputs
+pub const TOO_MANY_PARAMETERS: &str = "E0204";
+/// A top-level statement that is neither a definition, a `plot`, `view` nor `slider`
+pub const INVALID_TOP_LEVEL: &str = "E0205";
+/// A `plot` without arguments
+pub const EMPTY_PLOT: &str = "E0206";
+/// The parameter range of a curve isn't known at compile time
+pub const NON_CONSTANT_RANGE: &str = "E0207";
+/// The parameter range of a curve is empty or not finite
+pub const INVALID_RANGE: &str = "E0208";
+/// A named argument that the function doesn't take
+pub const UNKNOWN_OPTION: &str = "E0209";
+/// A named argument whose value isn't a valid setting of its option
+pub const INVALID_OPTION: &str = "E0210";
+/// A list anywhere other than as the value of a named argument
+pub const MISPLACED_LIST: &str = "E0211";
+/// More than one `view` statement in a program
+pub const DUPLICATE_VIEW: &str = "E0212";
+/// A `slider` whose name isn't an identifier, or whose step isn't a positive number
+pub const INVALID_SLIDER: &str = "E0213";
+
+// Limits of the GPU backend
+// E0300 and E0301 are reserved, they reported more plots and instructions than fit into the
+// fixed size GPU buffers
+/// A plot needs more values on the stack than the VM can hold
+pub const STACK_OVERFLOW: &str = "E0302";
+/// More sliders than the GPU has parameter slots
+pub const TOO_MANY_SLIDERS: &str = "E0303";
+
+// Type checking
+/// An expression has a different type than its context requires
+pub const TYPE_MISMATCH: &str = "E0400";
+/// A type declaration with a different number of parameters than its definition
+pub const SIGNATURE_MISMATCH: &str = "E0401";
+/// A type declaration without a definition
+pub const MISSING_DEFINITION: &str = "E0402";
+/// Two type declarations for the same name
+pub const DUPLICATE_DECLARATION: &str = "E0403";
@@ -0,0 +1,10 @@
+pub mod codes;
+mod diagnostic;
+mod render;
+mod span;
+
+pub use diagnostic::{Diagnostic, Label, Severity, Suggestion};
+pub use span::Span;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,144 @@
+use std::fmt::Write;
+
+use crate::{Diagnostic, Label, Span, span::floor_char_boundary};
+
+impl Diagnostic {
+    /// Render the diagnostic the way rustc does, with a snippet of `src` for every label and
+    /// suggestion. `filename` is only used in the location line
+    pub fn render(&self, src: &str, filename: Option<&str>) -> String {
+        let mut out = format!("{self}\n");
+
+        let max_line = self
+            .labels
+            .iter()
+            .map(|label| label.span)
+            .chain(
+                self.suggestions
+                    .iter()
+                    .filter_map(|s| s.edit.as_ref())
+                    .map(|e| e.0),
+            )
+            .map(|span| span.line_col(src).0)
+            .max()
+            .unwrap_or(1);
+        let pad = " ".repeat(max_line.to_string().len());
+
+        // Location and labelled snippets
+        if let Some(span) = self
+            .primary_span()
+            .or(self.labels.first().map(|label| label.span))
+        {
+            let (line, col) = span.line_col(src);
+            let filename = filename.unwrap_or("<input>");
+            _ = writeln!(out, "{pad}--> {filename}:{line}:{col}");
+            _ = writeln!(out, "{pad} |");
+
+            let mut labels: Vec<(usize, &Label)> = self
+                .labels
+                .iter()
+                .map(|label| (label.span.line_col(src).0, label))
+                .collect();
+            labels.sort_by_key(|(line, label)| (*line, label.span.start));
+
+            let mut prev_line = None;
+            for (line, label) in labels {
+                if prev_line != Some(line) {
+                    if let Some(prev) = prev_line
+                        && line > prev + 1
+                    {
+                        _ = writeln!(out, "...");
+                    }
+                    let text = &src[line_bounds(src, label.span.start)];
+                    _ = writeln!(out, "{line:>w$} | {text}", w = pad.len());
+                    prev_line = Some(line);
+                }
+
+                let mark = if label.primary { '^' } else { '-' };
+                let underline = underline(src, label.span, mark);
+                let label = match label.message.as_str() {
+                    "" => underline,
+                    message => format!("{underline} {message}"),
+                };
+                _ = writeln!(out, "{pad} | {label}");
+            }
+        }
+
+        // Notes and help without a source snippet
+        let helps = self.suggestions.iter().filter(|s| s.edit.is_none());
+        if !self.labels.is_empty() && (!self.notes.is_empty() || helps.clone().next().is_some()) {
+            _ = writeln!(out, "{pad} |");
+        }
+        for note in &self.notes {
+            _ = writeln!(out, "{pad} = note: {note}");
+        }
+        for help in helps {
+            _ = writeln!(out, "{pad} = help: {}", help.message);
+        }
+
+        // Suggestions shown applied to the source
+        for suggestion in &self.suggestions {
+            let Some((span, replacement)) = &suggestion.edit else {
+                continue;
+            };
+            let bounds = line_bounds(src, span.start);
+            let start = floor_char_boundary(src, span.start);
+            let end = floor_char_boundary(src, span.end).clamp(start, bounds.end);
+            let patched = format!(
+                "{}{replacement}{}",
+                &src[bounds.start..start],
+                &src[end..bounds.end]
+            );
+
+            _ = writeln!(out, "help: {}", suggestion.message);
+            _ = writeln!(out, "{pad} |");
+            _ = writeln!(
+                out,
+                "{:>w$} | {patched}",
+                span.line_col(src).0,
+                w = pad.len()
+            );
+            _ = writeln!(
+                out,
+                "{pad} | {}{}",
+                indent(&src[bounds.start..start]),
+                "~".repeat(replacement.chars().count().max(1))
+            );
+        }
+
+        out.truncate(out.trim_end().len());
+        out
+    }
+}
+
+/// Byte range of the line containing `offset`, without the line break
+fn line_bounds(src: &str, offset: usize) -> std::ops::Range<usize> {
+    let offset = floor_char_boundary(src, offset);
+    let start = src[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let end = src[offset..].find('\n').map_or(src.len(), |i| offset + i);
+    let end = if src[start..end].ends_with('\r') {
+        end - 1
+    } else {
+        end
+    };
+    start..end.max(start)
+}
+
+/// Whitespace taking up the same room as `text`, keeping tabs so the marks stay aligned
+fn indent(text: &str) -> String {
+    text.chars()
+        .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+        .collect()
+}
+
+/// Marks under the part of `span` on its first line, at least one wide
+fn underline(src: &str, span: Span, mark: char) -> String {
+    let bounds = line_bounds(src, span.start);
+    let start = floor_char_boundary(src, span.start).min(bounds.end);
+    let end = floor_char_boundary(src, span.end).clamp(start, bounds.end);
+    let width = src[start..end].chars().count().max(1);
+    format!(
+        "{}{}",
+        indent(&src[bounds.start..start]),
+        mark.to_string().repeat(width)
+    )
+}
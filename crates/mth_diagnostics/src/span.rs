@@ -0,0 +1,54 @@
+/// Byte range `start..end` into the source a diagnostic refers to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// Empty span at `offset`, rendered as a single caret
+    pub fn point(offset: usize) -> Self {
+        Self::new(offset, offset)
+    }
+
+    /// Smallest span covering both `self` and `other`
+    pub fn to(self, other: Span) -> Self {
+        Self::new(self.start.min(other.start), self.end.max(other.end))
+    }
+
+    pub fn len(&self) -> usize {
+        self.end.saturating_sub(self.start)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 1-based line and column (in chars) of the start of the span in `src`
+    pub fn line_col(&self, src: &str) -> (usize, usize) {
+        let start = floor_char_boundary(src, self.start);
+        let line_start = src[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line = src[..line_start].matches('\n').count() + 1;
+        let col = src[line_start..start].chars().count() + 1;
+        (line, col)
+    }
+}
+
+impl From<std::ops::Range<usize>> for Span {
+    fn from(range: std::ops::Range<usize>) -> Self {
+        Self::new(range.start, range.end)
+    }
+}
+
+/// Clamp `offset` to `src` and move it back to the closest char boundary
+pub(crate) fn floor_char_boundary(src: &str, offset: usize) -> usize {
+    let mut offset = offset.min(src.len());
+    while !src.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
@@ -0,0 +1,130 @@
+use crate::{Diagnostic, Span, codes};
+
+#[test]
+fn header_only() {
+    let d = Diagnostic::error("Undefined name `a`").with_code(codes::UNDEFINED_NAME);
+    assert_eq!(d.to_string(), "error[E0100]: Undefined name `a`");
+    assert_eq!(d.render("", None), "error[E0100]: Undefined name `a`");
+
+    let d = Diagnostic::warning("unused definition");
+    assert_eq!(d.to_string(), "warning: unused definition");
+}
+
+#[test]
+fn line_col() {
+    let src = "f(x) = x;\nplot(g);";
+    assert_eq!(Span::point(0).line_col(src), (1, 1));
+    assert_eq!(Span::point(7).line_col(src), (1, 8));
+    assert_eq!(Span::point(15).line_col(src), (2, 6));
+    assert_eq!(Span::point(100).line_col(src), (2, 9));
+    assert_eq!(Span::point(2).line_col("π(x"), (1, 2));
+}
+
+#[test]
+fn primary_label() {
+    let src = "f(x) = x;\nplot(g);";
+    let d = Diagnostic::error("Undefined name `g`")
+        .with_code(codes::UNDEFINED_NAME)
+        .with_primary(Span::new(15, 16), "not defined");
+    assert_eq!(
+        d.render(src, Some("main.mth")),
+        "\
+error[E0100]: Undefined name `g`
+ --> main.mth:2:6
+  |
+2 | plot(g);
+  |      ^ not defined"
+    );
+}
+
+#[test]
+fn labels_notes_and_help() {
+    let src = "a = b;\nb = a;";
+    let d = Diagnostic::error("Cyclic definition: a -> b -> a")
+        .with_code(codes::CYCLIC_DEFINITION)
+        .with_primary(Span::new(4, 5), "`a` depends on `b`")
+        .with_secondary(Span::new(7, 13), "which depends on `a`")
+        .with_note("definitions can't depend on themselves")
+        .with_help("use a parameter instead");
+    assert_eq!(
+        d.render(src, None),
+        "\
+error[E0103]: Cyclic definition: a -> b -> a
+ --> <input>:1:5
+  |
+1 | a = b;
+  |     ^ `a` depends on `b`
+2 | b = a;
+  | ------ which depends on `a`
+  |
+  = note: definitions can't depend on themselves
+  = help: use a parameter instead"
+    );
+}
+
+#[test]
+fn distant_lines_and_wide_gutter() {
+    let src = "a = 1;\n\n\n\n\n\n\n\n\nb = a a;";
+    let d = Diagnostic::error("syntax error")
+        .with_secondary(Span::new(0, 1), "")
+        .with_primary(Span::new(19, 22), "expected `;`");
+    assert_eq!(
+        d.render(src, None),
+        "\
+error: syntax error
+  --> <input>:10:5
+   |
+ 1 | a = 1;
+   | -
+...
+10 | b = a a;
+   |     ^^^ expected `;`"
+    );
+}
+
+#[test]
+fn span_past_end_of_line() {
+    let src = "plot(f\nf(x) = x;";
+    let d = Diagnostic::error("syntax error").with_primary(Span::new(6, 12), "expected `)`");
+    assert_eq!(
+        d.render(src, None),
+        "\
+error: syntax error
+ --> <input>:1:7
+  |
+1 | plot(f
+  |       ^ expected `)`"
+    );
+}
+
+#[test]
+fn suggestion_with_edit() {
+    let src = "f(x) = sni(x);";
+    let d = Diagnostic::error("Undefined name `sni`")
+        .with_primary(Span::new(7, 10), "")
+        .with_suggestion(
+            "a builtin with a similar name exists",
+            Span::new(7, 10),
+            "sin",
+        );
+    assert_eq!(
+        d.render(src, None),
+        "\
+error: Undefined name `sni`
+ --> <input>:1:8
+  |
+1 | f(x) = sni(x);
+  |        ^^^
+help: a builtin with a similar name exists
+  |
+1 | f(x) = sin(x);
+  |        ~~~"
+    );
+}
+
+#[test]
+fn tabs_keep_alignment() {
+    let src = "\tplot(g);";
+    let d = Diagnostic::error("Undefined name `g`").with_primary(Span::new(6, 7), "");
+    assert!(d.render(src, None).ends_with("  | \t     ^"));
+}
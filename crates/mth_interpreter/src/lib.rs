@@ -0,0 +1,268 @@
+//! CPU implementation of the stack VM in `graph_shader.wgsl`.
+//!
+//! Evaluates compiled programs without a GPU, in `f32` like the shader and with the same
+//! results for every instruction, including the fuzzy comparisons and the `-1.0` that
+//! signals errors
+
+use std::fmt;
+
+use mth_common::{
+    CURVE_SEGMENTS, N_LOCALS, PLOT_TYPE_NO_PLOT, PLOT_TYPE_POLAR, STACK_SIZE, inst, ops::*,
+    plot_desc::PlotDesc, spow,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Value the shader returns when the evaluation of a program fails
+pub const ERROR_VALUE: f32 = -1.0;
+
+/// Why the evaluation of a program failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The program reaches past the end of the instructions
+    OutOfBounds,
+    /// The program pushes more than `STACK_SIZE - 1` values
+    StackOverflow,
+    /// An instruction pops more values than there are on the stack.
+    ///
+    /// The shader reads out of bounds instead, with undefined results. The compiler never
+    /// emits such programs
+    StackUnderflow,
+    /// `OP_TEE` or `OP_LOAD` with a slot past `N_LOCALS`.
+    ///
+    /// The shader clamps the index instead. The compiler never emits such programs
+    InvalidLocal,
+    /// `OP_PARAM` or `OP_T`, whose values the shader reads from its uniforms. Programs with
+    /// parameters are evaluated after [`bind_parameters`]
+    UnboundParameter,
+    /// An opcode of [`OPS`] that the interpreter doesn't evaluate yet
+    UnsupportedOpcode(&'static str),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::OutOfBounds => write!(f, "program reaches past the end of the instructions"),
+            Error::StackOverflow => write!(f, "stack overflow, at most {} values", STACK_SIZE - 1),
+            Error::StackUnderflow => write!(f, "stack underflow"),
+            Error::InvalidLocal => write!(f, "local slot out of range, at most {N_LOCALS} slots"),
+            Error::UnboundParameter => write!(f, "parameter without a value"),
+            Error::UnsupportedOpcode(name) => write!(f, "OP_{name} is not supported"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Where the program of one plot is in the instruction buffer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Plot {
+    pub type_id: u32,
+    pub offset: usize,
+    pub len: usize,
+
+    /// Range of the parameter of parametric and polar curves
+    pub range: [f32; 2],
+}
+
+/// The plots described by `plot_descs`, up to the first unused slot like the shader
+pub fn plots(plot_descs: &[PlotDesc]) -> impl Iterator<Item = Plot> + '_ {
+    plot_descs
+        .iter()
+        .take_while(|desc| desc.type_id != PLOT_TYPE_NO_PLOT)
+        .scan(0, |offset, desc| {
+            let plot = Plot {
+                type_id: desc.type_id,
+                offset: *offset,
+                len: desc.length as usize,
+                range: desc.range,
+            };
+            *offset += plot.len;
+            Some(plot)
+        })
+}
+
+/// `instructions` with every `OP_PARAM` replaced by the constant value of its slot in `params`,
+/// and every `OP_T` by `time`. Slots past the end of `params` are 0.0, like the unused slots of
+/// the shader
+pub fn bind_parameters(
+    instructions: &[Instruction],
+    params: &[f32],
+    time: f32,
+) -> Vec<Instruction> {
+    instructions
+        .iter()
+        .map(|op| match op.opcode {
+            OP_PARAM => inst!(OP_CONST, params.get(op.a as usize).copied().unwrap_or(0.0)),
+            OP_T => inst!(OP_CONST, time),
+            _ => *op,
+        })
+        .collect()
+}
+
+/// Evaluate the program at `offset..offset + len` of `instructions` like `eval_function` in the
+/// shader, returning [`ERROR_VALUE`] on errors
+pub fn eval_function(
+    instructions: &[Instruction],
+    offset: usize,
+    len: usize,
+    x: f32,
+    y: f32,
+) -> f32 {
+    instructions
+        .get(offset..offset + len)
+        .ok_or(Error::OutOfBounds)
+        .and_then(|program| eval(program, x, y))
+        .unwrap_or(ERROR_VALUE)
+}
+
+/// Run `program` with the inputs `x` and `y`. The result is the value at the bottom of the
+/// stack, `0.0` for an empty program
+pub fn eval(program: &[Instruction], x: f32, y: f32) -> Result<f32, Error> {
+    run(program, x, y).map(|stack| stack[0])
+}
+
+/// Run `program` with the input `x` set to `t`, the result is the point made of the two values
+/// at the bottom of the stack, like the parametric case of `curve_point` in `graph_shader.wgsl`
+pub fn eval_point(program: &[Instruction], t: f32) -> Result<[f32; 2], Error> {
+    run(program, t, 0.0).map(|stack| [stack[0], stack[1]])
+}
+
+/// The point of the parametric or polar curve `plot` at the `i`-th of the
+/// [`CURVE_SEGMENTS`]` + 1` samples of its range, like `curve_point` in the shader
+pub fn curve_point(instructions: &[Instruction], plot: &Plot, i: u32) -> Result<[f32; 2], Error> {
+    let program = instructions
+        .get(plot.offset..plot.offset + plot.len)
+        .ok_or(Error::OutOfBounds)?;
+    let [start, end] = plot.range;
+    let t = start + (end - start) * (i as f32 / CURVE_SEGMENTS as f32);
+
+    if plot.type_id == PLOT_TYPE_POLAR {
+        let r = eval(program, t, 0.0)?;
+        return Ok([r * t.cos(), r * t.sin()]);
+    }
+    eval_point(program, t)
+}
+
+/// The comparison that the program of an equation ends with, if it is one of `==`, `<`, `<=`,
+/// `>` and `>=`. The renderers draw these by the distance to where both sides are equal, see
+/// [`eval_difference`]
+pub fn final_comparison(program: &[Instruction]) -> Option<u32> {
+    let last = program.last()?;
+    matches!(last.opcode, OP_EQ | OP_LT | OP_LE | OP_GT | OP_GE).then_some(last.opcode)
+}
+
+/// The left minus the right side of the final comparison of `program`, like `draw_equation` in
+/// the shader
+pub fn eval_difference(program: &[Instruction], x: f32, y: f32) -> Result<f32, Error> {
+    let Some((_comparison, sides)) = program.split_last() else {
+        return Ok(0.0);
+    };
+    run(sides, x, y).map(|stack| stack[0] - stack[1])
+}
+
+/// Run `program` and return the whole stack
+fn run(program: &[Instruction], x: f32, y: f32) -> Result<[f32; STACK_SIZE], Error> {
+    let mut stack = [0.0; STACK_SIZE];
+    let mut locals = [0.0; N_LOCALS];
+    let mut sp = 0;
+
+    for op in program {
+        execute_instruction(op, x, y, &mut sp, &mut stack, &mut locals)?;
+
+        if sp >= STACK_SIZE {
+            return Err(Error::StackOverflow);
+        }
+    }
+
+    Ok(stack)
+}
+
+fn execute_instruction(
+    op: &Instruction,
+    x: f32,
+    y: f32,
+    sp: &mut usize,
+    stack: &mut [f32; STACK_SIZE],
+    locals: &mut [f32; N_LOCALS],
+) -> Result<(), Error> {
+    // Unknown opcodes are skipped, like by the `default` case of the shader
+    let Some(info) = op_info(op.opcode) else {
+        return Ok(());
+    };
+
+    // The popped values, `b` is the top of the stack for binary operations
+    let base = sp
+        .checked_sub(info.pops as usize)
+        .ok_or(Error::StackUnderflow)?;
+    let operand = |i: usize| {
+        if i < info.pops as usize {
+            stack[base + i]
+        } else {
+            0.0
+        }
+    };
+    let (a, b) = (operand(0), operand(1));
+
+    *sp = base;
+    let mut push = |value| {
+        stack[*sp] = value;
+        *sp += 1;
+    };
+
+    let result = match op.opcode {
+        OP_DUP => {
+            push(a);
+            a
+        }
+        OP_TEE => {
+            *locals.get_mut(op.a as usize).ok_or(Error::InvalidLocal)? = a;
+            a
+        }
+        OP_LOAD => *locals.get(op.a as usize).ok_or(Error::InvalidLocal)?,
+        OP_PARAM | OP_T => return Err(Error::UnboundParameter),
+
+        OP_CONST => op.a,
+        OP_X => x,
+        OP_Y => y,
+
+        OP_ADD => a + b,
+        OP_SUB => a - b,
+        OP_MUL => a * b,
+        OP_DIV => a / b,
+        OP_POW => spow(a, b),
+
+        OP_COS => a.cos(),
+        OP_SIN => a.sin(),
+        OP_TAN => a.tan(),
+        OP_LOG => a.ln(),
+        OP_ABS => a.abs(),
+        OP_NEG => -a,
+
+        OP_EQ => step((a - b).abs(), 0.01),
+        OP_NE => 1.0 - step((a - b).abs(), 0.01),
+        OP_LT => step(a - b, 0.001),
+        OP_LE => step(a - b + 0.001, 0.001),
+        OP_GT => step(b - a, 0.001),
+        OP_GE => step(b - a + 0.001, 0.001),
+
+        OP_AND => step(1.001, a + b),
+        OP_OR => step(0.001, a + b),
+
+        // `i32(a)` of WGSL truncates and saturates like `as`
+        OP_BW_OR => ((a as i32) | (b as i32)) as f32,
+        OP_BW_XOR => ((a as i32) ^ (b as i32)) as f32,
+        OP_BW_AND => ((a as i32) & (b as i32)) as f32,
+
+        _ => return Err(Error::UnsupportedOpcode(info.name)),
+    };
+
+    push(result);
+    Ok(())
+}
+
+/// `step` of WGSL: 1.0 if `edge <= x`, else 0.0
+fn step(edge: f32, x: f32) -> f32 {
+    if edge <= x { 1.0 } else { 0.0 }
+}
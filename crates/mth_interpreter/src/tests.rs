@@ -0,0 +1,345 @@
+use code_generator::compile_source;
+use mth_common::{
+    CURVE_SEGMENTS, N_LOCALS, PLOT_TYPE_EQUATION, PLOT_TYPE_FN_GRAPH, STACK_SIZE, inst, ops::*,
+    plot_desc::PlotDesc, spow,
+};
+
+use crate::{
+    ERROR_VALUE, Error, Plot, bind_parameters, curve_point, eval, eval_difference, eval_function,
+    execute_instruction, final_comparison, plots,
+};
+
+fn eval_binary(opcode: u32, a: f32, b: f32) -> f32 {
+    eval(
+        &[inst!(OP_CONST, a), inst!(OP_CONST, b), inst!(opcode)],
+        0.0,
+        0.0,
+    )
+    .unwrap()
+}
+
+/// Compile `src` and evaluate each of its plots at `(x, y)`
+fn run(src: &str, x: f32, y: f32) -> Vec<f32> {
+    let compiled = compile_source(src);
+
+    plots(&compiled.plot_descs)
+        .map(|plot| eval_function(&compiled.instructions, plot.offset, plot.len, x, y))
+        .collect()
+}
+
+#[test]
+fn test_eval_inputs() {
+    let program = [inst!(OP_X), inst!(OP_Y), inst!(OP_SUB)];
+    assert_eq!(eval(&program, 5.0, 3.0), Ok(2.0));
+}
+
+#[test]
+fn test_eval_empty_program() {
+    assert_eq!(eval(&[], 1.0, 2.0), Ok(0.0));
+}
+
+#[test]
+fn test_eval_arithmetic() {
+    assert_eq!(eval_binary(OP_ADD, 1.5, 2.0), 3.5);
+    assert_eq!(eval_binary(OP_MUL, 1.5, 2.0), 3.0);
+    assert_eq!(eval_binary(OP_DIV, 1.0, 4.0), 0.25);
+    assert!(eval_binary(OP_DIV, 1.0, 0.0).is_infinite());
+    assert_eq!(eval_binary(OP_POW, 2.0, 10.0), 1024.0);
+}
+
+#[test]
+fn test_spow() {
+    assert_eq!(spow(-2.0, 2.0), 4.0);
+    assert_eq!(spow(-2.0, 3.0), -8.0);
+    assert_eq!(spow(-2.0, -2.0), 0.25);
+    assert_eq!(spow(-8.0, 1.0 / 3.0), -2.0);
+    assert_eq!(spow(0.0, 0.0), 1.0);
+}
+
+#[test]
+fn test_eval_fuzzy_equality() {
+    assert_eq!(eval_binary(OP_EQ, 1.0, 1.005), 1.0);
+    assert_eq!(eval_binary(OP_EQ, 1.0, 1.02), 0.0);
+    assert_eq!(eval_binary(OP_NE, 1.0, 1.005), 0.0);
+    assert_eq!(eval_binary(OP_NE, 1.0, 1.02), 1.0);
+}
+
+#[test]
+fn test_eval_fuzzy_comparisons() {
+    // `<` and `>` hold for equal values, up to a difference of 0.001
+    assert_eq!(eval_binary(OP_LT, 1.0, 2.0), 1.0);
+    assert_eq!(eval_binary(OP_LT, 1.0, 1.0), 1.0);
+    assert_eq!(eval_binary(OP_LT, 1.01, 1.0), 0.0);
+    assert_eq!(eval_binary(OP_GT, 2.0, 1.0), 1.0);
+    assert_eq!(eval_binary(OP_GT, 1.0, 1.0), 1.0);
+    assert_eq!(eval_binary(OP_GT, 1.0, 1.01), 0.0);
+
+    assert_eq!(eval_binary(OP_LE, 1.0, 1.0), 1.0);
+    assert_eq!(eval_binary(OP_LE, 1.0005, 1.0), 0.0);
+    assert_eq!(eval_binary(OP_GE, 1.0, 1.0), 1.0);
+    assert_eq!(eval_binary(OP_GE, 1.0, 1.0005), 0.0);
+}
+
+#[test]
+fn test_eval_logic() {
+    assert_eq!(eval_binary(OP_AND, 1.0, 1.0), 1.0);
+    assert_eq!(eval_binary(OP_AND, 1.0, 0.0), 0.0);
+    assert_eq!(eval_binary(OP_OR, 1.0, 0.0), 1.0);
+    assert_eq!(eval_binary(OP_OR, 0.0, 0.0), 0.0);
+}
+
+#[test]
+fn test_eval_bitwise() {
+    assert_eq!(eval_binary(OP_BW_AND, 6.0, 3.0), 2.0);
+    assert_eq!(eval_binary(OP_BW_OR, 6.0, 3.0), 7.0);
+    assert_eq!(eval_binary(OP_BW_XOR, 6.0, 3.0), 5.0);
+    assert_eq!(eval_binary(OP_BW_AND, 6.9, -1.0), 6.0);
+}
+
+#[test]
+fn test_eval_unknown_opcodes_are_skipped() {
+    let program = [inst!(OP_CONST, 6.0), inst!(99)];
+    assert_eq!(eval(&program, 0.0, 0.0), Ok(6.0));
+}
+
+#[test]
+fn test_eval_implements_every_opcode() {
+    for info in OPS {
+        let mut program = vec![inst!(OP_CONST, 1.0); info.pops as usize];
+        program.push(inst!(info.opcode, 2.0));
+        let result = eval(&program, 0.0, 0.0);
+        match info.opcode {
+            // Replaced by constants before evaluation, see `bind_parameters`
+            OP_PARAM | OP_T => assert_eq!(result, Err(Error::UnboundParameter)),
+            _ => assert!(result.is_ok(), "OP_{}", info.name),
+        }
+    }
+}
+
+#[test]
+fn test_execute_instruction_supports_every_opcode() {
+    for info in OPS {
+        let mut stack = [1.0; STACK_SIZE];
+        let mut locals = [0.0; N_LOCALS];
+        let mut sp = info.pops as usize;
+        let result = execute_instruction(
+            &inst!(info.opcode, 0.0),
+            0.0,
+            0.0,
+            &mut sp,
+            &mut stack,
+            &mut locals,
+        );
+        assert_ne!(result, Err(Error::UnsupportedOpcode(info.name)));
+    }
+}
+
+#[test]
+fn test_eval_locals() {
+    let program = [
+        inst!(OP_X),
+        inst!(OP_TEE, 3.0),
+        inst!(OP_DUP),
+        inst!(OP_MUL),
+        inst!(OP_LOAD, 3.0),
+        inst!(OP_SUB),
+    ];
+    assert_eq!(eval(&program, 3.0, 0.0), Ok(6.0));
+
+    // Slots start at zero
+    assert_eq!(eval(&[inst!(OP_LOAD, 0.0)], 1.0, 1.0), Ok(0.0));
+    assert_eq!(
+        eval(&[inst!(OP_LOAD, N_LOCALS as f32)], 0.0, 0.0),
+        Err(Error::InvalidLocal)
+    );
+}
+
+#[test]
+fn test_eval_stack_overflow() {
+    let fits = [inst!(OP_CONST, 1.0); STACK_SIZE - 1];
+    assert_eq!(eval(&fits, 0.0, 0.0), Ok(1.0));
+
+    let overflows = [inst!(OP_CONST, 1.0); STACK_SIZE];
+    assert_eq!(eval(&overflows, 0.0, 0.0), Err(Error::StackOverflow));
+    assert_eq!(
+        Error::StackOverflow.to_string(),
+        format!("stack overflow, at most {} values", STACK_SIZE - 1)
+    );
+    assert_eq!(
+        eval_function(&overflows, 0, STACK_SIZE, 0.0, 0.0),
+        ERROR_VALUE
+    );
+}
+
+#[test]
+fn test_eval_stack_underflow() {
+    assert_eq!(eval(&[inst!(OP_SIN)], 0.0, 0.0), Err(Error::StackUnderflow));
+    assert_eq!(
+        eval(&[inst!(OP_X), inst!(OP_ADD)], 0.0, 0.0),
+        Err(Error::StackUnderflow)
+    );
+}
+
+#[test]
+fn test_eval_function_bounds() {
+    let instructions = [inst!(OP_CONST, 2.0); 16];
+    assert_eq!(eval_function(&instructions, 10, 1, 0.0, 0.0), 2.0);
+    assert_eq!(eval_function(&instructions, 15, 1, 0.0, 0.0), 2.0);
+    assert_eq!(
+        eval_function(&instructions[..5], 4, 2, 0.0, 0.0),
+        ERROR_VALUE
+    );
+}
+
+#[test]
+fn test_plots() {
+    let descs = [
+        PlotDesc {
+            length: 3,
+            type_id: PLOT_TYPE_FN_GRAPH,
+            ..Default::default()
+        },
+        PlotDesc {
+            length: 5,
+            type_id: PLOT_TYPE_EQUATION,
+            ..Default::default()
+        },
+        PlotDesc::default(),
+        PlotDesc {
+            length: 1,
+            type_id: PLOT_TYPE_FN_GRAPH,
+            ..Default::default()
+        },
+    ];
+
+    assert_eq!(
+        plots(&descs).collect::<Vec<_>>(),
+        vec![
+            Plot {
+                type_id: PLOT_TYPE_FN_GRAPH,
+                offset: 0,
+                len: 3,
+                range: [0.0, 0.0],
+            },
+            Plot {
+                type_id: PLOT_TYPE_EQUATION,
+                offset: 3,
+                len: 5,
+                range: [0.0, 0.0],
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_compiled_polynomial() {
+    let src = "f(x) = x^2 - 3x + 1;\nplot(f);";
+    for x in [-2.5f32, 0.0, 0.5, 3.0] {
+        let expected = x * x - 3.0 * x + 1.0;
+        assert!((run(src, x, 0.0)[0] - expected).abs() < 1e-5, "x = {x}");
+    }
+}
+
+#[test]
+fn test_compiled_user_calls_and_constants() {
+    let src = "a = 2;\ng(u, v) = u * v + a;\nh(x) = g(x, -3) / pi;\nplot(h, sin(x) * abs(x));";
+    for x in [-1.0f32, 0.25, 4.0] {
+        let values = run(src, x, 0.0);
+        let h = (x * -3.0 + 2.0) / std::f32::consts::PI;
+        let inline = x.sin() * x.abs();
+        assert!((values[0] - h).abs() < 1e-5, "x = {x}");
+        assert!((values[1] - inline).abs() < 1e-5, "x = {x}");
+    }
+}
+
+#[test]
+fn test_compiled_negative_powers() {
+    // The sign binds tighter than `^`: `-x^2` is `(-x)^2`
+    let src = "plot(x^3, -x^2, -(x^2), (x - 1)^2);";
+    let values = run(src, -2.0, 0.0);
+    assert_eq!(values, vec![-8.0, 4.0, -4.0, 9.0]);
+}
+
+#[test]
+fn test_compiled_common_subexpressions() {
+    let src = "f(t) = sin(t)^2 + sin(t) * cos(t);\nplot(f, f(x) * f(x));";
+    for x in [-1.0f32, 0.25, 4.0] {
+        let values = run(src, x, 0.0);
+        let f = x.sin().powi(2) + x.sin() * x.cos();
+        assert!((values[0] - f).abs() < 1e-5, "x = {x}");
+        assert!((values[1] - f * f).abs() < 1e-5, "x = {x}");
+    }
+}
+
+#[test]
+fn test_compiled_equation() {
+    let src = "circle(x, y) = x^2 + y^2 == 1;\nplot(circle, (x, y) -> y < x and x > 0);";
+    assert_eq!(run(src, 0.6, 0.8), vec![1.0, 0.0]);
+    assert_eq!(run(src, 0.5, 0.1), vec![0.0, 1.0]);
+    assert_eq!(run(src, -0.5, -0.6), vec![0.0, 0.0]);
+}
+
+#[test]
+fn test_curve_points() {
+    let compiled =
+        compile_source("plot(x);\nplot_parametric(t -> t, t -> 2 t, 1, 3);\nplot_polar(θ -> 2);");
+    let [_, parametric, polar] = plots(&compiled.plot_descs).collect::<Vec<_>>()[..] else {
+        panic!("expected three plots");
+    };
+    let point = |plot, i| curve_point(&compiled.instructions, &plot, i).unwrap();
+
+    // The samples span the whole range
+    assert_eq!(point(parametric, 0), [1.0, 2.0]);
+    assert_eq!(point(parametric, CURVE_SEGMENTS / 2), [2.0, 4.0]);
+    assert_eq!(point(parametric, CURVE_SEGMENTS), [3.0, 6.0]);
+
+    let [x, y] = point(polar, CURVE_SEGMENTS / 4);
+    assert!(x.abs() < 1e-5 && (y - 2.0).abs() < 1e-5, "{x}, {y}");
+}
+
+#[test]
+fn test_eval_difference_of_final_comparison() {
+    let program = [
+        inst!(OP_X),
+        inst!(OP_X),
+        inst!(OP_MUL),
+        inst!(OP_Y),
+        inst!(OP_LE),
+    ];
+    assert_eq!(final_comparison(&program), Some(OP_LE));
+    assert_eq!(eval_difference(&program, 3.0, 2.0), Ok(7.0));
+
+    assert_eq!(final_comparison(&program[..4]), None);
+    assert_eq!(final_comparison(&[]), None);
+}
+
+#[test]
+fn test_bind_parameters() {
+    let compiled = compile_source("slider(a, 0, 4, 0.5);\nslider(b, 0, 1, 1);\nplot(a x + b);");
+
+    // The shader reads the parameters from its uniforms
+    assert_eq!(
+        eval(&compiled.instructions, 3.0, 0.0),
+        Err(Error::UnboundParameter)
+    );
+    let bound = bind_parameters(&compiled.instructions, &[1.5, 1.0], 0.0);
+    assert_eq!(eval(&bound, 3.0, 0.0), Ok(5.5));
+
+    // Missing slots are 0
+    let bound = bind_parameters(&compiled.instructions, &[2.0], 0.0);
+    assert_eq!(eval(&bound, 3.0, 0.0), Ok(6.0));
+}
+
+#[test]
+fn test_bind_time() {
+    let compiled = compile_source("plot(x - t);");
+
+    assert_eq!(
+        eval(&compiled.instructions, 3.0, 0.0),
+        Err(Error::UnboundParameter)
+    );
+    for time in [0.0, 0.5, 2.0] {
+        let bound = bind_parameters(&compiled.instructions, &[], time);
+        assert_eq!(eval(&bound, 3.0, 0.0), Ok(3.0 - time));
+    }
+}
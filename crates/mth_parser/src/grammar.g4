@@ -6,11 +6,24 @@ module
 	;
 
 toplevel
-	: fn_decl ';'
+	: type_decl ';'
+	| fn_decl ';'
 	| var_assign ';'
 	| expr ';'
 	;
 
+// parameter types, then the result type
+type_decl
+	: IDENT '::' type ( '->' type )*
+	;
+
+type
+	: 'number'
+	| 'int'
+	| 'string'
+	| 'bool'
+	;
+
 fn_decl
 	: IDENT '(' paramlist ')' '=' expr
 	;
@@ -26,8 +39,11 @@ var_assign
 expr
 	// lower precedence first
 
+	// anonymous functions
+	: lambda                                            # lambda_expr
+
 	// logical ops
-	: expr 'or' expr                                    # logical_or
+	| expr 'or' expr                                    # logical_or
 	| expr 'and' expr                                   # logical_and
 
 	// bitwise ops
@@ -53,6 +69,11 @@ expr
 	| unary                                             # unary_atom
 	;
 
+lambda
+	: IDENT '->' expr
+	| '(' paramlist ')' '->' expr
+	;
+
 unary
     : ( '+' | '-' | 'not' ) unary                       # unary_op
     | primary                                           # unary_to_primary
@@ -60,19 +81,33 @@ unary
 
 primary
 	: '(' expr ')'
+	| '[' (expr ',')* expr? ']'
 	| fn_call
 	| IDENT
 	| INT
+	| STRING
 	;
 
 fn_call
-	: IDENT '(' (expr ',')* expr? ')'
+	: IDENT '(' (argument ',')* argument? ')'
+	;
+
+// named arguments are options of builtins, like `color = "red"`
+argument
+	: IDENT '=' expr
+	| expr
 	;
 
 
 INT  : [0-9]+ ;
 IDENT: [a-zA-Z_][a-zA-Z_0-9]* ;
+STRING: '"' ~["\r\n]* '"' ;
 
 WS: [ \t\n\r\f]+ -> skip ;
 
+// `|| ...` documents the definition that follows it
+DOC_COMMENT: '||' ~[\r\n]* -> channel(HIDDEN) ;
+BLOCK_COMMENT: '|*' ( BLOCK_COMMENT | . )*? '*|' -> skip ;
+LINE_COMMENT: '|' ~[*|\r\n] ~[\r\n]* -> skip ;
+
 // vim: et! sw=3 ts=3 sts=3
@@ -1,12 +1,59 @@
 pub mod parse_functions;
 
-use mth_ast::Module;
-use parser_lib::{cursor::Cursor, types::PResult};
+use mth_ast::{Module, TopLevel};
+use mth_diagnostics::{Diagnostic, Span, codes};
+use parser_lib::{cursor::Cursor, types::PError};
 
 use crate::parse_functions::parse_module;
 
-pub fn parse_program(s: &'_ str) -> PResult<'_, Module<'_>> {
+/// Parse a whole program.
+///
+/// Never fails as a whole: statements that don't parse become [`TopLevel::Error`] nodes in the
+/// module, with one diagnostic each
+pub fn parse_program(s: &'_ str) -> (Module<'_>, Vec<Diagnostic>) {
     let src = Cursor::new(s);
-    let (src, module_ast) = parse_module(src)?;
-    Ok((src, module_ast))
+    let (_, (module_ast, errors)) = parse_module(src).expect("parse_module recovers from errors");
+
+    let statements = module_ast.top_level.iter().filter_map(|tl| match tl {
+        TopLevel::Error(span) => Some(*span),
+        _ => None,
+    });
+    let diagnostics = errors
+        .iter()
+        .zip(statements)
+        .map(|(e, statement)| syntax_error(e).with_secondary(statement.into(), "in this statement"))
+        .collect();
+
+    (module_ast, diagnostics)
+}
+
+/// Convert an error of the parser combinators into a diagnostic. What the innermost parser
+/// expected labels the position of the error, the parsers it was nested in are notes
+pub fn syntax_error(e: &PError) -> Diagnostic {
+    let mut diagnostic = Diagnostic::error("syntax error")
+        .with_code(codes::SYNTAX_ERROR)
+        .with_primary(Span::point(e.ctx.offset), without_tags(&e.msg));
+    let mut notes: Vec<&str> = Vec::new();
+    for frame in e.context.iter().map(|frame| without_tags(frame)) {
+        // Frames of `pmatch!` without a message are only tags
+        if !frame.is_empty() && !notes.contains(&frame) {
+            notes.push(frame);
+        }
+    }
+    for note in notes {
+        diagnostic = diagnostic.with_note(note);
+    }
+    diagnostic
+}
+
+/// `msg` without the `[parser_name]` tag the combinators put in front of it
+fn without_tags(msg: &str) -> &str {
+    let mut rest = msg.trim_start();
+    while let Some(tag) = rest.strip_prefix('[')
+        && let Some((name, after)) = tag.split_once(']')
+        && name.chars().all(|ch| ch.is_alphanumeric() || ch == '_')
+    {
+        rest = after.trim_start();
+    }
+    rest
 }
@@ -26,10 +26,13 @@ fn parse_expression_with_precedence(src: Cursor, min_prec: u8) -> PResult<Expr>
         let next_min_prec = if right_assoc { prec } else { prec + 1 };
         let (next_src, right) = parse_expression_with_precedence(src, next_min_prec)?;
 
+        let span = left.span().to(right.span());
         left = Expr::FunctionCall(FunctionCall {
             name: op,
             args: vec![left, right],
+            named_args: vec![],
             is_negated: false,
+            span,
         });
 
         src = next_src;
@@ -42,12 +45,17 @@ fn parse_prefix_expression(src: Cursor) -> PResult<Expr> {
     pmatch! {src; err = "[parse_prefix] Couldn't match any prefix expression";
 
         // Unary negation - must come before regular expressions
-        preceded(chr('-'), parse_prefix_expression), x => -x;
-        preceded(chr('+'), parse_prefix_expression), x => x;
+        spanned(preceded(chr('-'), parse_prefix_expression)), (x, span) => (-x).with_span(span);
+        spanned(preceded(chr('+'), parse_prefix_expression)), (x, span) => x.with_span(span);
+
+        // Lambdas - must come before parenthesized expressions and identifiers
+        parse_lambda, x => Expr::Lambda(x);
 
         // Parenthesized expressions (should be tried before implicit multiplication)
         between(expr, tok(chr('(')), tok(chr(')'))), x => x;
 
+        spanned(list), (x, span) => Expr::List(x, span);
+
         // Function call
         parse_fn_call, x => Expr::FunctionCall(x);
 
@@ -61,13 +69,12 @@ fn parse_binop(src: Cursor) -> PResult<(&'static str, u8, bool)> {
 
     let opinfo = get_operator_info()
         .iter()
-        .filter(|opinfo| opinfo.0 == op)
-        .next()
+        .find(|opinfo| opinfo.0 == op)
         .cloned()
-        .ok_or(PError {
-            msg: "[parse_binop] Unknown operator".to_string(),
-            ctx: src.ctx.clone(),
-        })?;
+        .ok_or(PError::new(
+            "[parse_binop] Unknown operator",
+            src.ctx.clone(),
+        ))?;
 
     Ok((src, opinfo))
 }
@@ -105,6 +112,7 @@ fn get_operator_info() -> &'static [(&'static str, u8, bool)] {
 ///     : Literal
 ///     | IDENT
 ///     | '(' expr ')'
+///     | '[' (expr ',')* expr? ']'
 ///
 /// Supports implicit multiplication: "2 x" -> "2 * x"
 pub fn primary(src: Cursor) -> PResult<Expr> {
@@ -112,20 +120,30 @@ pub fn primary(src: Cursor) -> PResult<Expr> {
         // Try implicit multiplication first (literal/identifier followed by expression)
         try_implicit_multiplication, x => x;
         // Regular primary expressions
-        literal, x => Expr::Literal(x);
+        spanned(literal), (x, span) => Expr::Literal(x, span);
         parse_fn_call, x => Expr::FunctionCall(x);
-        tok(non_keyword_ident), x => varref(x);
+        spanned(non_keyword_ident), (x, span) => varref(x).with_span(span);
         between(expr, tok(chr('(')), tok(chr(')'))), x => x;
+        spanned(list), (x, span) => Expr::List(x, span);
     }
 }
 
+/// The elements of a list, `[a, b, ...]`
+fn list(src: Cursor) -> PResult<Vec<Expr>> {
+    between(
+        delimited0(tok(expr), tok(chr(','))),
+        tok(chr('[')),
+        tok(chr(']')),
+    )(src)
+}
+
 pub fn non_keyword_ident(src: Cursor<'_>) -> PResult<'_, &'_ str> {
     let (src, id) = ident(src)?;
     if ["and", "or", "bitwise_and", "bitwise_xor", "bitwise_or"].contains(&id) {
-        return Err(PError {
-            msg: format!("Cannot use {id} as identifier since it is a hard keyword"),
-            ctx: src.ctx.clone(),
-        });
+        return Err(PError::new(
+            format!("Cannot use {id} as identifier since it is a hard keyword"),
+            src.ctx.clone(),
+        ));
     }
     Ok((src, id))
 }
@@ -134,8 +152,8 @@ pub fn non_keyword_ident(src: Cursor<'_>) -> PResult<'_, &'_ str> {
 fn try_implicit_multiplication(src: Cursor) -> PResult<Expr> {
     // Parse a literal or identifier first
     let (src, left) = pmatch! {src; err = "[implicit_multiply] Expected literal or identifier";
-        literal, x => Expr::Literal(x);
-        tok(non_keyword_ident), x => varref(x);
+        spanned(literal), (x, span) => Expr::Literal(x, span);
+        spanned(non_keyword_ident), (x, span) => varref(x).with_span(span);
     }?;
 
     // Skip whitespace - required for implicit multiplication
@@ -146,22 +164,18 @@ fn try_implicit_multiplication(src: Cursor) -> PResult<Expr> {
 
     // Check if next token can be multiplied implicitly (literal, identifier, or '(')
     // But NOT if it looks like a function call (identifier followed by '(' without space)
-    let can_multiply = match src.clone() {
-        test_src => {
-            // Check if it starts with '(' (for parentheses)
-            if test_src.remainder.starts_with('(') {
-                true
-            } else if literal(test_src.clone()).is_ok() {
-                // Check for literal
-                true
-            } else if let Ok((after_ident, _)) = tok(non_keyword_ident)(test_src.clone()) {
-                // Check for identifier, but make sure it's not followed immediately by '('
-                // If the identifier is followed immediately by '(', it's a function call, not implicit multiplication
-                !after_ident.remainder.starts_with('(')
-            } else {
-                false
-            }
-        }
+    let can_multiply = if src.remainder.starts_with('(') {
+        // Check if it starts with '(' (for parentheses)
+        true
+    } else if literal(src.clone()).is_ok() {
+        // Check for literal
+        true
+    } else if let Ok((after_ident, _)) = tok(non_keyword_ident)(src.clone()) {
+        // Check for identifier, but make sure it's not followed immediately by '('
+        // If the identifier is followed immediately by '(', it's a function call, not implicit multiplication
+        !after_ident.remainder.starts_with('(')
+    } else {
+        false
     };
 
     if !can_multiply {
@@ -171,28 +185,38 @@ fn try_implicit_multiplication(src: Cursor) -> PResult<Expr> {
     // Parse the right side (but only primary expressions, not full expressions)
     let (src, right) = pmatch! {src; err = "[implicit_multiply] Expected right operand";
         between(expr, tok(chr('(')), tok(chr(')'))), x => x;
-        literal, x => Expr::Literal(x);
-        tok(non_keyword_ident), x => varref(x);
+        spanned(literal), (x, span) => Expr::Literal(x, span);
+        spanned(non_keyword_ident), (x, span) => varref(x).with_span(span);
     }?;
 
+    let span = left.span().to(right.span());
     Ok((
         src,
         Expr::FunctionCall(FunctionCall {
             name: "*",
             args: vec![left, right],
+            named_args: vec![],
             is_negated: false,
+            span,
         }),
     ))
 }
 
 #[cfg(test)]
 mod tests {
+    use mth_ast::StripSpans;
+
     use super::*;
 
     /// Helpers for convenience
     fn assert_expr(input: &str, expected: Expr, expected_rem: &str) {
         let (next, expr) = expr(Cursor::new(input)).expect("parse_expr failed");
-        assert_eq!(expr, expected, "remainder: {}", next.remainder);
+        assert_eq!(
+            expr.strip_spans(),
+            expected,
+            "remainder: {}",
+            next.remainder
+        );
         assert_eq!(next.remainder, expected_rem);
     }
 
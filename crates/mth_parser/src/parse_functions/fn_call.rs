@@ -3,26 +3,73 @@ use parser_lib::{choice, types::Parser};
 use super::*;
 
 pub fn parse_fn_call(src: Cursor) -> PResult<FunctionCall> {
-    let (src, name) = parse!(tok(ident), "Couldn't parse function name", src)?;
+    let (src, ()) = whitespace(src)?;
+    let start = src.ctx.clone();
+
+    let (src, name) = parse!(ident, "Couldn't parse function name", src)?;
 
     // Parse comma-separated arguments
-    let parse_args = delimited0(tok(expr), tok(chr(',')));
-    let (src, args) = parse!(
+    let parse_args = delimited0(tok(argument), tok(chr(',')));
+    let (src, all_args) = parse!(
         between(parse_args, tok(chr('(')), tok(chr(')'))),
         "Couldn't parse function arguments",
         src
     )?;
 
+    let mut args = Vec::new();
+    let mut named_args = Vec::new();
+    for arg in all_args {
+        match arg {
+            Argument::Positional(arg) => args.push(arg),
+            Argument::Named(arg) => named_args.push(arg),
+        }
+    }
+
+    let span = span_from(&start, &src);
     Ok((
         src,
         FunctionCall {
             name,
             args,
+            named_args,
             is_negated: false,
+            span,
         },
     ))
 }
 
+#[derive(Clone)]
+enum Argument<'s> {
+    Positional(Expr<'s>),
+    Named(NamedArg<'s>),
+}
+
+/// argument
+///     : IDENT '=' expr
+///     | expr
+///     ;
+fn argument(src: Cursor) -> PResult<Argument> {
+    pmatch! {src; err = "[parse_argument] Expected an argument";
+        named_arg, x => Argument::Named(x);
+        expr, x => Argument::Positional(x);
+    }
+}
+
+fn named_arg(src: Cursor) -> PResult<NamedArg> {
+    let (src, ()) = whitespace(src)?;
+    let start = src.ctx.clone();
+
+    let (src, name) = non_keyword_ident(src)?;
+    let (src, _) = tok(chr('='))(src)?;
+    if src.remainder.starts_with('=') {
+        return Err(PError::new("[named_arg] Found a comparison", src.ctx));
+    }
+    let (src, value) = expr(src)?;
+
+    let span = span_from(&start, &src);
+    Ok((src, NamedArg { name, value, span }))
+}
+
 pub fn parse_op<'s>() -> impl Parser<'s, &'s str> {
     preceded(
         whitespace,
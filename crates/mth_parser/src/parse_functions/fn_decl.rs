@@ -1,11 +1,14 @@
 use super::*;
 
 /// var_assign
-///	: IDENT '=' expr
-///	;
+///    : IDENT '=' expr
+///    ;
 pub fn parse_var_assign(src: Cursor) -> PResult<Function> {
+    let (src, ()) = whitespace(src)?;
+    let start = src.ctx.clone();
+
     // Name
-    let (src, name) = tok(ident)(src)?;
+    let (src, name) = ident(src)?;
 
     // '='
     let (src, _) = tok(chr('='))(src)?;
@@ -13,26 +16,32 @@ pub fn parse_var_assign(src: Cursor) -> PResult<Function> {
     // Value
     let (src, value) = expr(src)?;
 
+    let span = span_from(&start, &src);
     Ok((
         src,
         Function {
             name,
             params: vec![],
             body: value,
+            span,
+            doc: vec![],
         },
     ))
 }
 
 /// fn_decl
-///	: IDENT '(' paramlist ')' '=' expr
-///	;
+///    : IDENT '(' paramlist ')' '=' expr
+///    ;
 ///
 /// paramlist
-///	: (IDENT ',')* IDENT?
-///	;
+///    : (IDENT ',')* IDENT?
+///    ;
 pub fn parse_fn_decl(src: Cursor) -> PResult<Function> {
+    let (src, ()) = whitespace(src)?;
+    let start = src.ctx.clone();
+
     // Name
-    let (src, name) = parse!(tok(ident), "Could not parse mapping name", src)?;
+    let (src, name) = parse!(ident, "Could not parse mapping name", src)?;
 
     // Params
     let (src, params) = parse!(
@@ -47,10 +56,20 @@ pub fn parse_fn_decl(src: Cursor) -> PResult<Function> {
     // Body
     let (src, body) = expr(src)?;
 
-    Ok((src, Function { name, params, body }))
+    let span = span_from(&start, &src);
+    Ok((
+        src,
+        Function {
+            name,
+            params,
+            body,
+            span,
+            doc: vec![],
+        },
+    ))
 }
 
 pub fn paramlist(src: Cursor) -> PResult<Vec<Param>> {
-    let param = pmap(Param, tok(ident));
+    let param = pmap(|(name, span)| Param(name, span), spanned(ident));
     delimited0(&param, &tok(chr(',')))(src)
 }
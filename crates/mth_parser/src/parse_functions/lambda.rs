@@ -0,0 +1,32 @@
+use super::*;
+
+/// lambda
+///     : IDENT '->' expr
+///     | '(' paramlist ')' '->' expr
+///     ;
+pub fn parse_lambda(src: Cursor) -> PResult<Lambda> {
+    let (src, ()) = whitespace(src)?;
+    let start = src.ctx.clone();
+
+    // Params
+    let (src, params) = pmatch! {src; err = "[parse_lambda] Expected lambda parameters";
+        between(paramlist, tok(chr('(')), tok(chr(')'))), x => x;
+        spanned(non_keyword_ident), (x, span) => vec![Param(x, span)];
+    }?;
+
+    // '->'
+    let (src, _) = parse!(tok(keyword("->")), "Expected '->' in lambda", src)?;
+
+    // Body
+    let (src, body) = expr(src)?;
+
+    let span = span_from(&start, &src);
+    Ok((
+        src,
+        Lambda {
+            params,
+            body: Box::new(body),
+            span,
+        },
+    ))
+}
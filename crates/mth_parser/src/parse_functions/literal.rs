@@ -3,6 +3,7 @@ use super::*;
 pub fn literal(src: Cursor) -> PResult<Literal> {
     pmatch! {src; err = "[parse_literal]";
         tok(boolean), x => Literal::Bool(x);
+        tok(string), x => Literal::String(x.to_string());
     }
     .or_else(|_| {
         tok(int)(src.clone()).and_then(|(int_remainder, int)| {
@@ -13,10 +14,8 @@ pub fn literal(src: Cursor) -> PResult<Literal> {
                 tok(float)(src.clone())
                     .map(|(src, x)| (src, Literal::Float(x)))
                     .map_err(|mut e| {
-                        e.msg = format!(
-                            "[parse_literal] Could not parse float: {}\n{}",
-                            src.remainder, e.msg
-                        );
+                        e.context
+                            .push("[parse_literal] Could not parse float".to_string());
                         e
                     })
             } else {
@@ -30,7 +29,7 @@ fn int(src: Cursor) -> PResult<i32> {
     let (src, int) = parse!(some(digit(10)), "Could not parse int", src)?;
     Ok((
         src,
-        String::from_iter(int.into_iter())
+        String::from_iter(int)
             .parse::<i32>()
             .expect("Failed to parse int"),
     ))
@@ -61,30 +60,38 @@ fn float(src: Cursor) -> PResult<f64> {
 
         return match s.parse::<f64>() {
             Ok(f) => Ok((src_e, f)),
-            Err(_) => Err(PError {
-                msg: format!("Invalid float literal: {}", s),
-                ctx: src_e.ctx,
-            }),
+            Err(_) => Err(PError::new(
+                format!("Invalid float literal: {}", s),
+                src_e.ctx,
+            )),
         };
     }
 
     match s.parse::<f64>() {
         Ok(f) => Ok((src, f)),
-        Err(_) => Err(PError {
-            msg: format!("Invalid float literal: {}", s),
-            ctx: src.ctx,
-        }),
+        Err(_) => Err(PError::new(
+            format!("Invalid float literal: {}", s),
+            src.ctx,
+        )),
     }
 }
 
+/// `"..."`, without escapes. Strings can't span lines
+fn string(src: Cursor<'_>) -> PResult<'_, &str> {
+    let (src, _) = chr('"')(src)?;
+    let (src, content) = chr_take_while(|ch| !matches!(ch, '"' | '\n'))(src)?;
+    let (src, _) = parse!(chr('"'), "Unterminated string", src)?;
+    Ok((src, content))
+}
+
 fn boolean(src: Cursor) -> PResult<bool> {
     match parse!(tok(ident), "Bool is not 'true'", src)? {
         (src, "true") => Ok((src, true)),
         (src, "false") => Ok((src, false)),
-        (src, ident) => Err(PError {
-            msg: format!("[boolean] Expected 'true' or 'false', found {ident}"),
-            ctx: src.ctx,
-        }),
+        (src, ident) => Err(PError::new(
+            format!("[boolean] Expected 'true' or 'false', found {ident}"),
+            src.ctx,
+        )),
     }
 }
 
@@ -128,6 +135,17 @@ mod tests {
         assert_eq!(src.remainder, "");
     }
 
+    #[test]
+    fn literal_string() {
+        let src = Cursor::new("\"red\", 1");
+        let (src, v) = literal(src).unwrap();
+        assert_eq!(v, Literal::String("red".to_string()));
+        assert_eq!(src.remainder, ", 1");
+
+        assert!(literal(Cursor::new("\"red")).is_err());
+        assert!(literal(Cursor::new("\"red\nblue\"")).is_err());
+    }
+
     #[test]
     fn literal_float() {
         let src = Cursor::new("1.23");
@@ -1,5 +1,5 @@
-pub(self) use mth_ast::*;
-pub(self) use parser_lib::{
+use mth_ast::*;
+use parser_lib::{
     combinators::*,
     cursor::Cursor,
     helpers::*,
@@ -15,13 +15,16 @@ mod top_level;
 pub use top_level::parse_top_level;
 
 mod expr;
-pub use expr::{expr, primary};
+pub use expr::{expr, non_keyword_ident, primary};
 
 mod type_decl;
 pub use type_decl::parse_type_decl;
 
 mod fn_decl;
-pub use fn_decl::{parse_fn_decl, parse_var_assign};
+pub use fn_decl::{paramlist, parse_fn_decl, parse_var_assign};
+
+mod lambda;
+pub use lambda::parse_lambda;
 
 mod fn_call;
 pub use fn_call::{parse_fn_call, parse_op};
@@ -29,5 +32,8 @@ pub use fn_call::{parse_fn_call, parse_op};
 mod literal;
 pub use literal::literal;
 
+mod spanned;
+pub use spanned::{span_from, spanned};
+
 #[cfg(test)]
 mod tests;
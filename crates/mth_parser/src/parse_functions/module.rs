@@ -1,30 +1,82 @@
 use super::*;
 
-pub fn parse_module(src: Cursor) -> PResult<Module> {
+/// Parse top-level statements until the end of the input.
+///
+/// A statement that fails to parse is skipped up to and including the next `;` and replaced by
+/// a [`TopLevel::Error`] node, so one broken statement doesn't hide the rest. The errors are
+/// returned in the order of the statements.
+///
+/// Doc comments (`|| ...`) are attached to the [`Function`] that follows them. Doc comments in
+/// front of a type declaration are kept for the definition of the same name right after it
+pub fn parse_module(src: Cursor) -> PResult<(Module, Vec<PError>)> {
     let mut src = src;
     let mut exprs = Vec::new();
+    let mut errors = Vec::new();
+    let mut declared_doc: Option<(&str, Vec<&str>)> = None;
 
     loop {
+        let doc;
+        (src, doc) = match doc_comments(src.clone()) {
+            Ok(v) => v,
+            Err(e) => {
+                // Unterminated block comment, which swallows the rest of the input
+                src.advance(src.remainder.chars().count());
+                exprs.push(TopLevel::Error(span_from(&e.ctx, &src)));
+                errors.push(e);
+                break;
+            }
+        };
+        if src.remainder.is_empty() {
+            break;
+        }
+
         match parse_top_level(src.clone()) {
-            Ok((new_src, tl)) => {
+            Ok((new_src, mut tl)) => {
                 src = new_src;
+                match &mut tl {
+                    TopLevel::Function(f) => {
+                        f.doc = match declared_doc.take() {
+                            Some((name, declared)) if doc.is_empty() && name == f.name => declared,
+                            _ => doc,
+                        };
+                    }
+                    TopLevel::TypeDecl(decl) if !doc.is_empty() => {
+                        declared_doc = Some((decl.name, doc));
+                    }
+                    _ => declared_doc = None,
+                }
                 exprs.push(tl);
             }
-            Err(_) => break,
+            Err(e) => {
+                let start = src.ctx.clone();
+                let statement = match src.remainder.find(';') {
+                    Some(end) => &src.remainder[..=end],
+                    None => src.remainder,
+                };
+                src.advance(statement.chars().count());
+
+                exprs.push(TopLevel::Error(span_from(&start, &src)));
+                errors.push(e);
+            }
         }
     }
 
     Ok((
         src,
-        Module {
-            name: None,
-            top_level: exprs,
-        },
+        (
+            Module {
+                name: None,
+                top_level: exprs,
+            },
+            errors,
+        ),
     ))
 }
 
 #[test]
 fn golden_test_module() {
+    use mth_ast::StripSpans;
+
     let src = r#"
     a = 1;
     b = 2;
@@ -46,29 +98,39 @@ fn golden_test_module() {
                 name: "a",
                 params: vec![],
                 body: int(1),
+                span: Span::default(),
+                doc: vec![],
             }),
             TopLevel::Function(Function {
                 name: "b",
                 params: vec![],
                 body: int(2),
+                span: Span::default(),
+                doc: vec![],
             }),
             TopLevel::TypeDecl(TypeDecl {
                 name: "c",
                 params: vec![Type::Int],
+                span: Span::default(),
             }),
             TopLevel::Function(Function {
                 name: "c",
                 params: vec![],
                 body: varref("a"),
+                span: Span::default(),
+                doc: vec![],
             }),
             TopLevel::TypeDecl(TypeDecl {
                 name: "add",
                 params: vec![Type::Int, Type::Int, Type::Int],
+                span: Span::default(),
             }),
             TopLevel::Function(Function {
                 name: "add",
-                params: vec![Param("x"), Param("y")],
+                params: vec![Param::new("x"), Param::new("y")],
                 body: function_call("+", vec![varref("x"), varref("y")]),
+                span: Span::default(),
+                doc: vec![],
             }),
             TopLevel::Expr(function_call("add", vec![varref("a"), varref("b")])),
             TopLevel::Expr(function_call(
@@ -81,10 +143,11 @@ fn golden_test_module() {
     let src = Cursor::new(src);
     let parse_result = parse_module(src);
 
-    let Ok((next, ast)) = parse_result else {
+    let Ok((next, (ast, errors))) = parse_result else {
         eprintln!("ParseError: {parse_result:?}");
         panic!("Module test failed: Parsing failed");
     };
+    let ast = ast.strip_spans();
 
     for (expr, expected_expr) in ast.top_level.iter().zip(expected.top_level.iter()) {
         if expr != expected_expr {
@@ -97,5 +160,120 @@ fn golden_test_module() {
     }
 
     assert_eq!(ast, expected);
+    assert!(errors.is_empty(), "{errors:?}");
     assert_eq!(next.remainder.trim(), "");
 }
+
+#[test]
+fn module_recovers_after_error() {
+    use mth_ast::StripSpans;
+
+    let src = "a = 1;\nb = (2;\nplot(a);\nc = $;";
+
+    let (next, (ast, errors)) = parse_module(Cursor::new(src)).unwrap();
+
+    assert_eq!(
+        ast.top_level.clone().strip_spans(),
+        vec![
+            TopLevel::Function(Function {
+                name: "a",
+                params: vec![],
+                body: int(1),
+                span: Span::default(),
+                doc: vec![],
+            }),
+            TopLevel::Error(Span::default()),
+            TopLevel::Expr(function_call("plot", vec![varref("a")])),
+            TopLevel::Error(Span::default()),
+        ]
+    );
+    let spans: Vec<_> = ast
+        .top_level
+        .iter()
+        .map(|tl| (tl.span().start..tl.span().end, tl.span().line))
+        .collect();
+    assert_eq!(spans, vec![(0..5, 1), (7..14, 2), (15..22, 3), (24..30, 4)]);
+    assert_eq!(errors.len(), 2);
+    assert_eq!(next.remainder, "");
+}
+
+#[test]
+fn module_error_without_semicolon() {
+    let src = "a = 1;\nplot(a";
+
+    let (next, (ast, errors)) = parse_module(Cursor::new(src)).unwrap();
+
+    let TopLevel::Error(span) = ast.top_level[1] else {
+        panic!("Expected an error node, got {:?}", ast.top_level[1]);
+    };
+    assert_eq!((span.start, span.end), (7, 13));
+    assert_eq!(errors.len(), 1);
+    assert_eq!(next.remainder, "");
+}
+
+#[test]
+fn module_with_comments() {
+    use mth_ast::StripSpans;
+
+    let src =
+        "| line comment\na = 1; | trailing\n|* block |* nested *| still a comment *|\nplot(a);";
+
+    let (next, (ast, errors)) = parse_module(Cursor::new(src)).unwrap();
+
+    assert_eq!(
+        ast.top_level.strip_spans(),
+        vec![
+            TopLevel::Function(Function {
+                name: "a",
+                params: vec![],
+                body: int(1),
+                span: Span::default(),
+                doc: vec![],
+            }),
+            TopLevel::Expr(function_call("plot", vec![varref("a")])),
+        ]
+    );
+    assert!(errors.is_empty());
+    assert_eq!(next.remainder, "");
+}
+
+#[test]
+fn module_attaches_doc_comments() {
+    let src = "|| The answer\n||\n|| to everything\na = 42;\n\
+        || Sum of two numbers\nadd :: int -> int -> int;\nadd(x, y) = x + y;\n\
+        || Lost, not followed by a definition\nplot(a);\nb = 1;";
+
+    let (_, (ast, errors)) = parse_module(Cursor::new(src)).unwrap();
+
+    assert!(errors.is_empty(), "{errors:?}");
+    let docs: Vec<_> = ast
+        .top_level
+        .iter()
+        .filter_map(|tl| match tl {
+            TopLevel::Function(f) => Some((f.name, f.doc.clone())),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        docs,
+        vec![
+            ("a", vec!["The answer", "", "to everything"]),
+            ("add", vec!["Sum of two numbers"]),
+            ("b", vec![]),
+        ]
+    );
+}
+
+#[test]
+fn module_unterminated_block_comment() {
+    let src = "a = 1;\n|* never closed\nb = 2;";
+
+    let (next, (ast, errors)) = parse_module(Cursor::new(src)).unwrap();
+
+    let TopLevel::Error(span) = ast.top_level[1] else {
+        panic!("Expected an error node, got {:?}", ast.top_level[1]);
+    };
+    assert_eq!((span.start, span.end), (7, src.len()));
+    assert_eq!(errors.len(), 1);
+    assert_eq!(next.remainder, "");
+}
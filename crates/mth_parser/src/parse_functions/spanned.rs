@@ -0,0 +1,24 @@
+use parser_lib::types::{FileContext, Parser};
+
+use super::*;
+
+/// Span from `start` to the current position of `src`
+pub fn span_from(start: &FileContext, src: &Cursor) -> Span {
+    Span {
+        start: start.offset,
+        end: src.ctx.offset,
+        line: start.line,
+        col: start.col,
+    }
+}
+
+/// Run `p` and also return the span of the input it consumed, without leading whitespace
+pub fn spanned<'s, O>(p: impl Parser<'s, O>) -> impl Parser<'s, (O, Span)> {
+    move |src| {
+        let (src, ()) = whitespace(src)?;
+        let start = src.ctx.clone();
+        let (src, out) = p(src)?;
+        let span = span_from(&start, &src);
+        Ok((src, (out, span)))
+    }
+}
@@ -1,14 +1,21 @@
+use mth_ast::StripSpans;
+
 use super::*;
 
 /// Helper to assert parser output and remaining input
-fn assert_parses<'s, T: std::fmt::Debug + PartialEq>(
+fn assert_parses<'s, T: std::fmt::Debug + PartialEq + StripSpans>(
     parser: impl Fn(Cursor<'s>) -> PResult<'s, T>,
     input: &'s str,
     expected_val: T,
     expected_rem: &'s str,
 ) {
     let (next, val) = parser(Cursor::new(input)).expect("parse failed");
-    assert_eq!(val, expected_val, "remainder: {}", next.remainder);
+    assert_eq!(
+        val.strip_spans(),
+        expected_val,
+        "remainder: {}",
+        next.remainder
+    );
     assert_eq!(next.remainder, expected_rem);
 }
 
@@ -41,6 +48,8 @@ fn parse_variable_assignment() {
             name: "a",
             params: vec![],
             body: int(1),
+            span: Span::default(),
+            doc: vec![],
         },
         "",
     );
@@ -50,8 +59,10 @@ fn parse_variable_assignment() {
 fn parse_fn_definition() {
     let output = Function {
         name: "add",
-        params: vec![Param("x".into()), Param("y".into())],
+        params: vec![Param::new("x"), Param::new("y")],
         body: function_call("+", vec![varref("x"), varref("y")]),
+        span: Span::default(),
+        doc: vec![],
     };
     // assert_parses(parse_fn_decl, "add(x, y) = x + y", output.clone(), "");
     assert_parses(parse_fn_decl, "add(x, y) = (x + y)", output, "");
@@ -65,6 +76,7 @@ fn parse_type_decl_simple() {
         TypeDecl {
             name: "x",
             params: vec![Type::Int],
+            span: Span::default(),
         },
         "",
     );
@@ -78,6 +90,21 @@ fn parse_type_decl_fn() {
         TypeDecl {
             name: "eq",
             params: vec![Type::Int, Type::Int, Type::Bool],
+            span: Span::default(),
+        },
+        "",
+    );
+}
+
+#[test]
+fn parse_type_decl_number() {
+    assert_parses(
+        parse_type_decl,
+        "f :: number -> number",
+        TypeDecl {
+            name: "f",
+            params: vec![Type::Number, Type::Number],
+            span: Span::default(),
         },
         "",
     );
@@ -107,10 +134,11 @@ fn parse_module_simple() {
         b = 2;
     "#;
 
-    let (_, module) = parse_module(Cursor::new(src)).unwrap();
+    let (_, (module, errors)) = parse_module(Cursor::new(src)).unwrap();
+    assert!(errors.is_empty());
 
     assert_eq!(
-        module,
+        module.strip_spans(),
         Module {
             name: None,
             top_level: vec![
@@ -118,11 +146,15 @@ fn parse_module_simple() {
                     name: "a",
                     params: vec![],
                     body: int(1),
+                    span: Span::default(),
+                    doc: vec![],
                 }),
                 TopLevel::Function(Function {
                     name: "b",
                     params: vec![],
                     body: int(2),
+                    span: Span::default(),
+                    doc: vec![],
                 }),
             ],
         }
@@ -1,18 +1,23 @@
 use super::*;
 
 pub fn parse_type_decl(src: Cursor) -> PResult<TypeDecl> {
-    let (src, name) = parse!(tok(ident), "Could not parse type name", src)?;
+    let (src, ()) = whitespace(src)?;
+    let start = src.ctx.clone();
+
+    let (src, name) = parse!(ident, "Could not parse type name", src)?;
     let (src, _) = parse!(tok(keyword("::")), "Could not find '::'", src)?;
     let (src, params) = parse!(
         delimited1(parse_type, tok(keyword("->"))),
         "Could not parse type params",
         src
     )?;
-    Ok((src, TypeDecl { name, params }))
+    let span = span_from(&start, &src);
+    Ok((src, TypeDecl { name, params, span }))
 }
 
 pub fn parse_type(src: Cursor) -> PResult<Type> {
     pmatch! {src; err = "[parse_type]";
+        tok(keyword("number")), _ => Type::Number;
         tok(keyword("int")), _ => Type::Int;
         tok(keyword("string")), _ => Type::String;
         tok(keyword("bool")), _ => Type::Bool;
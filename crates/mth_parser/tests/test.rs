@@ -1,19 +1,28 @@
-use mth_ast::{Expr, Literal, function_call, int, varref};
+#![allow(clippy::approx_constant)]
+
+use mth_ast::{
+    Expr, FunctionCall, Literal, NamedArg, Span, StripSpans, function_call, int, lambda, varref,
+};
 use mth_parser::parse_functions::expr;
 use parser_lib::cursor::Cursor;
 
 fn assert_expr(input: &str, expected: Expr<'_>, expected_rem: &str) {
     let (next, parsed) = expr(Cursor::new(input)).expect("parse_expr failed");
-    assert_eq!(parsed, expected, "remainder: {:?}", next.remainder);
+    assert_eq!(
+        parsed.strip_spans(),
+        expected,
+        "remainder: {:?}",
+        next.remainder
+    );
     assert_eq!(next.remainder, expected_rem);
 }
 
 fn float(x: f64) -> Expr<'static> {
-    Expr::Literal(Literal::Float(x))
+    Expr::Literal(Literal::Float(x), Span::default())
 }
 
 fn boolean(b: bool) -> Expr<'static> {
-    Expr::Literal(Literal::Bool(b))
+    Expr::Literal(Literal::Bool(b), Span::default())
 }
 
 mod literals {
@@ -358,6 +367,98 @@ mod function_calls {
             "",
         );
     }
+
+    #[test]
+    fn parse_fn_call_with_named_args() {
+        let named = |name, value| NamedArg {
+            name,
+            value,
+            span: Span::default(),
+        };
+        assert_expr(
+            "plot(f, color = \"red\", dash = [4, 2], g)",
+            Expr::FunctionCall(FunctionCall {
+                name: "plot",
+                args: vec![varref("f"), varref("g")],
+                named_args: vec![
+                    named(
+                        "color",
+                        Expr::Literal(Literal::String("red".to_string()), Span::default()),
+                    ),
+                    named("dash", Expr::List(vec![int(4), int(2)], Span::default())),
+                ],
+                is_negated: false,
+                span: Span::default(),
+            }),
+            "",
+        );
+    }
+
+    #[test]
+    fn parse_fn_call_comparison_is_not_named_arg() {
+        assert_expr(
+            "plot(x == y)",
+            function_call(
+                "plot",
+                vec![function_call("==", vec![varref("x"), varref("y")])],
+            ),
+            "",
+        );
+    }
+}
+
+mod lambdas {
+    use super::*;
+
+    #[test]
+    fn parse_lambda_single_param() {
+        assert_expr(
+            "t -> t ^ 2",
+            lambda(&["t"], function_call("^", vec![varref("t"), int(2)])),
+            "",
+        );
+    }
+
+    #[test]
+    fn parse_lambda_param_list() {
+        assert_expr(
+            "(a, b) -> a < b",
+            lambda(
+                &["a", "b"],
+                function_call("<", vec![varref("a"), varref("b")]),
+            ),
+            "",
+        );
+    }
+
+    #[test]
+    fn parse_lambda_no_params() {
+        assert_expr("() -> 1", lambda(&[], int(1)), "");
+    }
+
+    #[test]
+    fn parse_lambda_as_argument() {
+        assert_expr(
+            "plot(t -> sin(t), x)",
+            function_call(
+                "plot",
+                vec![
+                    lambda(&["t"], function_call("sin", vec![varref("t")])),
+                    varref("x"),
+                ],
+            ),
+            "",
+        );
+    }
+
+    #[test]
+    fn parse_parenthesized_is_not_lambda() {
+        assert_expr(
+            "(a) - b",
+            function_call("-", vec![varref("a"), varref("b")]),
+            "",
+        );
+    }
 }
 
 mod complex_expressions {
@@ -469,3 +570,146 @@ mod regression_tests {
         assert_expr("xyz+1", function_call("+", vec![varref("xyz"), int(1)]), "");
     }
 }
+
+mod programs {
+    use mth_ast::TopLevel;
+    use mth_diagnostics::{Span, codes};
+    use mth_parser::parse_program;
+
+    #[test]
+    fn parse_complete_program() {
+        let (module, errors) = parse_program("f(x) = sin(x);\nplot(f);\n");
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(module.top_level.len(), 2);
+    }
+
+    #[test]
+    fn syntax_error_points_at_failure() {
+        let src = "f(x) = x;\nplot(f)";
+        let (_, errors) = parse_program(src);
+        let [err] = errors.as_slice() else {
+            panic!("Expected one error, got {errors:?}");
+        };
+        assert_eq!(err.code, Some(codes::SYNTAX_ERROR));
+        assert_eq!(err.primary_span(), Some(Span::point(src.len())));
+        assert_eq!(err.labels[0].message, "Unexpected EOF, expected ';'");
+    }
+
+    #[test]
+    fn syntax_error_renders_context_as_notes() {
+        let src = "f x -> { x };";
+        let (_, errors) = parse_program(src);
+        let [err] = errors.as_slice() else {
+            panic!("Expected one error, got {errors:?}");
+        };
+        assert_eq!(
+            err.render(src, Some("test.mth")),
+            "error[E0001]: syntax error
+ --> test.mth:1:6
+  |
+1 | f x -> { x };
+  | ------------- in this statement
+  |      ^ Expected '[', found '>'
+  |
+  = note: Couldn't match any subparser
+  = note: Couldn't match any prefix expression"
+        );
+    }
+
+    #[test]
+    fn syntax_error_labels_statement() {
+        let src = "a = 1;\n3 $ 4;\nb = 2;";
+        let (_, errors) = parse_program(src);
+        let statement = errors[0]
+            .labels
+            .iter()
+            .find(|label| !label.primary)
+            .unwrap();
+        assert_eq!(statement.span, Span::new(7, 13));
+        assert_eq!(errors[0].primary_span(), Some(Span::point(9)));
+    }
+
+    #[test]
+    fn keeps_valid_statements() {
+        let src = "f(x) = sin(x;\ng(x) = x;\nplot(g);\nplot(;";
+        let (module, errors) = parse_program(src);
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(
+            module.top_level.as_slice(),
+            [
+                TopLevel::Error(_),
+                TopLevel::Function(_),
+                TopLevel::Expr(_),
+                TopLevel::Error(_)
+            ]
+        ));
+    }
+}
+
+mod spans {
+    use mth_ast::{Expr, FunctionCall, Lambda, TopLevel};
+    use mth_parser::{parse_functions::expr, parse_program};
+    use parser_lib::cursor::Cursor;
+
+    fn range(expr: &Expr) -> (usize, usize) {
+        (expr.span().start, expr.span().end)
+    }
+
+    #[test]
+    fn function_call_and_args() {
+        let (_, parsed) = expr(Cursor::new("  sin(x) + 2.5")).unwrap();
+        let Expr::FunctionCall(FunctionCall { args, .. }) = &parsed else {
+            panic!("Expected a function call, got {parsed:?}");
+        };
+        assert_eq!(range(&parsed), (2, 14));
+        assert_eq!(range(&args[0]), (2, 8));
+        assert_eq!(range(&args[1]), (11, 14));
+
+        let Expr::FunctionCall(FunctionCall { args, .. }) = &args[0] else {
+            panic!("Expected a function call, got {:?}", args[0]);
+        };
+        assert_eq!(range(&args[0]), (6, 7));
+    }
+
+    #[test]
+    fn negation_includes_sign() {
+        let (_, parsed) = expr(Cursor::new("-x")).unwrap();
+        assert_eq!(range(&parsed), (0, 2));
+    }
+
+    #[test]
+    fn implicit_multiplication() {
+        let (_, parsed) = expr(Cursor::new("2 x")).unwrap();
+        assert_eq!(range(&parsed), (0, 3));
+    }
+
+    #[test]
+    fn lambda_and_params() {
+        let (_, parsed) = expr(Cursor::new("(a, b) -> a b")).unwrap();
+        let Expr::Lambda(Lambda { params, body, .. }) = &parsed else {
+            panic!("Expected a lambda, got {parsed:?}");
+        };
+        assert_eq!(range(&parsed), (0, 13));
+        assert_eq!((params[1].1.start, params[1].1.end), (4, 5));
+        assert_eq!(range(body), (10, 13));
+    }
+
+    #[test]
+    fn line_and_column() {
+        let (module, _) = parse_program("a = 1;\n\n  f(x) = x;\nplot(θ -> θ);");
+        let spans: Vec<_> = module
+            .top_level
+            .iter()
+            .map(|tl| {
+                let span = tl.span();
+                (span.start, span.end, span.line, span.col)
+            })
+            .collect();
+        assert_eq!(spans, vec![(0, 5, 1, 1), (10, 18, 3, 3), (20, 34, 4, 1)]);
+
+        let TopLevel::Function(f) = &module.top_level[1] else {
+            panic!("Expected a function, got {:?}", module.top_level[1]);
+        };
+        assert_eq!((f.body.span().line, f.body.span().col), (3, 10));
+    }
+}
@@ -0,0 +1,88 @@
+//! Equations and inequalities, drawn by their distance to the boundary where both sides of
+//! their final comparison are equal, like `draw_equation` in the shader
+
+use mth_common::ops::*;
+use mth_interpreter::{eval_difference, final_comparison};
+
+use crate::{
+    Viewport,
+    raster::{along, dash_at},
+};
+
+/// The difference of the sides of an equation at every pixel of a viewport
+pub(crate) struct Implicit {
+    comparison: u32,
+
+    /// Columns of `values`. Columns and rows are rounded up to even numbers, since the
+    /// derivatives of the shader are taken within blocks of 2x2 pixels
+    width: usize,
+
+    /// Rows of differences from the top, `None` where the evaluation failed
+    values: Vec<Option<f32>>,
+}
+
+impl Implicit {
+    /// Evaluate the equation `program` at every pixel of `viewport`. `None` if it doesn't end
+    /// with a comparison, such equations are drawn where they hold
+    pub(crate) fn new(program: &[Instruction], viewport: &Viewport) -> Option<Self> {
+        let comparison = final_comparison(program)?;
+        let width = (viewport.width as usize + 1) & !1;
+        let height = (viewport.height as usize + 1) & !1;
+
+        let values = (0..height)
+            .flat_map(|py| (0..width).map(move |px| (px, py)))
+            .map(|(px, py)| {
+                let [x, y] = viewport.to_graph(px as f32 + 0.5, py as f32 + 0.5);
+                eval_difference(program, x, y).ok()
+            })
+            .collect();
+        Some(Self {
+            comparison,
+            width,
+            values,
+        })
+    }
+
+    /// Whether the pixel in column `px` and row `py` at the graph coordinates `p` is on a dash
+    /// within `d` pixels of the boundary, and whether the inequality holds there. `None` if the
+    /// evaluation failed
+    pub(crate) fn coverage(
+        &self,
+        px: u32,
+        py: u32,
+        p: [f32; 2],
+        dash: [f32; 2],
+        d: f32,
+        viewport: &Viewport,
+    ) -> Option<[f32; 2]> {
+        let (px, py) = (px as usize, py as usize);
+        let at = |px: usize, py: usize| self.values[py * self.width + px].unwrap_or(f32::NAN);
+        let diff = self.values[py * self.width + px]?;
+
+        // `dpdxFine` and `dpdyFine` differentiate within blocks of 2x2 pixels
+        let (even_x, even_y) = (px & !1, py & !1);
+        // Per pixel, so that the distance is in pixels
+        let gradient = [
+            at(even_x + 1, py) - at(even_x, py),
+            // Rows go down, the y axis up
+            at(px, even_y) - at(px, even_y + 1),
+        ];
+        let length = (gradient[0] * gradient[0] + gradient[1] * gradient[1]).sqrt();
+        let dist = diff.abs() / length.max(1e-30);
+
+        let line = if dist <= d {
+            dash_at(
+                dash,
+                along(viewport.in_pixels(p[0], p[1]), [-gradient[1], gradient[0]]),
+            )
+        } else {
+            0.0
+        };
+        let holds = match self.comparison {
+            OP_LT | OP_LE => diff < 0.0,
+            OP_GT | OP_GE => diff > 0.0,
+            _ => false,
+        };
+        Some([line, if holds { 1.0 } else { 0.0 }])
+    }
+}
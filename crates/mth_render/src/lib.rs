@@ -0,0 +1,98 @@
+//! Rendering of compiled plots without a GPU.
+//!
+//! [`render`] draws the same pixels as `graph_shader.wgsl` for the same viewport, [`render_svg`]
+//! samples the plots into vector paths
+
+mod implicit;
+
+mod raster;
+pub use raster::{Image, render, write_gif};
+
+mod svg;
+pub use svg::render_svg;
+
+#[cfg(test)]
+mod tests;
+
+/// Thickness of lines in pixels, like `STROKE_WIDTH` in the shader
+pub const STROKE_WIDTH: f32 = 1.0;
+
+/// The part of the graph to render, and the size of the output
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    /// Graph coordinates at the center of the output
+    pub center: [f32; 2],
+
+    /// Zoom levels of the x and y axis like in the editor, see [`mth_common::pixel_ratio`]
+    pub zoom: [f64; 2],
+
+    /// Size of the output in pixels
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for Viewport {
+    /// The view of the editor at startup
+    fn default() -> Self {
+        Self {
+            center: [0.0, 0.0],
+            zoom: [1.0; 2],
+            width: 800,
+            height: 600,
+        }
+    }
+}
+
+impl Viewport {
+    /// The viewport of the size `width` by `height` pixels that shows the graph coordinates
+    /// `x` and `y`, each from the smallest to the largest, like a `view` statement
+    pub fn showing(x: [f32; 2], y: [f32; 2], width: u32, height: u32) -> Self {
+        let zoom = |[min, max]: [f32; 2], size: u32| {
+            mth_common::zoom_for_pixel_ratio(f64::from(max - min) / f64::from(size.max(1)))
+        };
+        Self {
+            center: [(x[0] + x[1]) * 0.5, (y[0] + y[1]) * 0.5],
+            zoom: [zoom(x, width), zoom(y, height)],
+            width,
+            height,
+        }
+    }
+
+    /// Width and height of a pixel in graph units
+    pub fn pixel_ratio(&self) -> [f32; 2] {
+        self.zoom.map(|zoom| mth_common::pixel_ratio(zoom) as f32)
+    }
+
+    /// The graph coordinates `(x, y)` in pixels from the origin, where distances are the same
+    /// along both axes, like `to_pixels` in the shader
+    pub(crate) fn in_pixels(&self, x: f32, y: f32) -> [f32; 2] {
+        let [rx, ry] = self.pixel_ratio();
+        [x / rx, y / ry]
+    }
+
+    /// Offset of the screen position `(px, py)` from the center of the output, in graph units
+    /// but with y pointing down
+    fn scaled(&self, px: f32, py: f32) -> [f32; 2] {
+        let [rx, ry] = self.pixel_ratio();
+        [
+            (px - self.width as f32 * 0.5) * rx,
+            (py - self.height as f32 * 0.5) * ry,
+        ]
+    }
+
+    /// Graph coordinates of the screen position `(px, py)`, measured in pixels from the top
+    /// left corner
+    pub fn to_graph(&self, px: f32, py: f32) -> [f32; 2] {
+        let [sx, sy] = self.scaled(px, py);
+        [sx + self.center[0], self.center[1] - sy]
+    }
+
+    /// Screen position of the graph coordinates `(x, y)`, the inverse of [`Viewport::to_graph`]
+    pub fn to_screen(&self, x: f32, y: f32) -> [f32; 2] {
+        let [rx, ry] = self.pixel_ratio();
+        [
+            (x - self.center[0]) / rx + self.width as f32 * 0.5,
+            (self.center[1] - y) / ry + self.height as f32 * 0.5,
+        ]
+    }
+}
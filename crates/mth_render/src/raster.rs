@@ -0,0 +1,374 @@
+use std::io::{self, Write};
+
+use mth_common::{
+    CURVE_SEGMENTS, PLOT_TYPE_EQUATION, PLOT_TYPE_FN_GRAPH, PLOT_TYPE_INVERSE,
+    PLOT_TYPE_PARAMETRIC, PLOT_TYPE_POLAR, ops::Instruction, plot_desc::PlotDesc,
+};
+use mth_interpreter::{Plot, curve_point, eval_function, plots};
+
+use crate::{STROKE_WIDTH, Viewport, implicit::Implicit};
+
+/// Color of pixels whose plots failed to evaluate
+const ERROR_COLOR: [f32; 4] = [1.0, 0.0, 1.0, 1.0];
+
+/// Speed of the color quantization of GIF frames, from 1 (best) to 30 (fastest)
+const GIF_QUANTIZATION_SPEED: i32 = 10;
+
+/// An RGBA image with 8 bits per channel
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+
+    /// Rows of pixels from the top, 4 bytes per pixel
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    /// The color of the pixel in column `x` and row `y`
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = (y * self.width + x) as usize * 4;
+        self.pixels[i..i + 4].try_into().unwrap()
+    }
+
+    pub fn write_png(&self, w: impl Write) -> Result<(), png::EncodingError> {
+        let mut encoder = png::Encoder::new(w, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+        writer.finish()
+    }
+}
+
+/// Encode `frames`, which all have the size of the first one, as a GIF that loops forever and
+/// shows `fps` frames per second. Each frame is reduced to 256 colors
+pub fn write_gif(frames: &[Image], fps: f32, w: impl Write) -> Result<(), gif::EncodingError> {
+    let (width, height) = frames
+        .first()
+        .map_or((0, 0), |image| (image.width, image.height));
+    let too_large = || {
+        gif::EncodingError::Io(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "GIFs are at most 65535 pixels wide and high",
+        ))
+    };
+    let width = u16::try_from(width).map_err(|_| too_large())?;
+    let height = u16::try_from(height).map_err(|_| too_large())?;
+
+    let mut encoder = gif::Encoder::new(w, width, height, &[])?;
+    encoder.set_repeat(gif::Repeat::Infinite)?;
+    // In hundredths of a second
+    let delay = (100.0 / fps).round() as u16;
+    for image in frames {
+        let mut pixels = image.pixels.clone();
+        let mut frame =
+            gif::Frame::from_rgba_speed(width, height, &mut pixels, GIF_QUANTIZATION_SPEED);
+        frame.delay = delay;
+        encoder.write_frame(&frame)?;
+    }
+    Ok(())
+}
+
+/// Draw the plots of a compiled module into an image, pixel by pixel like `fs_main` in the
+/// shader
+pub fn render(instructions: &[Instruction], plot_descs: &[PlotDesc], viewport: &Viewport) -> Image {
+    let plots: Vec<(Plot, &PlotDesc)> = plots(plot_descs).zip(plot_descs).collect();
+    // In pixels
+    let d = STROKE_WIDTH;
+
+    let prepared: Vec<Prepared> = plots
+        .iter()
+        .map(|(plot, desc)| prepare(instructions, plot, desc, d * desc.width, viewport))
+        .collect();
+
+    let mut pixels = Vec::with_capacity((viewport.width * viewport.height) as usize * 4);
+    for py in 0..viewport.height {
+        for px in 0..viewport.width {
+            let (fx, fy) = (px as f32 + 0.5, py as f32 + 0.5);
+
+            // Viewport origin indicator
+            let (cx, cy) = (
+                fx - viewport.width as f32 * 0.5,
+                fy - viewport.height as f32 * 0.5,
+            );
+            let color = if cx.abs() < d && cy.abs() < d {
+                [0.5, 0.5, 0.5, 1.0]
+            } else {
+                let p = viewport.to_graph(fx, fy);
+                draw_graph(instructions, &plots, &prepared, [px, py], p, d, viewport)
+            };
+            pixels.extend(color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8));
+        }
+    }
+
+    Image {
+        width: viewport.width,
+        height: viewport.height,
+        pixels,
+    }
+}
+
+/// What is computed once per plot rather than per pixel
+enum Prepared {
+    /// Function graphs only depend on the column, inverse function graphs on the row. A value
+    /// for every column or row, and one more for the neighbour of the last one
+    Samples(Vec<f32>),
+
+    /// The coverage of every pixel by a parametric or polar curve, see [`curve_pixels`].
+    /// `None` if the curve failed to evaluate
+    Curve(Option<Vec<[f32; 2]>>),
+
+    /// Equations and inequalities, see [`Implicit`]
+    Equation(Implicit),
+
+    /// Other conditions are evaluated per pixel
+    Nothing,
+}
+
+fn prepare(
+    instructions: &[Instruction],
+    plot: &Plot,
+    desc: &PlotDesc,
+    d: f32,
+    viewport: &Viewport,
+) -> Prepared {
+    match plot.type_id {
+        PLOT_TYPE_FN_GRAPH => Prepared::Samples(
+            (0..=viewport.width)
+                .map(|px| {
+                    let [x, _] = viewport.to_graph(px as f32 + 0.5, 0.0);
+                    eval_function(instructions, plot.offset, plot.len, x, 0.0)
+                })
+                .collect(),
+        ),
+        PLOT_TYPE_INVERSE => Prepared::Samples(
+            (0..=viewport.height)
+                .map(|py| {
+                    let [_, y] = viewport.to_graph(0.0, py as f32 + 0.5);
+                    eval_function(instructions, plot.offset, plot.len, 0.0, y)
+                })
+                .collect(),
+        ),
+        PLOT_TYPE_PARAMETRIC | PLOT_TYPE_POLAR => {
+            Prepared::Curve(curve_pixels(instructions, plot, desc, d, viewport))
+        }
+        PLOT_TYPE_EQUATION => instructions
+            .get(plot.offset..plot.offset + plot.len)
+            .and_then(|program| Implicit::new(program, viewport))
+            .map_or(Prepared::Nothing, Prepared::Equation),
+        _ => Prepared::Nothing,
+    }
+}
+
+fn draw_graph(
+    instructions: &[Instruction],
+    plots: &[(Plot, &PlotDesc)],
+    prepared: &[Prepared],
+    [px, py]: [u32; 2],
+    [x, y]: [f32; 2],
+    d: f32,
+    viewport: &Viewport,
+) -> [f32; 4] {
+    let [rx, ry] = viewport.pixel_ratio();
+    let q = viewport.in_pixels(x, y);
+
+    // x and y axis
+    let mut color = if q[0].abs() < d || q[1].abs() < d {
+        [0.3; 3]
+    } else {
+        [0.0; 3]
+    };
+
+    for ((plot, desc), prepared) in plots.iter().zip(prepared) {
+        let d = d * desc.width;
+        let [line, area] = match (plot.type_id, prepared) {
+            (PLOT_TYPE_FN_GRAPH, Prepared::Samples(columns)) => {
+                on_curve(columns, px as usize, 1.0, ry, q, desc.dash, d)
+            }
+            (PLOT_TYPE_INVERSE, Prepared::Samples(rows)) => {
+                // Rows go down, the y axis up
+                on_curve(rows, py as usize, -1.0, rx, [q[1], q[0]], desc.dash, d)
+            }
+            (PLOT_TYPE_EQUATION, Prepared::Equation(implicit)) => {
+                match implicit.coverage(px, py, [x, y], desc.dash, d, viewport) {
+                    Some(coverage) => coverage,
+                    None => return ERROR_COLOR,
+                }
+            }
+            (PLOT_TYPE_EQUATION, _) => {
+                let holds = eval_function(instructions, plot.offset, plot.len, x, y);
+                if holds < 0.0 {
+                    return ERROR_COLOR;
+                }
+                [0.0, holds]
+            }
+            (_, Prepared::Curve(Some(pixels))) => pixels[(py * viewport.width + px) as usize],
+            _ => return ERROR_COLOR,
+        };
+
+        let alpha = line.max(desc.fill * area);
+        color = std::array::from_fn(|i| color[i] + (desc.color[i] - color[i]) * alpha);
+    }
+
+    [color[0], color[1], color[2], 1.0]
+}
+
+/// 1.0 on the dashes of a line, `s` pixels along it, like `dash_at` in the shader
+pub(crate) fn dash_at([on, off]: [f32; 2], s: f32) -> f32 {
+    if off <= 0.0 {
+        return 1.0;
+    }
+    let period = on + off;
+    if s - (s / period).floor() * period <= on {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// Position along a line through `q` in the direction `tangent`, all in pixels, like `along`
+/// in the shader
+pub(crate) fn along(q: [f32; 2], tangent: [f32; 2]) -> f32 {
+    let length = (tangent[0] * tangent[0] + tangent[1] * tangent[1])
+        .sqrt()
+        .max(1e-30);
+    (q[0] * tangent[0] + q[1] * tangent[1]) / length
+}
+
+/// Whether the point `(x, y)` in pixels from the origin, in column `px`, is on a dash within
+/// `d` pixels of the curve, and whether it is between the curve and the axis, like
+/// `is_on_curve` in the shader. `step` is the distance in pixels from one column to the next
+/// and `ratio` the size of a pixel along the values in graph units. With the rows of an
+/// inverse function graph instead of the columns and `x` and `y` swapped, like
+/// `is_on_inverse_curve`
+fn on_curve(
+    column: &[f32],
+    px: usize,
+    step: f32,
+    ratio: f32,
+    [x, y]: [f32; 2],
+    dash: [f32; 2],
+    d: f32,
+) -> [f32; 2] {
+    let curve_y = column[px] / ratio;
+
+    // `dpdxFine` differentiates within pairs of columns
+    let even = px & !1;
+    let dy = (column[even + 1] - column[even]) / ratio / step;
+
+    let dist = (y - curve_y).abs() / (1.0 + dy * dy).sqrt();
+    let line = if dist <= d {
+        dash_at(dash, along([x, y], [1.0, dy]))
+    } else {
+        0.0
+    };
+    let area = (0.0 <= y && y <= curve_y) || (curve_y <= y && y <= 0.0);
+    [line, if area { 1.0 } else { 0.0 }]
+}
+
+/// For every pixel, whether it is on a dash within `d` pixels of the polyline through the
+/// samples of a parametric or polar curve, and whether it is inside the closed polyline, like
+/// `is_on_parametric_curve` in the shader. Only the pixels around each segment are tested
+/// for the line, and the inside is only computed when the curve is filled
+fn curve_pixels(
+    instructions: &[Instruction],
+    plot: &Plot,
+    desc: &PlotDesc,
+    d: f32,
+    viewport: &Viewport,
+) -> Option<Vec<[f32; 2]>> {
+    let points = (0..=CURVE_SEGMENTS)
+        .map(|i| curve_point(instructions, plot, i))
+        .collect::<Result<Vec<_>, _>>()
+        .ok()?;
+    let is_finite = |p: &[f32; 2]| p.iter().all(|c| c.is_finite());
+    let length = |a: [f32; 2], b: [f32; 2]| (b[0] - a[0]).hypot(b[1] - a[1]);
+
+    let (width, height) = (viewport.width as f32, viewport.height as f32);
+    let margin = d + 1.0;
+
+    // Distance to the closest segment and the length along the curve there
+    let mut closest = vec![(f32::MAX, 0.0); (viewport.width * viewport.height) as usize];
+    let mut curve_length = 0.0;
+    for segment in points.windows(2) {
+        let [a, b] = [segment[0], segment[1]];
+        if !(is_finite(&a) && is_finite(&b)) {
+            continue;
+        }
+
+        let [ax, ay] = viewport.to_screen(a[0], a[1]);
+        let [bx, by] = viewport.to_screen(b[0], b[1]);
+        // Measured in pixels
+        let (a, b) = (
+            viewport.in_pixels(a[0], a[1]),
+            viewport.in_pixels(b[0], b[1]),
+        );
+        let x_range = (ax.min(bx) - margin).floor().max(0.0) as u32
+            ..(ax.max(bx) + margin).ceil().min(width) as u32;
+        for py in (ay.min(by) - margin).floor().max(0.0) as u32
+            ..(ay.max(by) + margin).ceil().min(height) as u32
+        {
+            for px in x_range.clone() {
+                let [x, y] = viewport.to_graph(px as f32 + 0.5, py as f32 + 0.5);
+                let p = viewport.in_pixels(x, y);
+                let h = closest_on_segment(p, a, b);
+                let dist = length(p, [a[0] + (b[0] - a[0]) * h, a[1] + (b[1] - a[1]) * h]);
+                let pixel = &mut closest[(py * viewport.width + px) as usize];
+                if dist < pixel.0 {
+                    *pixel = (dist, curve_length + h * length(a, b));
+                }
+            }
+        }
+        curve_length += length(a, b);
+    }
+
+    let mut pixels: Vec<[f32; 2]> = closest
+        .into_iter()
+        .map(|(dist, s)| {
+            let line = if dist <= d {
+                dash_at(desc.dash, s)
+            } else {
+                0.0
+            };
+            [line, 0.0]
+        })
+        .collect();
+
+    if desc.fill > 0.0 {
+        // The segments crossed by a ray to the right of each pixel, row by row
+        let closing = [points[points.len() - 1], points[0]];
+        let segments: Vec<[[f32; 2]; 2]> = points
+            .windows(2)
+            .map(|segment| [segment[0], segment[1]])
+            .chain([closing])
+            .filter(|[a, b]| is_finite(a) && is_finite(b))
+            .collect();
+        for py in 0..viewport.height {
+            let [_, y] = viewport.to_graph(0.0, py as f32 + 0.5);
+            let mut crossings: Vec<f32> = segments
+                .iter()
+                .filter(|[a, b]| (a[1] > y) != (b[1] > y))
+                .map(|[a, b]| a[0] + (y - a[1]) * (b[0] - a[0]) / (b[1] - a[1]))
+                .collect();
+            crossings.sort_by(f32::total_cmp);
+            for px in 0..viewport.width {
+                let [x, _] = viewport.to_graph(px as f32 + 0.5, 0.0);
+                let right = crossings.len() - crossings.partition_point(|&c| c <= x);
+                if right % 2 == 1 {
+                    pixels[(py * viewport.width + px) as usize][1] = 1.0;
+                }
+            }
+        }
+    }
+    Some(pixels)
+}
+
+/// Where the point of the segment from `a` to `b` closest to `p` is, from 0.0 at `a` to 1.0
+/// at `b`, like `closest_on_segment` in the shader
+fn closest_on_segment(p: [f32; 2], a: [f32; 2], b: [f32; 2]) -> f32 {
+    let pa = [p[0] - a[0], p[1] - a[1]];
+    let ba = [b[0] - a[0], b[1] - a[1]];
+    let dot = |u: [f32; 2], v: [f32; 2]| u[0] * v[0] + u[1] * v[1];
+    (dot(pa, ba) / dot(ba, ba).max(1e-30)).clamp(0.0, 1.0)
+}
@@ -0,0 +1,273 @@
+use std::fmt::Write as _;
+
+use mth_common::{
+    CURVE_SEGMENTS, PLOT_TYPE_EQUATION, PLOT_TYPE_FN_GRAPH, PLOT_TYPE_INVERSE,
+    PLOT_TYPE_PARAMETRIC, PLOT_TYPE_POLAR, ops::Instruction, plot_desc::PlotDesc,
+};
+use mth_interpreter::{Plot, curve_point, eval, plots};
+
+use crate::{STROKE_WIDTH, Viewport, implicit::Implicit};
+
+/// Samples per pixel along function graphs
+const SAMPLES_PER_PIXEL: u32 = 2;
+
+/// Draw the plots of a compiled module as an SVG document.
+///
+/// Function graphs become paths through samples of the function, which are split where it
+/// can't be evaluated or jumps by more than the height of the viewport, and likewise for
+/// inverse function graphs along the rows. Parametric and polar curves are paths through
+/// the same samples as on the GPU. Equations and inequalities are filled pixel rows, so the
+/// result has the resolution of the viewport there. Filled areas are translucent shapes below
+/// the lines
+pub fn render_svg(
+    instructions: &[Instruction],
+    plot_descs: &[PlotDesc],
+    viewport: &Viewport,
+) -> String {
+    let (width, height) = (viewport.width as f32, viewport.height as f32);
+    let stroke = 2.0 * STROKE_WIDTH;
+
+    let mut out = String::new();
+    _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+    );
+    _ = writeln!(out, r#"<rect width="100%" height="100%" fill="black"/>"#);
+
+    // Axes
+    let [origin_x, origin_y] = viewport.to_screen(0.0, 0.0);
+    if (0.0..=width).contains(&origin_x) {
+        _ = writeln!(
+            out,
+            r##"<line x1="{origin_x:.2}" y1="0" x2="{origin_x:.2}" y2="{height}" stroke="#4d4d4d" stroke-width="{stroke}"/>"##
+        );
+    }
+    if (0.0..=height).contains(&origin_y) {
+        _ = writeln!(
+            out,
+            r##"<line x1="0" y1="{origin_y:.2}" x2="{width}" y2="{origin_y:.2}" stroke="#4d4d4d" stroke-width="{stroke}"/>"##
+        );
+    }
+
+    for (plot, desc) in plots(plot_descs).zip(plot_descs) {
+        let program = instructions.get(plot.offset..plot.offset + plot.len);
+        let Some(program) = program else {
+            continue;
+        };
+        let lines = match plot.type_id {
+            PLOT_TYPE_FN_GRAPH => graph_lines(program, viewport),
+            PLOT_TYPE_INVERSE => inverse_graph_lines(program, viewport),
+            PLOT_TYPE_PARAMETRIC | PLOT_TYPE_POLAR => curve_lines(instructions, &plot, viewport),
+            PLOT_TYPE_EQUATION => {
+                out += &equation_rects(program, desc, viewport);
+                continue;
+            }
+            _ => continue,
+        };
+        if lines.is_empty() {
+            continue;
+        }
+
+        let color = hex(desc.color);
+        if desc.fill > 0.0 {
+            let area = match plot.type_id {
+                PLOT_TYPE_FN_GRAPH => area_path(&lines, |[sx, _]| [sx, origin_y]),
+                PLOT_TYPE_INVERSE => area_path(&lines, |[_, sy]| [origin_x, sy]),
+                _ => path(&lines),
+            };
+            _ = writeln!(
+                out,
+                r#"<path d="{area}" fill="{color}" fill-opacity="{}" fill-rule="evenodd" stroke="none"/>"#,
+                desc.fill
+            );
+        }
+
+        let mut dash = String::new();
+        if desc.dash[1] > 0.0 {
+            dash = format!(r#" stroke-dasharray="{} {}""#, desc.dash[0], desc.dash[1]);
+        }
+        _ = writeln!(
+            out,
+            r#"<path d="{}" fill="none" stroke="{color}" stroke-width="{}"{dash}/>"#,
+            path(&lines),
+            stroke * desc.width
+        );
+    }
+
+    out += "</svg>\n";
+    out
+}
+
+/// Connected points of a plot in screen coordinates
+type Line = Vec<[f32; 2]>;
+
+/// Path data through the points of `lines`
+fn path(lines: &[Line]) -> String {
+    let mut path = String::new();
+    for line in lines {
+        for (i, [sx, sy]) in line.iter().enumerate() {
+            let command = if i == 0 { 'M' } else { 'L' };
+            _ = write!(path, "{command}{sx:.2},{sy:.2} ");
+        }
+    }
+    path.trim_end().to_string()
+}
+
+/// Path data of the areas between `lines` and an axis, `to_axis` projects a point onto it
+fn area_path(lines: &[Line], to_axis: impl Fn([f32; 2]) -> [f32; 2]) -> String {
+    lines
+        .iter()
+        .map(|line| {
+            let first = to_axis(line[0]);
+            let last = to_axis(line[line.len() - 1]);
+            let closed = [first]
+                .into_iter()
+                .chain(line.iter().copied())
+                .chain([last]);
+            path(&[closed.collect()]) + " Z"
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Samples of the function graph `program`
+fn graph_lines(program: &[Instruction], viewport: &Viewport) -> Vec<Line> {
+    let height = viewport.height as f32;
+    let mut lines: Vec<Line> = Vec::new();
+    let mut prev: Option<f32> = None;
+
+    for i in 0..=viewport.width * SAMPLES_PER_PIXEL {
+        let sx = i as f32 / SAMPLES_PER_PIXEL as f32;
+        let [x, _] = viewport.to_graph(sx, 0.0);
+        let sy = match eval(program, x, 0.0) {
+            Ok(y) if y.is_finite() => viewport.to_screen(x, y)[1].clamp(-height, 2.0 * height),
+            _ => {
+                prev = None;
+                continue;
+            }
+        };
+
+        match (prev, lines.last_mut()) {
+            (Some(prev_sy), Some(line)) if (sy - prev_sy).abs() <= height => line.push([sx, sy]),
+            _ => lines.push(vec![[sx, sy]]),
+        }
+        prev = Some(sy);
+    }
+
+    lines
+}
+
+/// Samples of the inverse function graph `program`, one per row like [`graph_lines`] per
+/// column
+fn inverse_graph_lines(program: &[Instruction], viewport: &Viewport) -> Vec<Line> {
+    let width = viewport.width as f32;
+    let mut lines: Vec<Line> = Vec::new();
+    let mut prev: Option<f32> = None;
+
+    for i in 0..=viewport.height * SAMPLES_PER_PIXEL {
+        let sy = i as f32 / SAMPLES_PER_PIXEL as f32;
+        let [_, y] = viewport.to_graph(0.0, sy);
+        let sx = match eval(program, 0.0, y) {
+            Ok(x) if x.is_finite() => viewport.to_screen(x, y)[0].clamp(-width, 2.0 * width),
+            _ => {
+                prev = None;
+                continue;
+            }
+        };
+
+        match (prev, lines.last_mut()) {
+            (Some(prev_sx), Some(line)) if (sx - prev_sx).abs() <= width => line.push([sx, sy]),
+            _ => lines.push(vec![[sx, sy]]),
+        }
+        prev = Some(sx);
+    }
+
+    lines
+}
+
+/// The samples of a parametric or polar curve. It is split at points that aren't finite or
+/// lie far outside of the viewport
+fn curve_lines(instructions: &[Instruction], plot: &Plot, viewport: &Viewport) -> Vec<Line> {
+    let (width, height) = (viewport.width as f32, viewport.height as f32);
+    let mut lines: Vec<Line> = Vec::new();
+    let mut connected = false;
+
+    for i in 0..=CURVE_SEGMENTS {
+        let point = curve_point(instructions, plot, i)
+            .ok()
+            .map(|[x, y]| viewport.to_screen(x, y))
+            .filter(|[sx, sy]| {
+                (-width..=2.0 * width).contains(sx) && (-height..=2.0 * height).contains(sy)
+            });
+        let Some(point) = point else {
+            connected = false;
+            continue;
+        };
+
+        match lines.last_mut() {
+            Some(line) if connected => line.push(point),
+            _ => lines.push(vec![point]),
+        }
+        connected = true;
+    }
+
+    lines
+}
+
+/// One rect per run of pixels in a row with the same opacity. Equations and inequalities
+/// are drawn like by [`render`](crate::render), other conditions are filled where they hold
+fn equation_rects(program: &[Instruction], desc: &PlotDesc, viewport: &Viewport) -> String {
+    let implicit = Implicit::new(program, viewport);
+    let d = STROKE_WIDTH * desc.width;
+    let opacity = |px: u32, py: u32| {
+        let p = viewport.to_graph(px as f32 + 0.5, py as f32 + 0.5);
+        let [line, area] = match &implicit {
+            Some(implicit) => implicit
+                .coverage(px, py, p, desc.dash, d, viewport)
+                .unwrap_or([0.0, 0.0]),
+            None => {
+                let holds = eval(program, p[0], p[1]).is_ok_and(|value| value >= 0.5);
+                [0.0, if holds { 1.0 } else { 0.0 }]
+            }
+        };
+        line.max(desc.fill * area)
+    };
+
+    let color = hex(desc.color);
+    let mut out = String::new();
+    for py in 0..viewport.height {
+        let mut run: Option<(u32, f32)> = None;
+        for px in 0..=viewport.width {
+            let current = if px < viewport.width {
+                opacity(px, py)
+            } else {
+                0.0
+            };
+
+            match run {
+                Some((_, run_opacity)) if run_opacity == current => {}
+                _ => {
+                    if let Some((start, run_opacity)) = run {
+                        let mut fill_opacity = String::new();
+                        if run_opacity < 1.0 {
+                            fill_opacity = format!(r#" fill-opacity="{run_opacity}""#);
+                        }
+                        _ = writeln!(
+                            out,
+                            r#"<rect x="{start}" y="{py}" width="{}" height="1" fill="{color}"{fill_opacity}/>"#,
+                            px - start,
+                        );
+                    }
+                    run = (current > 0.0).then_some((px, current));
+                }
+            }
+        }
+    }
+    out
+}
+
+/// The color in hex notation, like `#ff8000`
+fn hex(color: [f32; 3]) -> String {
+    let [r, g, b] = color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
@@ -0,0 +1,356 @@
+use code_generator::{CompiledModule, compile_source};
+
+use crate::{Image, Viewport, render, render_svg, write_gif};
+
+/// 100 x 100 pixels, 0.08 graph units per pixel
+const VIEWPORT: Viewport = Viewport {
+    center: [0.0, 0.0],
+    zoom: [-4.0; 2],
+    width: 100,
+    height: 100,
+};
+
+const BLACK: [u8; 4] = [0, 0, 0, 255];
+const WHITE: [u8; 4] = [255, 255, 255, 255];
+const AXIS: [u8; 4] = [77, 77, 77, 255];
+const REGION: [u8; 4] = [102, 102, 102, 255];
+
+fn render_src(src: &str) -> Image {
+    let CompiledModule {
+        instructions,
+        plot_descs,
+        ..
+    } = compile_source(src);
+    render(&instructions, &plot_descs, &VIEWPORT)
+}
+
+#[test]
+fn test_viewport_mapping() {
+    let viewport = Viewport {
+        center: [1.0, -2.0],
+        ..VIEWPORT
+    };
+    assert_eq!(viewport.pixel_ratio(), [0.08; 2]);
+
+    // y points up in the graph, down on the screen
+    assert_eq!(viewport.to_graph(50.0, 50.0), [1.0, -2.0]);
+    assert_eq!(viewport.to_graph(0.0, 0.0), [-3.0, 2.0]);
+    assert_eq!(viewport.to_screen(-3.0, 2.0), [0.0, 0.0]);
+    assert_eq!(viewport.to_screen(5.0, -6.0), [100.0, 100.0]);
+}
+
+#[test]
+fn test_viewport_showing_ranges() {
+    let viewport = Viewport::showing([-4.0, 4.0], [-2.0, 2.0], 100, 100);
+    assert_eq!(viewport.pixel_ratio(), [0.08, 0.04]);
+    assert_eq!(viewport.to_graph(0.0, 0.0), [-4.0, 2.0]);
+    assert_eq!(viewport.to_graph(100.0, 100.0), [4.0, -2.0]);
+}
+
+#[test]
+fn test_render_stretched_axes() {
+    // The unit circle is twice as high as wide, with lines as thin as at any scale. Its top
+    // and bottom fall between two rows
+    let CompiledModule {
+        instructions,
+        plot_descs,
+        ..
+    } = compile_source("plot(x^2 + y^2 == 1);");
+    let viewport = Viewport::showing([-4.0, 4.0], [-2.0, 2.0], 100, 100);
+    let image = render(&instructions, &plot_descs, &viewport);
+    let row: Vec<_> = (0..100)
+        .filter(|px| image.pixel(*px, 50) == WHITE)
+        .collect();
+    assert_eq!(row, vec![37, 62]);
+    let column: Vec<_> = (0..100)
+        .filter(|py| image.pixel(50, *py) == WHITE)
+        .collect();
+    assert_eq!(column, vec![24, 25, 74, 75]);
+
+    // Function graphs too, y = 1 is between the same rows as the top of the circle
+    let CompiledModule {
+        instructions,
+        plot_descs,
+        ..
+    } = compile_source("plot(1);");
+    let image = render(&instructions, &plot_descs, &viewport);
+    let column: Vec<_> = (0..100)
+        .filter(|py| image.pixel(10, *py) == WHITE)
+        .collect();
+    assert_eq!(column, vec![24, 25]);
+}
+
+#[test]
+fn test_render_axes_and_origin_indicator() {
+    let image = render_src("");
+    assert_eq!((image.width, image.height), (100, 100));
+    assert_eq!(image.pixel(50, 50), [128, 128, 128, 255]);
+    assert_eq!(image.pixel(10, 50), AXIS);
+    assert_eq!(image.pixel(50, 90), AXIS);
+    assert_eq!(image.pixel(10, 10), BLACK);
+}
+
+#[test]
+fn test_render_function_graph() {
+    // y = 1 is 12.5 pixels above the center
+    let image = render_src("plot(1);");
+    assert_eq!(image.pixel(10, 37), WHITE);
+    assert_eq!(image.pixel(90, 37), WHITE);
+    assert_eq!(image.pixel(10, 30), BLACK);
+    assert_eq!(image.pixel(10, 40), BLACK);
+}
+
+#[test]
+fn test_render_steep_graph_uses_distance_to_curve() {
+    // Without the slope correction, only one pixel per column would be on a steep line
+    let image = render_src("plot(10x);");
+    let row: Vec<_> = (0..100).map(|px| image.pixel(px, 20)).collect();
+    assert!(row.contains(&WHITE));
+    assert_eq!(image.pixel(10, 10), BLACK);
+}
+
+#[test]
+fn test_render_inequality() {
+    // The region where y < x is shaded, its boundary drawn like a curve
+    let image = render_src("plot(y < x);");
+    assert_eq!(image.pixel(80, 80), REGION);
+    assert_eq!(image.pixel(70, 90), REGION);
+    assert_eq!(image.pixel(20, 80), WHITE);
+    assert_eq!(image.pixel(80, 20), WHITE);
+    assert_eq!(image.pixel(20, 20), BLACK);
+    assert_eq!(image.pixel(40, 10), BLACK);
+}
+
+#[test]
+fn test_render_equation_as_contour() {
+    // The unit circle, 12.5 pixels around the center
+    let image = render_src("plot(x^2 + y^2 == 1);");
+    let row: Vec<_> = (0..100)
+        .filter(|px| image.pixel(*px, 50) == WHITE)
+        .collect();
+    assert_eq!(row, vec![37, 62]);
+    assert_eq!(image.pixel(50, 37), WHITE);
+
+    // The width of the line doesn't depend on the gradient of the sides
+    let white = |image: &Image| image.pixels.chunks(4).filter(|p| *p == WHITE).count();
+    let scaled = render_src("plot(100 * x^2 == 100 - 100 * y^2);");
+    assert_eq!(white(&scaled), white(&image));
+}
+
+#[test]
+fn test_render_other_conditions_where_they_hold() {
+    let image = render_src("plot(y < x and x < 1);");
+    assert_eq!(image.pixel(55, 80), REGION);
+    assert_eq!(image.pixel(80, 80), BLACK);
+}
+
+#[test]
+fn test_render_polar_curve() {
+    // A circle with radius 2, 25 pixels around the center
+    let image = render_src("plot_polar(θ -> 2);");
+    assert_eq!(image.pixel(75, 50), WHITE);
+    assert_eq!(image.pixel(50, 25), WHITE);
+    assert_eq!(image.pixel(25, 50), WHITE);
+    assert_eq!(image.pixel(60, 40), BLACK);
+    assert_eq!(image.pixel(80, 80), BLACK);
+}
+
+#[test]
+fn test_render_parametric_curve_range() {
+    // The segment from (-1, 1) to (1, 1) of the line y = 1
+    let image = render_src("plot_parametric(t -> t, t -> 1, -1, 1);");
+    assert_eq!(image.pixel(45, 37), WHITE);
+    assert_eq!(image.pixel(55, 37), WHITE);
+    assert_eq!(image.pixel(20, 37), BLACK);
+}
+
+#[test]
+fn test_render_inverse_graph() {
+    // x = 1 is 12.5 pixels right of the center, x = y^2 is steep near the x axis
+    let image = render_src("plot_inverse(1, s -> s^2);");
+    assert_eq!(image.pixel(62, 10), WHITE);
+    assert_eq!(image.pixel(62, 90), WHITE);
+    assert_eq!(image.pixel(70, 10), BLACK);
+    let row: Vec<_> = (0..100).map(|px| image.pixel(px, 50 - 6)).collect();
+    assert!(row.contains(&WHITE));
+}
+
+#[test]
+fn test_write_png() {
+    let image = render_src("plot(y < x);");
+    let mut png = Vec::new();
+    image.write_png(&mut png).unwrap();
+
+    let mut reader = png::Decoder::new(std::io::Cursor::new(png))
+        .read_info()
+        .unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size().unwrap()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    assert_eq!((info.width, info.height), (100, 100));
+    assert_eq!(pixels, image.pixels);
+}
+
+#[test]
+fn test_write_gif() {
+    let frames = [render_src("plot(x);"), render_src("plot(-x);")];
+    let mut gif = Vec::new();
+    write_gif(&frames, 20.0, &mut gif).unwrap();
+
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(std::io::Cursor::new(gif)).unwrap();
+    assert_eq!((decoder.width(), decoder.height()), (100, 100));
+    assert_eq!(decoder.repeat(), gif::Repeat::Infinite);
+
+    // The few colors of a graph survive the reduction to a palette
+    for image in &frames {
+        let frame = decoder.read_next_frame().unwrap().unwrap();
+        assert_eq!(frame.delay, 5);
+        assert_eq!(&*frame.buffer, image.pixels);
+    }
+    assert!(decoder.read_next_frame().unwrap().is_none());
+}
+
+#[test]
+fn test_svg_graph_is_split_at_poles() {
+    let CompiledModule {
+        instructions,
+        plot_descs,
+        ..
+    } = compile_source("plot(1 / x);");
+    let svg = render_svg(&instructions, &plot_descs, &VIEWPORT);
+
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"100\""));
+    assert!(svg.trim_end().ends_with("</svg>"));
+    assert_eq!(svg.matches("<line").count(), 2);
+
+    let path = svg.lines().find(|line| line.starts_with("<path")).unwrap();
+    assert_eq!(path.matches('M').count(), 2, "{path}");
+}
+
+#[test]
+fn test_svg_equation() {
+    let CompiledModule {
+        instructions,
+        plot_descs,
+        ..
+    } = compile_source("plot(x^2 + y^2 < 1);");
+    let svg = render_svg(&instructions, &plot_descs, &VIEWPORT);
+
+    // The unit circle is about 25 pixels across, shaded inside with its boundary in white
+    let rects: Vec<_> = svg
+        .lines()
+        .filter(|line| line.starts_with("<rect x="))
+        .collect();
+    for rect in [
+        r##"<rect x="37" y="50" width="1" height="1" fill="#ffffff"/>"##,
+        r##"<rect x="38" y="50" width="24" height="1" fill="#ffffff" fill-opacity="0.4"/>"##,
+        r##"<rect x="62" y="50" width="1" height="1" fill="#ffffff"/>"##,
+    ] {
+        assert!(rects.contains(&rect), "{rects:?}");
+    }
+    let rows = rects
+        .iter()
+        .filter(|rect| rect.contains("fill-opacity"))
+        .count();
+    assert!((22..=26).contains(&rows), "{rows}");
+}
+
+#[test]
+fn test_svg_curves() {
+    let CompiledModule {
+        instructions,
+        plot_descs,
+        ..
+    } = compile_source("plot_polar(θ -> 1);\nplot_inverse(s -> 1 / s);");
+    let svg = render_svg(&instructions, &plot_descs, &VIEWPORT);
+    let paths: Vec<_> = svg
+        .lines()
+        .filter(|line| line.starts_with("<path"))
+        .collect();
+    assert_eq!(paths.len(), 2);
+
+    // The circle is one path through all samples
+    assert_eq!(paths[0].matches('M').count(), 1, "{}", paths[0]);
+    assert_eq!(
+        paths[0].matches('L').count(),
+        mth_common::CURVE_SEGMENTS as usize
+    );
+    assert!(paths[0].contains("M62.50,50.00 "), "{}", paths[0]);
+
+    // Split at the pole like function graphs
+    assert_eq!(paths[1].matches('M').count(), 2, "{}", paths[1]);
+}
+
+#[test]
+fn test_render_palette_cycles_across_plots() {
+    let image = render_src("plot(1, -1);");
+    assert_eq!(image.pixel(10, 37), WHITE);
+    assert_eq!(image.pixel(10, 63), [240, 96, 96, 255]);
+}
+
+#[test]
+fn test_render_color_and_width() {
+    let image = render_src("plot(1, color = \"#0080ff\", width = 3);");
+    for py in 35..=39 {
+        assert_eq!(image.pixel(10, py), [0, 128, 255, 255], "row {py}");
+    }
+    assert_eq!(image.pixel(10, 32), BLACK);
+    assert_eq!(image.pixel(10, 42), BLACK);
+}
+
+#[test]
+fn test_render_dashes() {
+    // Dashes of 4 pixels with gaps of 4 pixels along y = 1
+    let image = render_src("plot(1, dash = [4, 4]);");
+    let row: Vec<bool> = (0..100).map(|px| image.pixel(px, 37) == WHITE).collect();
+    let dashes = row.windows(2).filter(|w| !w[0] && w[1]).count();
+    assert!((11..=13).contains(&dashes), "{row:?}");
+    assert!(row.iter().filter(|on| **on).count() >= 45);
+}
+
+#[test]
+fn test_render_fill() {
+    // The area between y = 1 and the x axis
+    let image = render_src("plot(1, fill = 0.4);");
+    assert_eq!(image.pixel(10, 45), REGION);
+    assert_eq!(image.pixel(10, 30), BLACK);
+    assert_eq!(image.pixel(10, 60), BLACK);
+
+    // The inside of a circle
+    let image = render_src("plot_polar(θ -> 2, fill = 0.4);");
+    assert_eq!(image.pixel(60, 40), REGION);
+    assert_eq!(image.pixel(90, 90), BLACK);
+    assert_eq!(image.pixel(75, 50), WHITE);
+}
+
+#[test]
+fn test_svg_style() {
+    let CompiledModule {
+        instructions,
+        plot_descs,
+        ..
+    } = compile_source("plot(x, color = \"red\", width = 2, dash = [4, 2], fill = 0.3);");
+    let svg = render_svg(&instructions, &plot_descs, &VIEWPORT);
+    let paths: Vec<_> = svg
+        .lines()
+        .filter(|line| line.starts_with("<path"))
+        .collect();
+    assert_eq!(paths.len(), 2, "{svg}");
+
+    // The area below the line
+    assert!(
+        paths[0].ends_with(
+            r##" Z" fill="#f06060" fill-opacity="0.3" fill-rule="evenodd" stroke="none"/>"##
+        ),
+        "{}",
+        paths[0]
+    );
+    assert!(
+        paths[1].ends_with(
+            r##"fill="none" stroke="#f06060" stroke-width="4" stroke-dasharray="4 2"/>"##
+        ),
+        "{}",
+        paths[1]
+    );
+}
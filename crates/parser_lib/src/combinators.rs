@@ -50,10 +50,7 @@ pub fn choice_f<'s, T>(parsers: Vec<BoxedParser<'s, T>>) -> impl Parser<'s, T> {
             }
         }
 
-        Err(last_err.unwrap_or(PError {
-            msg: "no matching parser".into(),
-            ctx: src.ctx,
-        }))
+        Err(last_err.unwrap_or(PError::new("no matching parser", src.ctx)))
     }
 }
 
@@ -71,18 +68,13 @@ macro_rules! choice {
 pub fn many0<'s, T>(p: impl Parser<'s, T>) -> impl Parser<'s, Vec<T>> {
     move |mut src| {
         let mut out = Vec::new();
-        loop {
-            match p(src.clone()) {
-                Ok((next_src, v)) => {
-                    // Prevent infinite loops: ensure progress
-                    if next_src.remainder.len() == src.remainder.len() {
-                        panic!("src not advanced");
-                    }
-                    src = next_src;
-                    out.push(v);
-                }
-                Err(_) => break,
+        while let Ok((next_src, v)) = p(src.clone()) {
+            // Prevent infinite loops: ensure progress
+            if next_src.remainder.len() == src.remainder.len() {
+                panic!("src not advanced");
             }
+            src = next_src;
+            out.push(v);
         }
         Ok((src, out))
     }
@@ -91,24 +83,17 @@ pub fn many0<'s, T>(p: impl Parser<'s, T>) -> impl Parser<'s, Vec<T>> {
 pub fn some<'s, T>(p: impl Parser<'s, T>) -> impl Parser<'s, Vec<T>> {
     move |src| {
         // Try the first element
-        let (mut src, first) = p(src.clone()).map_err(|_| PError {
-            msg: "Expected at least one element".into(),
-            ctx: src.ctx,
-        })?;
+        let (mut src, first) =
+            p(src.clone()).map_err(|_| PError::new("Expected at least one element", src.ctx))?;
 
         // Inline the rest of many0 instead of calling it
         let mut out = vec![first];
-        loop {
-            match p(src.clone()) {
-                Ok((next_src, v)) => {
-                    if next_src.remainder.len() == src.remainder.len() {
-                        break;
-                    }
-                    src = next_src;
-                    out.push(v);
-                }
-                Err(_) => break,
+        while let Ok((next_src, v)) = p(src.clone()) {
+            if next_src.remainder.len() == src.remainder.len() {
+                break;
             }
+            src = next_src;
+            out.push(v);
         }
 
         Ok((src, out))
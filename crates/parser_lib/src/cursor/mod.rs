@@ -21,9 +21,9 @@ impl<'s> Cursor<'s> {
         let first_char = chars.next();
         Self {
             ctx: FileContext::default(),
-            src: src,
+            src,
             remainder: src,
-            chars: chars,
+            chars,
             cur_char: first_char,
         }
     }
@@ -39,6 +39,7 @@ impl<'s> Iterator for Cursor<'s> {
     type Item = char;
 
     fn next(&mut self) -> Option<Self::Item> {
+        let prev_len = self.cur_char.map_or(0, char::len_utf8);
         self.cur_char = self.chars.next();
         self.ctx.col += 1;
         if let Some(ch) = self.cur_char {
@@ -46,10 +47,12 @@ impl<'s> Iterator for Cursor<'s> {
                 self.ctx.line += 1;
                 self.ctx.col = 0;
             }
-            self.remainder = &self.remainder[1..];
+            self.remainder = &self.remainder[prev_len..];
+            self.ctx.offset = self.src.len() - self.remainder.len();
             Some(ch)
         } else {
             self.remainder = "";
+            self.ctx.offset = self.src.len();
             self.cur_char = None;
             None
         }
@@ -87,3 +87,18 @@ fn cursor_empty_input() {
     assert_eq!(c.ctx.line, 1);
     assert_eq!(c.ctx.col, 2);
 }
+
+#[test]
+fn cursor_byte_offset() {
+    let mut c = make_cursor("aπb");
+    assert_eq!(c.ctx.offset, 0);
+    c.next();
+    assert_eq!(c.cur_char, Some('π'));
+    assert_eq!(c.ctx.offset, 1);
+    c.next();
+    assert_eq!(c.cur_char, Some('b'));
+    assert_eq!(c.remainder, "b");
+    assert_eq!(c.ctx.offset, 3);
+    c.next();
+    assert_eq!(c.ctx.offset, 4);
+}
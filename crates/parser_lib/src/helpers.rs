@@ -8,7 +8,7 @@ use crate::{
 macro_rules! parse {
     ($parser:expr, $msg:expr, $src:expr) => {
         $parser($src).map_err(|mut e| {
-            e.msg = format!("{}: {}", $msg, e.msg);
+            e.context.push($msg.to_string());
             e
         })
     };
@@ -36,7 +36,7 @@ macro_rules! pmatch {
         match $p($src.clone()) {
             Ok((src, $pattern)) => Ok((src, $action)),
             Err(mut e) => {
-                e.msg = $err.to_string() + &e.msg;
+                e.context.push($err.to_string());
                 Err(e)
             }
         }
@@ -57,25 +57,92 @@ macro_rules! pmatch {
         $(
             let res = match res {
                 Ok(v) => Ok(v),
-                Err(_) => pmatch!($src; err = $err; $p, $( $pat => $act )?),
+                Err(prev) => match pmatch!($src; err = $err; $p, $( $pat => $act )?) {
+                    Ok(v) => Ok(v),
+                    // Report the arm that got furthest, it's most likely the one that was meant
+                    Err(e) if e.ctx.offset >= prev.ctx.offset => Err(e),
+                    Err(_) => Err(prev),
+                },
             };
         )+
         res
     }};
 }
 
+/// Skip whitespace and comments.
+///
+/// Comments are line comments `| ...`, doc comments `|| ...` (see [`doc_comments`]) and block
+/// comments `|* ... *|`, which can be nested. Fails only on an unterminated block comment
 pub fn whitespace<'s>(mut src: Cursor<'s>) -> PResult<'s, ()> {
-    while let Some(ch) = src.cur_char
-        && ch.is_whitespace()
-    {
-        src.next();
+    loop {
+        match src.cur_char {
+            Some(ch) if ch.is_whitespace() => {
+                src.next();
+            }
+            Some('|') => (src, ()) = comment(src)?,
+            _ => return Ok((src, ())),
+        }
+    }
+}
+
+/// Skip one comment starting at the current `|`
+fn comment<'s>(mut src: Cursor<'s>) -> PResult<'s, ()> {
+    if !src.remainder.starts_with("|*") {
+        // Line comment, up to the end of the line
+        while let Some(ch) = src.cur_char
+            && ch != '\n'
+        {
+            src.next();
+        }
+        return Ok((src, ()));
+    }
+
+    let start = src.ctx.clone();
+    src.advance(2);
+    let mut depth = 1;
+    while depth > 0 {
+        if src.remainder.starts_with("|*") {
+            depth += 1;
+            src.advance(2);
+        } else if src.remainder.starts_with("*|") {
+            depth -= 1;
+            src.advance(2);
+        } else if src.cur_char.is_none() {
+            return Err(PError::new(
+                "Unterminated block comment, expected '*|'",
+                start,
+            ));
+        } else {
+            src.next();
+        }
     }
     Ok((src, ()))
 }
 
+/// Skip whitespace and comments like [`whitespace`], and collect the lines of the doc comments
+/// (`|| ...`) on the way, without the `||` and a single space after it
+pub fn doc_comments<'s>(mut src: Cursor<'s>) -> PResult<'s, Vec<&'s str>> {
+    let mut lines = Vec::new();
+    loop {
+        match src.cur_char {
+            Some(ch) if ch.is_whitespace() => {
+                src.next();
+            }
+            Some('|') if src.remainder.starts_with("||") => {
+                let line = src.remainder.lines().next().unwrap_or_default();
+                let text = &line[2..];
+                lines.push(text.strip_prefix(' ').unwrap_or(text).trim_end());
+                src.advance(line.chars().count());
+            }
+            Some('|') => (src, ()) = comment(src)?,
+            _ => return Ok((src, lines)),
+        }
+    }
+}
+
 pub fn tok<'s, O>(f: impl Parser<'s, O>) -> impl Parser<'s, O> {
     move |src| {
-        let (src, ()) = whitespace(src).expect("Always succeeds");
+        let (src, ()) = whitespace(src)?;
         f(src)
     }
 }
@@ -86,22 +153,23 @@ pub fn digit<'s>(radix: u32) -> impl Parser<'s, char> {
 
 pub fn ident<'s>(mut src: Cursor<'s>) -> PResult<'s, &'s str> {
     let Some(ch) = src.cur_char else {
-        return Err(PError {
-            msg: "Unexpected EOF, expecting ident".to_string(),
-            ctx: src.ctx,
-        });
+        return Err(PError::new("Unexpected EOF, expecting ident", src.ctx));
     };
     if !ch.is_alphabetic() {
-        return Err(PError {
-            msg: "Ident has to start with an alphabetic character".to_string(),
-            ctx: src.ctx,
-        });
+        return Err(PError::new(
+            "Ident has to start with an alphabetic character",
+            src.ctx,
+        ));
     }
 
     let remainder = src.remainder;
-    let end = src
-        .position(|c| !(c.is_alphanumeric() || c == '_'))
-        .unwrap_or(remainder.len() - 1);
+    let start = src.ctx.offset;
+    while let Some(ch) = src.cur_char
+        && (ch.is_alphanumeric() || ch == '_')
+    {
+        src.next();
+    }
 
-    Ok((src, &remainder[..end + 1]))
+    let len = src.ctx.offset - start;
+    Ok((src, &remainder[..len]))
 }
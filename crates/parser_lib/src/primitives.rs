@@ -14,14 +14,8 @@ pub fn satisfy<'s>(pred: impl Fn(char) -> bool) -> impl Parser<'s, char> {
                 src.next(); // advances in place
                 Ok((src, ch))
             }
-            Some(_) => Err(PError {
-                msg: "[satisfy] Predicate failed".to_string(),
-                ctx: src.ctx,
-            }),
-            None => Err(PError {
-                msg: "[satisfy] Unexpected EOF".to_string(),
-                ctx: src.ctx,
-            }),
+            Some(_) => Err(PError::new("[satisfy] Predicate failed", src.ctx)),
+            None => Err(PError::new("[satisfy] Unexpected EOF", src.ctx)),
         }
     }
 }
@@ -39,14 +33,14 @@ pub fn chr<'s>(expected: char) -> impl Parser<'s, char> {
             src.next();
             Ok((src, expected))
         }
-        Some(other) => Err(PError {
-            msg: format!("[chr] Expected '{expected}', found '{other}'"),
-            ctx: src.ctx,
-        }),
-        None => Err(PError {
-            msg: format!("[chr] Unexpected EOF, expected '{expected}'"),
-            ctx: src.ctx,
-        }),
+        Some(other) => Err(PError::new(
+            format!("[chr] Expected '{expected}', found '{other}'"),
+            src.ctx,
+        )),
+        None => Err(PError::new(
+            format!("[chr] Unexpected EOF, expected '{expected}'"),
+            src.ctx,
+        )),
     }
 }
 
@@ -54,11 +48,10 @@ pub fn chr_take_while<'s>(pred: impl Fn(&char) -> bool) -> impl Parser<'s, &'s s
     move |mut src| {
         let len = src
             .remainder
-            .chars()
-            .position(|ref ch| !pred(ch))
-            .unwrap_or_else(|| src.remainder.len());
+            .find(|ref ch| !pred(ch))
+            .unwrap_or(src.remainder.len());
         let slice = &src.remainder[..len];
-        src.advance(len);
+        src.advance(slice.chars().count());
         Ok((src, slice))
     }
 }
@@ -66,22 +59,23 @@ pub fn chr_take_while<'s>(pred: impl Fn(&char) -> bool) -> impl Parser<'s, &'s s
 pub fn keyword<'s, 'exp>(expected: &'exp str) -> impl Parser<'s, &'s str> + 'exp {
     move |mut src: Cursor<'s>| {
         if src.remainder.len() < expected.len() {
-            return Err(PError {
-                msg: format!("[keyword] Unexpected EOF. Expected: '{expected}'"),
-                ctx: src.ctx,
-            });
+            return Err(PError::new(
+                format!("[keyword] Unexpected EOF. Expected: '{expected}'"),
+                src.ctx,
+            ));
         }
 
-        let slice = &src.remainder[..expected.len()];
-
-        if slice != expected {
-            return Err(PError {
-                msg: format!("[keyword] Expected '{expected}', found '{slice}'"),
-                ctx: src.ctx,
-            });
+        let n_chars = expected.chars().count();
+        if !src.remainder.starts_with(expected) {
+            let found: String = src.remainder.chars().take(n_chars).collect();
+            return Err(PError::new(
+                format!("[keyword] Expected '{expected}', found '{found}'"),
+                src.ctx,
+            ));
         }
 
-        src.advance(expected.len());
+        let slice = &src.remainder[..expected.len()];
+        src.advance(n_chars);
         Ok((src, slice))
     }
 }
@@ -18,7 +18,7 @@ fn test_satisfy() {
 #[test]
 fn test_satisfy_eof() {
     let src = Cursor::new("");
-    let err = satisfy(|c| true)(src).unwrap_err();
+    let err = satisfy(|_| true)(src).unwrap_err();
     assert!(err.msg.contains("Unexpected EOF"));
 }
 
@@ -160,10 +160,18 @@ fn test_between_success() {
 #[test]
 fn test_ident_with_underscore() {
     let src = Cursor::new("hello_world");
-    let (src, name) = ident(src).unwrap();
+    let (_, name) = ident(src).unwrap();
     assert_eq!(name, "hello_world");
 }
 
+#[test]
+fn test_ident_multibyte() {
+    let src = Cursor::new("θ_ß2+x");
+    let (src, name) = ident(src).unwrap();
+    assert_eq!(name, "θ_ß2");
+    assert_eq!(src.remainder, "+x");
+}
+
 #[test]
 fn test_pmap() {
     let src = Cursor::new("a");
@@ -193,7 +201,8 @@ fn test_okparser_does_not_consume() {
 fn test_parse_macro_error_wrapping() {
     let src = Cursor::new("x");
     let err = parse!(chr('y'), "Expected y", src).unwrap_err();
-    assert!(err.msg.contains("Expected y"));
+    assert_eq!(err.msg, "[chr] Expected 'y', found 'x'");
+    assert_eq!(err.context, vec!["Expected y".to_string()]);
 }
 
 #[test]
@@ -420,7 +429,7 @@ mod combinator_tests {
     fn test_delimited0_empty() {
         let src = Cursor::new("");
         let p = delimited0(chr('a'), chr(','));
-        let (src, results) = p(src).unwrap();
+        let (_, results) = p(src).unwrap();
         assert!(results.is_empty());
     }
 
@@ -588,7 +597,7 @@ mod combinator_tests {
     fn test_pmap_with_complex_transform() {
         let src = Cursor::new("hello");
         let p = pmap(|s: &str| s.to_uppercase(), ident);
-        let (src, result) = p(src).unwrap();
+        let (_, result) = p(src).unwrap();
         assert_eq!(result, "HELLO");
     }
 
@@ -596,7 +605,7 @@ mod combinator_tests {
     fn test_choice_macro_single_parser() {
         let src = Cursor::new("a");
         let p = choice!(chr('a'));
-        let (src, ch) = p(src).unwrap();
+        let (_, ch) = p(src).unwrap();
         assert_eq!(ch, 'a');
     }
 
@@ -604,7 +613,7 @@ mod combinator_tests {
     fn test_choice_macro_many_parsers() {
         let src = Cursor::new("e");
         let p = choice!(chr('a'), chr('b'), chr('c'), chr('d'), chr('e'));
-        let (src, ch) = p(src).unwrap();
+        let (_, ch) = p(src).unwrap();
         assert_eq!(ch, 'e');
     }
 }
@@ -653,6 +662,51 @@ mod whitespace_tests {
         let (src, _) = whitespace(src).unwrap();
         assert_eq!(src.cur_char, None);
     }
+    #[test]
+    fn test_whitespace_line_comment() {
+        let src = Cursor::new("  | comment\n| another\nabc");
+        let (src, _) = whitespace(src).unwrap();
+        assert_eq!(src.remainder, "abc");
+    }
+
+    #[test]
+    fn test_whitespace_comment_at_eof() {
+        let src = Cursor::new("| comment");
+        let (src, _) = whitespace(src).unwrap();
+        assert_eq!(src.cur_char, None);
+    }
+
+    #[test]
+    fn test_whitespace_block_comment() {
+        let src = Cursor::new("|* a\nb *| abc");
+        let (src, _) = whitespace(src).unwrap();
+        assert_eq!(src.remainder, "abc");
+    }
+
+    #[test]
+    fn test_whitespace_nested_block_comment() {
+        let src = Cursor::new("|* outer |* inner *| still outer *|abc");
+        let (src, _) = whitespace(src).unwrap();
+        assert_eq!(src.remainder, "abc");
+    }
+
+    #[test]
+    fn test_whitespace_unterminated_block_comment() {
+        let src = Cursor::new("x |* |* *| abc");
+        let (src, _) = chr('x')(src).unwrap();
+        let err = whitespace(src).unwrap_err();
+        assert!(err.msg.contains("Unterminated block comment"));
+        assert_eq!(err.ctx.offset, 2);
+    }
+
+    #[test]
+    fn test_doc_comments() {
+        let src =
+            Cursor::new("|| First line\n  ||second\n| not doc\n|*block*|\n||   indented  \nabc");
+        let (src, lines) = doc_comments(src).unwrap();
+        assert_eq!(lines, vec!["First line", "second", "  indented"]);
+        assert_eq!(src.remainder, "abc");
+    }
 }
 
 mod tok_tests {
@@ -682,6 +736,14 @@ mod tok_tests {
         assert_eq!(src.cur_char, None);
     }
 
+    #[test]
+    fn test_tok_skips_comments() {
+        let src = Cursor::new("|* block *| | line\n a");
+        let (src, ch) = tok(chr('a'))(src).unwrap();
+        assert_eq!(ch, 'a');
+        assert_eq!(src.cur_char, None);
+    }
+
     #[test]
     fn test_tok_fails_without_match() {
         let src = Cursor::new("   xbc");
@@ -5,6 +5,8 @@ pub struct FileContext {
     pub filename: Option<String>,
     pub line: usize,
     pub col: usize,
+    /// Byte offset into the source
+    pub offset: usize,
 }
 
 impl std::fmt::Display for FileContext {
@@ -13,6 +15,7 @@ impl std::fmt::Display for FileContext {
             filename,
             line,
             col,
+            ..
         } = self;
         if let Some(fname) = filename {
             write!(f, "{fname}:{line}:{col}")
@@ -28,20 +31,40 @@ impl Default for FileContext {
             filename: None,
             line: 1,
             col: 1,
+            offset: 0,
         }
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct PError {
+    /// What the innermost parser expected
     pub msg: String,
+
+    /// What the enclosing parsers were trying to match, innermost first. Added by `parse!` and
+    /// `pmatch!` as the error propagates
+    pub context: Vec<String>,
     pub ctx: FileContext,
 }
 
+impl PError {
+    pub fn new(msg: impl Into<String>, ctx: FileContext) -> Self {
+        Self {
+            msg: msg.into(),
+            context: Vec::new(),
+            ctx,
+        }
+    }
+}
+
 impl std::fmt::Display for PError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let PError { msg, ctx } = self;
-        write!(f, "Error: {msg}\n   {ctx}")
+        let PError { msg, context, ctx } = self;
+        write!(f, "Error: {msg}")?;
+        for frame in context {
+            write!(f, "\n   in: {frame}")?;
+        }
+        write!(f, "\n   {ctx}")
     }
 }
 